@@ -0,0 +1,42 @@
+#!/usr/bin/env python3
+"""Pretends to be a Vantage Pro2 console on a pseudo-terminal.
+
+Prints the pty path to use as `port` in the davis source config, then answers
+wake-ups and LOOP/LPS requests with the packets synthesized in fixtures/davis/.
+"""
+import os
+import sys
+import tty
+
+FIXTURES = os.path.join(os.path.dirname(__file__), "..", "fixtures", "davis")
+
+
+def load(name):
+    with open(os.path.join(FIXTURES, name), "rb") as f:
+        return f.read()
+
+
+def main():
+    loop1, loop2 = load("loop1.bin"), load("loop2.bin")
+    master, slave = os.openpty()
+    tty.setraw(slave)
+    print(f"console listening on {os.ttyname(slave)}", flush=True)
+
+    buffer = b""
+    while True:
+        buffer += os.read(master, 64)
+        while b"\n" in buffer:
+            line, buffer = buffer.split(b"\n", 1)
+            command = line.strip()
+            if not command:
+                os.write(master, b"\n\r")
+            elif command.startswith(b"LOOP"):
+                os.write(master, b"\x06" + loop1)
+            elif command.startswith(b"LPS"):
+                os.write(master, b"\x06" + loop2)
+            else:
+                print(f"ignoring {command!r}", file=sys.stderr)
+
+
+if __name__ == "__main__":
+    main()
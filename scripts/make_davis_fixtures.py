@@ -0,0 +1,73 @@
+#!/usr/bin/env python3
+"""Synthesizes LOOP/LOOP2 packets (with valid CRC) under fixtures/davis/.
+
+The packets are laid out from the Vantage Serial Protocol spec, not captured from a
+console, so they only show the decoder agrees with the spec.
+"""
+import os
+import struct
+
+
+def crc16_ccitt(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 8
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x1021) if crc & 0x8000 else crc << 1
+            crc &= 0xFFFF
+    return crc
+
+
+def finish(packet):
+    packet[95:97] = b"\n\r"
+    crc = crc16_ccitt(packet[:97])
+    packet[97:99] = struct.pack(">H", crc)
+    return bytes(packet)
+
+
+def loop1():
+    p = bytearray(99)
+    p[0:3] = b"LOO"
+    p[3] = 0            # bar trend: steady
+    p[4] = 0            # packet type LOOP
+    struct.pack_into("<H", p, 7, 30012)    # barometer, inHg/1000
+    struct.pack_into("<h", p, 9, 712)      # inside temp, F/10
+    p[11] = 40                             # inside humidity
+    struct.pack_into("<h", p, 12, 645)     # outside temp, F/10 (18.1 C)
+    p[14] = 7                              # wind speed, mph
+    p[15] = 6                              # 10-min avg wind, mph
+    struct.pack_into("<H", p, 16, 225)     # wind direction, degrees
+    p[33] = 68                             # outside humidity
+    struct.pack_into("<H", p, 41, 12)      # rain rate, clicks/h
+    p[43] = 35                             # UV index * 10
+    struct.pack_into("<H", p, 44, 612)     # solar radiation, W/m2
+    return finish(p)
+
+
+def loop2():
+    p = bytearray(99)
+    p[0:3] = b"LOO"
+    p[3] = 0
+    p[4] = 1            # packet type LOOP2
+    struct.pack_into("<H", p, 7, 29874)
+    struct.pack_into("<h", p, 9, 701)
+    p[11] = 42
+    struct.pack_into("<h", p, 12, 388)     # 3.8 C
+    p[14] = 14
+    struct.pack_into("<H", p, 16, 360)     # north
+    struct.pack_into("<H", p, 20, 118)     # 2-min avg wind, 0.1 mph
+    p[33] = 91
+    struct.pack_into("<H", p, 41, 0)
+    p[43] = 0xFF                           # UV sensor not connected
+    struct.pack_into("<H", p, 44, 0x7FFF)  # solar sensor not connected
+    return finish(p)
+
+
+if __name__ == "__main__":
+    out = os.path.join(os.path.dirname(__file__), "..", "fixtures", "davis")
+    os.makedirs(out, exist_ok=True)
+    for name, packet in (("loop1.bin", loop1()), ("loop2.bin", loop2())):
+        with open(os.path.join(out, name), "wb") as f:
+            f.write(packet)
+        assert crc16_ccitt(packet) == 0
+        print(f"wrote {name}")
@@ -0,0 +1,69 @@
+#!/usr/bin/env python3
+"""Minimal SMTP server that prints every message it accepts.
+
+Speaks just enough plain SMTP (no TLS, no auth) for the email notification
+channel with `"security": "none"`.
+
+    scripts/mock_smtp_server.py [--port 2525]
+"""
+import argparse
+import socketserver
+
+
+class SmtpHandler(socketserver.StreamRequestHandler):
+    def reply(self, line):
+        self.wfile.write(line.encode() + b"\r\n")
+        self.wfile.flush()
+
+    def handle(self):
+        self.reply("220 localhost mock SMTP")
+        sender, recipients = None, []
+        while True:
+            line = self.rfile.readline()
+            if not line:
+                return
+            command = line.decode(errors="replace").strip()
+            verb = command.split(" ", 1)[0].upper()
+            if verb in ("EHLO", "HELO"):
+                self.reply("250 localhost")
+            elif verb == "MAIL":
+                sender, recipients = command[10:], []
+                self.reply("250 OK")
+            elif verb == "RCPT":
+                recipients.append(command[8:])
+                self.reply("250 OK")
+            elif verb == "DATA":
+                self.reply("354 End data with <CR><LF>.<CR><LF>")
+                lines = []
+                while True:
+                    data = self.rfile.readline().decode(errors="replace")
+                    if data in (".\r\n", ".\n", ""):
+                        break
+                    lines.append(data.rstrip("\r\n"))
+                print(f"--- mail from {sender} to {', '.join(recipients)}", flush=True)
+                print("\n".join(lines), flush=True)
+                self.reply("250 OK queued")
+            elif verb == "RSET":
+                sender, recipients = None, []
+                self.reply("250 OK")
+            elif verb == "NOOP":
+                self.reply("250 OK")
+            elif verb == "QUIT":
+                self.reply("221 Bye")
+                return
+            else:
+                self.reply("502 Command not implemented")
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--port", type=int, default=2525)
+    args = parser.parse_args()
+    socketserver.ThreadingTCPServer.allow_reuse_address = True
+    with socketserver.ThreadingTCPServer(("127.0.0.1", args.port), SmtpHandler) as server:
+        print(f"mock SMTP server on 127.0.0.1:{args.port}", flush=True)
+        server.serve_forever()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,16 @@
+#!/usr/bin/env sh
+# Replays captured gateway uploads against the ecowitt ingest source.
+#   scripts/post_gateway_payload.sh [base-url]
+# Ecowitt gateways POST a form body; Ambient consoles GET with a query string.
+set -eu
+
+BASE_URL="${1:-http://localhost:8081/data/report}"
+DIR="$(dirname "$0")/../fixtures"
+
+echo "Ecowitt GW2000 (POST):"
+curl -sS -o /dev/null -w "%{http_code}\n" -X POST \
+    -H "Content-Type: application/x-www-form-urlencoded" \
+    --data-binary "@$DIR/ecowitt/gw2000.form" "$BASE_URL"
+
+echo "Ambient WS-2902 (GET):"
+curl -sS -o /dev/null -w "%{http_code}\n" "$BASE_URL/?$(cat "$DIR/ambient/ws2902.query")"
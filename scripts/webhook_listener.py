@@ -0,0 +1,46 @@
+#!/usr/bin/env python3
+"""Local stand-in for an alert webhook receiver.
+
+Prints every JSON body POSTed to it. `--fail N` answers the first N requests
+with HTTP 503 to exercise the notifier's retries.
+
+    scripts/webhook_listener.py [--port 9000] [--fail 0]
+"""
+import argparse
+import json
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--port", type=int, default=9000)
+    parser.add_argument("--fail", type=int, default=0)
+    args = parser.parse_args()
+    remaining_failures = [args.fail]
+
+    class Handler(BaseHTTPRequestHandler):
+        def do_POST(self):
+            body = self.rfile.read(int(self.headers.get("Content-Length", 0)))
+            if remaining_failures[0] > 0:
+                remaining_failures[0] -= 1
+                print(f"rejected {self.path} (503)", flush=True)
+                self.send_response(503)
+                self.end_headers()
+                return
+            try:
+                payload = json.dumps(json.loads(body), indent=2)
+            except ValueError:
+                payload = body.decode(errors="replace")
+            print(f"POST {self.path}\n{payload}", flush=True)
+            self.send_response(204)
+            self.end_headers()
+
+        def log_message(self, *_):
+            pass
+
+    print(f"webhook listener on http://127.0.0.1:{args.port}/", flush=True)
+    HTTPServer(("127.0.0.1", args.port), Handler).serve_forever()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,518 @@
+use crate::data::{Metric, WeatherData};
+use crate::rain::RainTotals;
+use crate::units::{self, Dimension, UnitPreferences};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub mod notify;
+
+const HISTORY_CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Derived {
+    DewPoint,
+    FeelsLike,
+    RainLastHour,
+    RainToday,
+    RainLast24h,
+}
+
+/// What a rule watches: a raw reading field or a value derived from readings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Quantity {
+    Metric(Metric),
+    Derived(Derived),
+}
+
+impl Quantity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Quantity::Metric(Metric::Temperature) => "Temperature",
+            Quantity::Metric(Metric::Humidity) => "Humidity",
+            Quantity::Metric(Metric::Pressure) => "Pressure",
+            Quantity::Metric(Metric::WindSpeed) => "Wind speed",
+            Quantity::Metric(Metric::WindDirection) => "Wind direction",
+            Quantity::Metric(Metric::RainRate) => "Rain rate",
+            Quantity::Metric(Metric::UvIndex) => "UV index",
+            Quantity::Metric(Metric::SolarRadiation) => "Solar radiation",
+            Quantity::Derived(Derived::DewPoint) => "Dew point",
+            Quantity::Derived(Derived::FeelsLike) => "Feels like",
+            Quantity::Derived(Derived::RainLastHour) => "Rain last hour",
+            Quantity::Derived(Derived::RainToday) => "Rain today",
+            Quantity::Derived(Derived::RainLast24h) => "Rain last 24h",
+        }
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        match self {
+            Quantity::Metric(metric) => metric.dimension(),
+            Quantity::Derived(Derived::DewPoint | Derived::FeelsLike) => Dimension::Temperature,
+            Quantity::Derived(_) => Dimension::Rain,
+        }
+    }
+
+    fn uses_rain(&self) -> bool {
+        matches!(self, Quantity::Derived(Derived::RainLastHour | Derived::RainToday | Derived::RainLast24h))
+    }
+
+    fn value(&self, data: &WeatherData, rain: Option<&RainTotals>) -> f32 {
+        match self {
+            Quantity::Metric(metric) => metric.value(data),
+            Quantity::Derived(Derived::DewPoint) => data.dew_point(),
+            Quantity::Derived(Derived::FeelsLike) => data.feels_like(),
+            Quantity::Derived(Derived::RainLastHour) => rain.map_or(0.0, |rain| rain.last_hour_mm),
+            Quantity::Derived(Derived::RainToday) => rain.map_or(0.0, |rain| rain.today_mm),
+            Quantity::Derived(Derived::RainLast24h) => rain.map_or(0.0, |rain| rain.last_24h_mm),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    Above { threshold: f32 },
+    Below { threshold: f32 },
+    // Change over the trailing window, e.g. pressure falling 3 hPa within 3 hours.
+    Rises { by: f32, within_secs: i64 },
+    Falls { by: f32, within_secs: i64 },
+}
+
+/// A named condition on a quantity, e.g. temperature below 0 °C for 10 minutes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub quantity: Quantity,
+    pub condition: Condition,
+    /// How long the condition has to hold before the alert fires.
+    #[serde(default)]
+    pub for_secs: i64,
+    /// How far back past the threshold the value has to come before the alert clears.
+    #[serde(default)]
+    pub hysteresis: f32,
+    /// Unit of the threshold, change and hysteresis, e.g. `°F` or `mph`; SI if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+impl AlertRule {
+    /// The rule in words, with values in the preferred units. Expects a rule in SI units.
+    pub fn describe(&self, units: &UnitPreferences) -> String {
+        let dimension = self.quantity.dimension();
+        let change = |by: f32| units.format_value(dimension, units.convert_delta(dimension, by));
+        let condition = match &self.condition {
+            Condition::Above { threshold } => format!("above {}", units.format(dimension, *threshold)),
+            Condition::Below { threshold } => format!("below {}", units.format(dimension, *threshold)),
+            Condition::Rises { by, within_secs } => format!("rises {} within {}", change(*by), format_secs(*within_secs)),
+            Condition::Falls { by, within_secs } => format!("falls {} within {}", change(*by), format_secs(*within_secs)),
+        };
+        if self.for_secs > 0 {
+            format!("{} {} for {}", self.quantity.label(), condition, format_secs(self.for_secs))
+        } else {
+            format!("{} {}", self.quantity.label(), condition)
+        }
+    }
+
+    /// Errors start with the offending key, e.g. `for_secs: must not be negative`.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            bail!("name: must not be empty");
+        }
+        if self.for_secs < 0 {
+            bail!("for_secs: must not be negative");
+        }
+        if self.hysteresis < 0.0 {
+            bail!("hysteresis: must not be negative");
+        }
+        if let Some(unit) = &self.unit {
+            if units::to_si(self.quantity.dimension(), unit, 0.0, false).is_none() {
+                bail!("unit: '{}' is not a unit for {}", unit, self.quantity.label().to_lowercase());
+            }
+        }
+        if let Condition::Rises { by, within_secs } | Condition::Falls { by, within_secs } = &self.condition {
+            if *by <= 0.0 {
+                bail!("condition.by: must be positive");
+            }
+            if *within_secs <= 0 {
+                bail!("condition.within_secs: must be positive");
+            }
+        }
+        Ok(())
+    }
+
+    // The same rule with its values converted from `unit` to SI.
+    fn in_si(&self) -> Self {
+        let Some(unit) = &self.unit else {
+            return self.clone();
+        };
+        let dimension = self.quantity.dimension();
+        let convert = |value: f32, delta: bool| units::to_si(dimension, unit, value, delta).unwrap_or(value);
+        let condition = match self.condition {
+            Condition::Above { threshold } => Condition::Above { threshold: convert(threshold, false) },
+            Condition::Below { threshold } => Condition::Below { threshold: convert(threshold, false) },
+            Condition::Rises { by, within_secs } => Condition::Rises { by: convert(by, true), within_secs },
+            Condition::Falls { by, within_secs } => Condition::Falls { by: convert(by, true), within_secs },
+        };
+        Self {
+            condition,
+            hysteresis: convert(self.hysteresis, true),
+            unit: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Used when the config file has no `alerts`: frost, gale, a fast pressure drop and high UV.
+pub fn default_rules() -> Vec<AlertRule> {
+    vec![
+        AlertRule {
+            name: "frost".to_string(),
+            quantity: Quantity::Metric(Metric::Temperature),
+            condition: Condition::Below { threshold: 0.0 },
+            for_secs: 600,
+            hysteresis: 0.5,
+            unit: None,
+        },
+        AlertRule {
+            name: "high-wind".to_string(),
+            quantity: Quantity::Metric(Metric::WindSpeed),
+            condition: Condition::Above { threshold: 20.0 },
+            for_secs: 0,
+            hysteresis: 2.0,
+            unit: None,
+        },
+        AlertRule {
+            name: "pressure-drop".to_string(),
+            quantity: Quantity::Metric(Metric::Pressure),
+            condition: Condition::Falls { by: 3.0, within_secs: 3 * 3600 },
+            for_secs: 0,
+            hysteresis: 0.5,
+            unit: None,
+        },
+        AlertRule {
+            name: "high-uv".to_string(),
+            quantity: Quantity::Metric(Metric::UvIndex),
+            condition: Condition::Above { threshold: 8.0 },
+            for_secs: 600,
+            hysteresis: 0.5,
+            unit: None,
+        },
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Ok,
+    Pending,
+    Firing,
+    // Fired earlier and has since cleared.
+    Resolved,
+}
+
+impl AlertState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlertState::Ok => "OK",
+            AlertState::Pending => "Pending",
+            AlertState::Firing => "Firing",
+            AlertState::Resolved => "Resolved",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertEvent {
+    pub rule: String,
+    pub state: AlertState,
+    pub timestamp: DateTime<Utc>,
+    pub value: f32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertStatus {
+    pub rule: String,
+    pub quantity: Quantity,
+    pub description: String,
+    pub state: AlertState,
+    pub since: Option<DateTime<Utc>>,
+    pub value: Option<f32>,
+}
+
+struct RuleState {
+    state: AlertState,
+    since: Option<DateTime<Utc>>,
+    value: Option<f32>,
+    // Recent values for rate-of-change conditions.
+    samples: VecDeque<(DateTime<Utc>, f32)>,
+}
+
+/// Evaluates every rule against each new reading and keeps a history of state changes.
+pub struct AlertEngine {
+    rules: Vec<(AlertRule, RuleState)>,
+    history: VecDeque<AlertEvent>,
+    last_timestamp: Option<DateTime<Utc>>,
+    units: UnitPreferences,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        Self {
+            rules: rules.iter()
+                .map(|rule| (rule.in_si(), RuleState { state: AlertState::Ok, since: None, value: None, samples: VecDeque::new() }))
+                .collect(),
+            history: VecDeque::with_capacity(HISTORY_CAPACITY),
+            last_timestamp: None,
+            units: UnitPreferences::default(),
+        }
+    }
+
+    /// Units used in descriptions and event messages; rules and values stay in SI.
+    pub fn set_units(&mut self, units: UnitPreferences) {
+        self.units = units;
+    }
+
+    pub fn uses_rain(&self) -> bool {
+        self.rules.iter().any(|(rule, _)| rule.quantity.uses_rain())
+    }
+
+    /// Returns the state changes caused by `data`. Readings older than the last one
+    /// evaluated are ignored so late arrivals cannot flip an alert back and forth.
+    pub fn evaluate(&mut self, data: &WeatherData, rain: Option<&RainTotals>) -> Vec<AlertEvent> {
+        let now = data.timestamp;
+        if self.last_timestamp.is_some_and(|last| now < last) {
+            return Vec::new();
+        }
+        self.last_timestamp = Some(now);
+
+        let mut events = Vec::new();
+        for (rule, state) in &mut self.rules {
+            let value = rule.quantity.value(data, rain);
+            // Not measured by this reading; keep the state until one that is.
+            if value.is_nan() {
+                continue;
+            }
+            state.value = Some(value);
+
+            let Some(measure) = condition_measure(rule, state, now, value) else {
+                continue;
+            };
+            // Once active, the value has to come back past the threshold by the
+            // hysteresis margin before the alert clears.
+            let active = matches!(state.state, AlertState::Pending | AlertState::Firing);
+            let margin = if active { rule.hysteresis } else { 0.0 };
+            let triggered = match rule.condition {
+                Condition::Below { threshold } => measure < threshold + margin,
+                Condition::Above { threshold } => measure > threshold - margin,
+                Condition::Rises { by, .. } | Condition::Falls { by, .. } => measure >= by - margin,
+            };
+
+            let next = match (state.state, triggered) {
+                (AlertState::Ok | AlertState::Resolved, true) if rule.for_secs == 0 => AlertState::Firing,
+                (AlertState::Ok | AlertState::Resolved, true) => AlertState::Pending,
+                (AlertState::Pending, true) if state.since.is_some_and(|since| now - since >= Duration::seconds(rule.for_secs)) => AlertState::Firing,
+                (AlertState::Pending, false) => AlertState::Ok,
+                (AlertState::Firing, false) => AlertState::Resolved,
+                (current, _) => current,
+            };
+            if next == state.state {
+                continue;
+            }
+
+            state.state = next;
+            state.since = (next != AlertState::Ok).then_some(now);
+            if next == AlertState::Ok {
+                continue;
+            }
+
+            let reading = self.units.format(rule.quantity.dimension(), value);
+            let message = match next {
+                AlertState::Pending => format!("{} is {}: {} (waiting for {})", rule.quantity.label(), reading, rule.describe(&self.units), format_secs(rule.for_secs)),
+                AlertState::Firing => format!("{} is {}: {}", rule.quantity.label(), reading, rule.describe(&self.units)),
+                _ => format!("{} back to {}", rule.quantity.label(), reading),
+            };
+            match next {
+                AlertState::Firing => tracing::warn!("Alert '{}' firing: {}", rule.name, message),
+                _ => tracing::info!("Alert '{}' {}: {}", rule.name, next.label().to_lowercase(), message),
+            }
+            events.push(AlertEvent {
+                rule: rule.name.clone(),
+                state: next,
+                timestamp: now,
+                value,
+                message,
+            });
+        }
+
+        for event in &events {
+            if self.history.len() == HISTORY_CAPACITY {
+                self.history.pop_front();
+            }
+            self.history.push_back(event.clone());
+        }
+        events
+    }
+
+    pub fn statuses(&self) -> Vec<AlertStatus> {
+        self.rules.iter()
+            .map(|(rule, state)| AlertStatus {
+                rule: rule.name.clone(),
+                quantity: rule.quantity,
+                description: rule.describe(&self.units),
+                state: state.state,
+                since: state.since,
+                value: state.value,
+            })
+            .collect()
+    }
+
+    /// State changes, newest first.
+    pub fn history(&self) -> Vec<AlertEvent> {
+        self.history.iter().rev().cloned().collect()
+    }
+}
+
+// The value compared against the rule's threshold: the reading itself, or for rate
+// conditions the rise (or fall) since the oldest sample in the window.
+fn condition_measure(rule: &AlertRule, state: &mut RuleState, now: DateTime<Utc>, value: f32) -> Option<f32> {
+    let (within_secs, falling) = match rule.condition {
+        Condition::Above { .. } | Condition::Below { .. } => return Some(value),
+        Condition::Rises { within_secs, .. } => (within_secs, false),
+        Condition::Falls { within_secs, .. } => (within_secs, true),
+    };
+
+    let cutoff = now - Duration::seconds(within_secs);
+    state.samples.push_back((now, value));
+    while state.samples.front().is_some_and(|(timestamp, _)| *timestamp < cutoff) {
+        state.samples.pop_front();
+    }
+    // Measured from the window's peak (or trough for rises), so the change counts
+    // wherever in the window it started.
+    let reference = state.samples.iter().map(|(_, sample)| *sample);
+    let change = if falling {
+        reference.fold(f32::NEG_INFINITY, f32::max) - value
+    } else {
+        value - reference.fold(f32::INFINITY, f32::min)
+    };
+    (state.samples.len() > 1).then_some(change)
+}
+
+fn format_secs(secs: i64) -> String {
+    match secs {
+        s if s >= 3600 && s % 3600 == 0 => format!("{} h", s / 3600),
+        s if s >= 60 && s % 60 == 0 => format!("{} min", s / 60),
+        s => format!("{} s", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use AlertState::{Firing, Ok as Clear, Pending, Resolved};
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn reading(secs: i64, temperature: f32, pressure: f32) -> WeatherData {
+        WeatherData::new(temperature, 60.0, pressure, 2.0, 180.0, 0.0, 1.0, 100.0).with_timestamp(at(secs))
+    }
+
+    fn rule(quantity: Metric, condition: Condition, for_secs: i64, hysteresis: f32) -> AlertRule {
+        AlertRule { name: "test".to_string(), quantity: Quantity::Metric(quantity), condition, for_secs, hysteresis, unit: None }
+    }
+
+    // Feeds temperatures one minute apart and returns the state after each.
+    fn states(engine: &mut AlertEngine, temperatures: &[f32]) -> Vec<AlertState> {
+        temperatures.iter().enumerate()
+            .map(|(i, temperature)| {
+                engine.evaluate(&reading(i as i64 * 60, *temperature, 1013.0), None);
+                engine.statuses()[0].state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fires_immediately_without_for_secs_and_resolves_past_hysteresis() {
+        let mut engine = AlertEngine::new(vec![rule(Metric::Temperature, Condition::Above { threshold: 30.0 }, 0, 1.0)]);
+        // 29.5 is still within the hysteresis margin, so the alert stays firing until 28.9.
+        let states = states(&mut engine, &[25.0, 30.5, 29.5, 29.1, 28.9, 29.5, 30.2]);
+        assert_eq!(states, vec![Clear, Firing, Firing, Firing, Resolved, Resolved, Firing]);
+
+        let history = engine.history();
+        assert_eq!(history.iter().map(|event| event.state).collect::<Vec<_>>(), vec![Firing, Resolved, Firing]);
+        assert_eq!(history[1].timestamp, at(240));
+        assert_eq!(history[1].value, 28.9);
+    }
+
+    #[test]
+    fn pending_until_the_condition_has_held_for_secs() {
+        let mut engine = AlertEngine::new(vec![rule(Metric::Temperature, Condition::Below { threshold: 0.0 }, 180, 0.5)]);
+        let states = states(&mut engine, &[1.0, -1.0, -1.0, -1.0, -1.0, 0.2, 0.6]);
+        // Pending at 60 s, firing once 180 s have passed at 240 s, and the 0.2 is inside the margin.
+        assert_eq!(states, vec![Clear, Pending, Pending, Pending, Firing, Firing, Resolved]);
+        let status = &engine.statuses()[0];
+        assert_eq!(status.since, Some(at(360)));
+        assert_eq!(status.value, Some(0.6));
+    }
+
+    #[test]
+    fn pending_that_clears_goes_back_to_ok_without_an_event() {
+        let mut engine = AlertEngine::new(vec![rule(Metric::Temperature, Condition::Below { threshold: 0.0 }, 600, 0.0)]);
+        let states = states(&mut engine, &[-1.0, -2.0, 1.0, -1.0]);
+        assert_eq!(states, vec![Pending, Pending, Clear, Pending]);
+        // The timer restarted with the second dip.
+        assert_eq!(engine.statuses()[0].since, Some(at(180)));
+        assert!(engine.history().iter().all(|event| event.state == Pending));
+    }
+
+    #[test]
+    fn falls_within_window() {
+        let mut engine = AlertEngine::new(vec![rule(Metric::Pressure, Condition::Falls { by: 3.0, within_secs: 3600 }, 0, 0.5)]);
+        let mut state_at = |secs: i64, pressure: f32| {
+            engine.evaluate(&reading(secs, 10.0, pressure), None);
+            engine.statuses()[0].state
+        };
+        assert_eq!(state_at(0, 1015.0), Clear);
+        assert_eq!(state_at(1800, 1013.0), Clear);
+        // 3.2 hPa below the window's peak.
+        assert_eq!(state_at(3000, 1011.8), Firing);
+        // With 1015 out of the window the fall from 1013 is 2.4, short of 3 less the 0.5 hysteresis.
+        assert_eq!(state_at(3700, 1010.6), Resolved);
+    }
+
+    #[test]
+    fn slow_change_outside_the_window_does_not_fire() {
+        let mut engine = AlertEngine::new(vec![rule(Metric::Temperature, Condition::Rises { by: 5.0, within_secs: 600 }, 0, 0.0)]);
+        // 0.2 °C a minute never adds up to 5 °C within ten minutes.
+        let slow: Vec<f32> = (0..12).map(|i| 10.0 + i as f32 * 0.2).collect();
+        assert!(states(&mut engine, &slow).iter().all(|state| *state == Clear));
+
+        let mut engine = AlertEngine::new(vec![rule(Metric::Temperature, Condition::Rises { by: 5.0, within_secs: 600 }, 0, 0.0)]);
+        assert_eq!(states(&mut engine, &[10.0, 12.0, 15.5]), vec![Clear, Clear, Firing]);
+    }
+
+    #[test]
+    fn late_and_unmeasured_readings_are_ignored() {
+        let mut engine = AlertEngine::new(vec![rule(Metric::Temperature, Condition::Above { threshold: 30.0 }, 0, 0.0)]);
+        engine.evaluate(&reading(600, 31.0, 1013.0), None);
+        assert!(engine.evaluate(&reading(0, 20.0, 1013.0), None).is_empty());
+        assert!(engine.evaluate(&reading(660, f32::NAN, 1013.0), None).is_empty());
+        let status = &engine.statuses()[0];
+        assert_eq!((status.state, status.value), (Firing, Some(31.0)));
+    }
+
+    #[test]
+    fn thresholds_in_other_units_are_converted() {
+        let mut fahrenheit = rule(Metric::Temperature, Condition::Below { threshold: 32.0 }, 0, 0.9);
+        fahrenheit.unit = Some("°F".to_string());
+        let mut engine = AlertEngine::new(vec![fahrenheit]);
+        // 0.9 °F of hysteresis is 0.5 °C.
+        assert_eq!(states(&mut engine, &[1.0, -0.1, 0.4, 0.6]), vec![Clear, Firing, Firing, Resolved]);
+    }
+}
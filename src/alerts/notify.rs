@@ -0,0 +1,695 @@
+use crate::alerts::{AlertEvent, AlertState};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub name: String,
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            name: "webhook".to_string(),
+            url: "http://127.0.0.1:9000/alerts".to_string(),
+            headers: BTreeMap::new(),
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesktopConfig {
+    pub name: String,
+    pub app_name: String,
+}
+
+impl Default for DesktopConfig {
+    fn default() -> Self {
+        Self {
+            name: "desktop".to_string(),
+            app_name: "Weather Station".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    None,
+    StartTls,
+    Tls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmailConfig {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            name: "email".to_string(),
+            host: "localhost".to_string(),
+            port: 25,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from: "weather-station@localhost".to_string(),
+            to: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelConfig {
+    Webhook(WebhookConfig),
+    Desktop(DesktopConfig),
+    Email(EmailConfig),
+}
+
+impl ChannelConfig {
+    fn name(&self) -> &str {
+        match self {
+            ChannelConfig::Webhook(config) => &config.name,
+            ChannelConfig::Desktop(config) => &config.name,
+            ChannelConfig::Email(config) => &config.name,
+        }
+    }
+
+    fn build(&self) -> Result<Box<dyn NotificationChannel>> {
+        Ok(match self {
+            ChannelConfig::Webhook(config) => Box::new(WebhookChannel::new(config.clone())?),
+            ChannelConfig::Desktop(config) => Box::new(DesktopChannel { config: config.clone() }),
+            ChannelConfig::Email(config) => Box::new(EmailChannel::new(config.clone())?),
+        })
+    }
+}
+
+/// Sends events for the named rules ("*" for all) in the given states to the named channels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    #[serde(default = "all_rules")]
+    pub rules: Vec<String>,
+    pub channels: Vec<String>,
+    #[serde(default = "default_states")]
+    pub states: Vec<AlertState>,
+}
+
+fn all_rules() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn default_states() -> Vec<AlertState> {
+    vec![AlertState::Firing, AlertState::Resolved]
+}
+
+impl Route {
+    fn matches(&self, event: &AlertEvent) -> bool {
+        self.states.contains(&event.state)
+            && self.rules.iter().any(|rule| rule == "*" || *rule == event.rule)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub channels: Vec<ChannelConfig>,
+    /// Without routes, firing and resolved events go to every channel.
+    pub routes: Vec<Route>,
+    /// Minimum time between two notifications for the same rule, state and channel.
+    pub min_interval_secs: u64,
+    pub retry_attempts: u32,
+    pub retry_backoff_secs: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            routes: Vec::new(),
+            min_interval_secs: 300,
+            retry_attempts: 3,
+            retry_backoff_secs: 2,
+        }
+    }
+}
+
+impl NotificationConfig {
+    /// Errors start with the offending key, e.g. `routes[0].channels: ...`.
+    pub fn validate(&self) -> Result<()> {
+        for (i, route) in self.routes.iter().enumerate() {
+            for channel in &route.channels {
+                if !self.channels.iter().any(|config| config.name() == channel) {
+                    bail!("routes[{}].channels: unknown notification channel '{}'", i, channel);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn name(&self) -> &str;
+    async fn send(&self, event: &AlertEvent) -> Result<()>;
+}
+
+fn subject(event: &AlertEvent) -> String {
+    format!("[{}] {}", event.state.label(), event.rule)
+}
+
+struct WebhookChannel {
+    config: WebhookConfig,
+    client: reqwest::Client,
+}
+
+impl WebhookChannel {
+    fn new(config: WebhookConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()?;
+        Ok(Self { config, client })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn send(&self, event: &AlertEvent) -> Result<()> {
+        let mut request = self.client.post(&self.config.url).json(event);
+        for (header, value) in &self.config.headers {
+            request = request.header(header, value);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+struct DesktopChannel {
+    config: DesktopConfig,
+}
+
+#[async_trait]
+impl NotificationChannel for DesktopChannel {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn send(&self, event: &AlertEvent) -> Result<()> {
+        let summary = subject(event);
+        let body = event.message.clone();
+        let app_name = self.config.app_name.clone();
+        // Showing a notification talks to the session bus synchronously.
+        tokio::task::spawn_blocking(move || {
+            let mut notification = notify_rust::Notification::new();
+            notification.appname(&app_name).summary(&summary).body(&body);
+            notification.show().map(|_| ())
+        })
+        .await??;
+        Ok(())
+    }
+}
+
+struct EmailChannel {
+    config: EmailConfig,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl EmailChannel {
+    fn new(config: EmailConfig) -> Result<Self> {
+        let from = config.from.parse()?;
+        let to = config.to.iter().map(|address| address.parse()).collect::<Result<Vec<Mailbox>, _>>()?;
+        if to.is_empty() {
+            bail!("email channel '{}' has no recipients", config.name);
+        }
+
+        let mut builder = match config.security {
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+        }
+        .port(config.port);
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self { transport: builder.build(), config, from, to })
+    }
+
+    fn message(&self, event: &AlertEvent) -> Result<Message> {
+        let mut message = Message::builder().from(self.from.clone()).subject(subject(event));
+        for recipient in &self.to {
+            message = message.to(recipient.clone());
+        }
+        let body = format!(
+            "{}\n\nRule: {}\nState: {}\nValue: {:.1}\nTime: {}\n",
+            event.message,
+            event.rule,
+            event.state.label(),
+            event.value,
+            event.timestamp.to_rfc3339(),
+        );
+        Ok(message.body(body)?)
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn send(&self, event: &AlertEvent) -> Result<()> {
+        self.transport.send(self.message(event)?).await?;
+        Ok(())
+    }
+}
+
+/// Routes alert events to channels, rate limiting per rule and channel and retrying
+/// failed deliveries in the background so evaluation is never held up.
+pub struct Notifier {
+    channels: Vec<Arc<dyn NotificationChannel>>,
+    routes: Vec<Route>,
+    min_interval: chrono::Duration,
+    retry_attempts: u32,
+    retry_backoff: Duration,
+    last_sent: HashMap<(String, AlertState, String), DateTime<Utc>>,
+    deliveries: JoinSet<()>,
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationConfig) -> Self {
+        let mut channels: Vec<Arc<dyn NotificationChannel>> = Vec::new();
+        for channel in &config.channels {
+            match channel.build() {
+                Ok(built) => channels.push(Arc::from(built)),
+                Err(e) => tracing::error!("Failed to set up notification channel '{}': {}", channel.name(), e),
+            }
+        }
+        Self::with_channels(config, channels)
+    }
+
+    /// Uses `channels` instead of building the ones in `config`.
+    pub fn with_channels(config: &NotificationConfig, channels: Vec<Arc<dyn NotificationChannel>>) -> Self {
+        Self {
+            channels,
+            routes: config.routes.clone(),
+            min_interval: chrono::Duration::seconds(config.min_interval_secs as i64),
+            retry_attempts: config.retry_attempts.max(1),
+            retry_backoff: Duration::from_secs(config.retry_backoff_secs),
+            last_sent: HashMap::new(),
+            deliveries: JoinSet::new(),
+        }
+    }
+
+    fn targets(&self, event: &AlertEvent) -> Vec<Arc<dyn NotificationChannel>> {
+        if self.routes.is_empty() {
+            return if default_states().contains(&event.state) { self.channels.clone() } else { Vec::new() };
+        }
+        self.channels.iter()
+            .filter(|channel| {
+                self.routes.iter().any(|route| route.matches(event) && route.channels.iter().any(|name| name == channel.name()))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Must be called from within a tokio runtime.
+    pub fn dispatch(&mut self, events: &[AlertEvent]) {
+        // Reap finished deliveries so the set only holds those still running.
+        while self.deliveries.try_join_next().is_some() {}
+
+        for event in events {
+            for channel in self.targets(event) {
+                let key = (event.rule.clone(), event.state, channel.name().to_string());
+                if self.last_sent.get(&key).is_some_and(|last| event.timestamp - *last < self.min_interval) {
+                    tracing::debug!("Rate limited '{}' notification for alert '{}'", channel.name(), event.rule);
+                    continue;
+                }
+                self.last_sent.insert(key, event.timestamp);
+
+                let event = event.clone();
+                let attempts = self.retry_attempts;
+                let backoff = self.retry_backoff;
+                self.deliveries.spawn(async move {
+                    if let Err(e) = deliver(channel.as_ref(), &event, attempts, backoff).await {
+                        tracing::error!("Failed to notify '{}' about alert '{}': {}", channel.name(), event.rule, e);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Waits for deliveries still in flight, giving up on any left after `timeout`.
+    pub async fn finish(&mut self, timeout: Duration) {
+        let pending = self.deliveries.len();
+        if pending == 0 {
+            return;
+        }
+        tracing::info!("Waiting for {} alert notification(s) to be delivered", pending);
+        let drained = tokio::time::timeout(timeout, async {
+            while self.deliveries.join_next().await.is_some() {}
+        });
+        if drained.await.is_err() {
+            tracing::warn!("Dropping {} undelivered alert notification(s)", self.deliveries.len());
+            self.deliveries.abort_all();
+        }
+    }
+}
+
+async fn deliver(channel: &dyn NotificationChannel, event: &AlertEvent, attempts: u32, backoff: Duration) -> Result<()> {
+    let mut delay = backoff;
+    let mut last_error = anyhow!("no delivery attempts made");
+    for attempt in 1..=attempts {
+        match channel.send(event).await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                tracing::warn!("Notification via '{}' failed (attempt {}/{}): {}", channel.name(), attempt, attempts, e);
+                last_error = e;
+            }
+        }
+        if attempt < attempts {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+    use tokio::time::Instant;
+
+    // Records when each send was attempted and fails the first `failures` of them.
+    struct FakeChannel {
+        name: String,
+        failures: u32,
+        attempts: Mutex<Vec<(String, AlertState, Instant)>>,
+    }
+
+    impl FakeChannel {
+        fn new(name: &str, failures: u32) -> Arc<Self> {
+            Arc::new(Self { name: name.to_string(), failures, attempts: Mutex::new(Vec::new()) })
+        }
+
+        fn attempts(&self) -> Vec<(String, AlertState, Instant)> {
+            self.attempts.lock().unwrap().clone()
+        }
+
+        fn delivered(&self) -> usize {
+            self.attempts().len().saturating_sub(self.failures as usize)
+        }
+    }
+
+    #[async_trait]
+    impl NotificationChannel for FakeChannel {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        async fn send(&self, event: &AlertEvent) -> Result<()> {
+            let mut attempts = self.attempts.lock().unwrap();
+            attempts.push((event.rule.clone(), event.state, Instant::now()));
+            if attempts.len() as u32 <= self.failures {
+                bail!("injected failure {}", attempts.len());
+            }
+            Ok(())
+        }
+    }
+
+    fn event(rule: &str, state: AlertState, secs: i64) -> AlertEvent {
+        AlertEvent {
+            rule: rule.to_string(),
+            state,
+            timestamp: Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            value: 1.0,
+            message: "test".to_string(),
+        }
+    }
+
+    fn notifier(config: NotificationConfig, channels: &[&Arc<FakeChannel>]) -> Notifier {
+        let channels = channels.iter().map(|channel| Arc::clone(channel) as Arc<dyn NotificationChannel>).collect();
+        Notifier::with_channels(&config, channels)
+    }
+
+    fn route(rules: &[&str], channels: &[&str], states: &[AlertState]) -> Route {
+        Route {
+            rules: rules.iter().map(|rule| rule.to_string()).collect(),
+            channels: channels.iter().map(|channel| channel.to_string()).collect(),
+            states: states.to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn without_routes_firing_and_resolved_go_everywhere() {
+        let (a, b) = (FakeChannel::new("a", 0), FakeChannel::new("b", 0));
+        let mut notifier = notifier(NotificationConfig::default(), &[&a, &b]);
+        notifier.dispatch(&[event("frost", AlertState::Pending, 0), event("frost", AlertState::Firing, 1), event("frost", AlertState::Resolved, 2)]);
+        notifier.finish(Duration::from_secs(5)).await;
+        for channel in [&a, &b] {
+            let states: Vec<AlertState> = channel.attempts().into_iter().map(|(_, state, _)| state).collect();
+            assert_eq!(states.len(), 2);
+            assert!(!states.contains(&AlertState::Pending));
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_pick_channels_by_rule_and_state() {
+        let (pager, log) = (FakeChannel::new("pager", 0), FakeChannel::new("log", 0));
+        let config = NotificationConfig {
+            routes: vec![
+                route(&["high-wind"], &["pager"], &[AlertState::Firing]),
+                route(&["*"], &["log"], &[AlertState::Pending, AlertState::Firing, AlertState::Resolved]),
+            ],
+            ..Default::default()
+        };
+        let mut notifier = notifier(config, &[&pager, &log]);
+        notifier.dispatch(&[
+            event("high-wind", AlertState::Firing, 0),
+            event("high-wind", AlertState::Resolved, 1),
+            event("frost", AlertState::Pending, 2),
+        ]);
+        notifier.finish(Duration::from_secs(5)).await;
+
+        let pager_events: Vec<(String, AlertState)> = pager.attempts().into_iter().map(|(rule, state, _)| (rule, state)).collect();
+        assert_eq!(pager_events, vec![("high-wind".to_string(), AlertState::Firing)]);
+        assert_eq!(log.delivered(), 3);
+    }
+
+    #[tokio::test]
+    async fn repeats_within_min_interval_are_dropped_per_rule_state_and_channel() {
+        let channel = FakeChannel::new("a", 0);
+        let config = NotificationConfig { min_interval_secs: 300, ..Default::default() };
+        let mut notifier = notifier(config, &[&channel]);
+        notifier.dispatch(&[
+            event("frost", AlertState::Firing, 0),
+            // Same rule and state two minutes later: rate limited.
+            event("frost", AlertState::Firing, 120),
+            // A different state or rule is not.
+            event("frost", AlertState::Resolved, 130),
+            event("high-uv", AlertState::Firing, 140),
+            // Five minutes after the first one it goes out again.
+            event("frost", AlertState::Firing, 300),
+        ]);
+        notifier.finish(Duration::from_secs(5)).await;
+        assert_eq!(channel.delivered(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_sends_are_retried_with_doubling_backoff() {
+        let flaky = FakeChannel::new("flaky", 2);
+        let config = NotificationConfig { retry_attempts: 3, retry_backoff_secs: 2, ..Default::default() };
+        let mut notifier = notifier(config, &[&flaky]);
+        notifier.dispatch(&[event("frost", AlertState::Firing, 0)]);
+        notifier.finish(Duration::from_secs(60)).await;
+
+        let attempts = flaky.attempts();
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(flaky.delivered(), 1);
+        assert_eq!(attempts[1].2 - attempts[0].2, Duration::from_secs(2));
+        assert_eq!(attempts[2].2 - attempts[1].2, Duration::from_secs(4));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_the_last_attempt() {
+        let down = FakeChannel::new("down", u32::MAX);
+        let config = NotificationConfig { retry_attempts: 2, retry_backoff_secs: 1, ..Default::default() };
+        let mut notifier = notifier(config, &[&down]);
+        notifier.dispatch(&[event("frost", AlertState::Firing, 0)]);
+        notifier.finish(Duration::from_secs(60)).await;
+        assert_eq!(down.attempts().len(), 2);
+        assert_eq!(down.delivered(), 0);
+    }
+
+    #[test]
+    fn routes_must_name_configured_channels() {
+        let config = NotificationConfig {
+            channels: vec![ChannelConfig::Desktop(DesktopConfig::default())],
+            routes: vec![route(&["*"], &["desktop"], &[AlertState::Firing]), route(&["*"], &["pager"], &[AlertState::Firing])],
+            ..Default::default()
+        };
+        let error = config.validate().unwrap_err().to_string();
+        assert_eq!(error, "routes[1].channels: unknown notification channel 'pager'");
+    }
+
+    // Records the method, `x-token` header and JSON body of every request and answers the
+    // first `failures` with 503.
+    async fn webhook_server(failures: usize) -> (String, Arc<Mutex<Vec<(String, Option<String>, serde_json::Value)>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let app = axum::Router::new().route("/alerts", axum::routing::any(move |method: axum::http::Method, headers: axum::http::HeaderMap, body: String| async move {
+            let mut requests = recorded.lock().unwrap();
+            let token = headers.get("x-token").and_then(|value| value.to_str().ok()).map(str::to_string);
+            requests.push((method.to_string(), token, serde_json::from_str(&body).unwrap_or_default()));
+            if requests.len() <= failures { axum::http::StatusCode::SERVICE_UNAVAILABLE } else { axum::http::StatusCode::OK }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/alerts", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
+
+    fn webhook(url: String) -> WebhookChannel {
+        WebhookChannel::new(WebhookConfig {
+            url,
+            headers: BTreeMap::from([("x-token".to_string(), "secret".to_string())]),
+            ..WebhookConfig::default()
+        }).unwrap()
+    }
+
+    #[tokio::test]
+    async fn webhook_posts_the_event_as_json_with_its_headers() {
+        let (url, requests) = webhook_server(0).await;
+        webhook(url).send(&event("frost", AlertState::Firing, 0)).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let (method, token, body) = &requests[0];
+        assert_eq!((method.as_str(), token.as_deref()), ("POST", Some("secret")));
+        assert_eq!(body["rule"], "frost");
+        assert_eq!(body["state"], "firing");
+        assert_eq!(body["message"], "test");
+        assert_eq!(body["timestamp"], "2023-11-14T22:13:20Z");
+    }
+
+    #[tokio::test]
+    async fn webhook_server_errors_are_retried() {
+        let (url, requests) = webhook_server(2).await;
+        deliver(&webhook(url), &event("frost", AlertState::Firing, 0), 3, Duration::from_millis(10)).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        let (url, requests) = webhook_server(5).await;
+        let error = deliver(&webhook(url), &event("frost", AlertState::Firing, 0), 2, Duration::from_millis(10)).await.unwrap_err();
+        assert!(error.to_string().contains("503"), "{}", error);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    fn email(port: u16) -> EmailChannel {
+        EmailChannel::new(EmailConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            to: vec!["shed@example.com".to_string(), "Pat <pat@example.com>".to_string()],
+            ..EmailConfig::default()
+        }).unwrap()
+    }
+
+    #[test]
+    fn email_message_has_the_subject_recipients_and_details() {
+        let message = email(25).message(&event("frost", AlertState::Resolved, 0)).unwrap();
+        let text = String::from_utf8(message.formatted()).unwrap();
+        assert!(text.contains("From: weather-station@localhost\r\n"), "{}", text);
+        assert!(text.contains("To: shed@example.com, Pat <pat@example.com>\r\n"), "{}", text);
+        assert!(text.contains("Subject: [Resolved] frost\r\n"), "{}", text);
+        assert!(text.contains("test\r\n\r\nRule: frost\r\nState: Resolved\r\nValue: 1.0\r\nTime: 2023-11-14T22:13:20+00:00\r\n"), "{}", text);
+        assert!(EmailChannel::new(EmailConfig::default()).is_err());
+    }
+
+    // Accepts one connection over plain SMTP and returns its commands and message.
+    async fn smtp_sink() -> (u16, tokio::task::JoinHandle<(Vec<String>, String)>) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sink = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+            let (mut commands, mut data, mut in_data) = (Vec::new(), String::new(), false);
+            while let Ok(Some(line)) = lines.next_line().await {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        writer.write_all(b"250 queued\r\n").await.unwrap();
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+                let verb = line.split(' ').next().unwrap_or_default().to_ascii_uppercase();
+                commands.push(line);
+                let reply: &[u8] = match verb.as_str() {
+                    "DATA" => {
+                        in_data = true;
+                        b"354 go ahead\r\n"
+                    }
+                    "QUIT" => {
+                        let _ = writer.write_all(b"221 bye\r\n").await;
+                        break;
+                    }
+                    _ => b"250 ok\r\n",
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            (commands, data)
+        });
+        (port, sink)
+    }
+
+    #[tokio::test]
+    async fn email_is_delivered_over_smtp() {
+        let (port, sink) = smtp_sink().await;
+        let channel = email(port);
+        channel.send(&event("frost", AlertState::Firing, 0)).await.unwrap();
+        drop(channel);
+
+        let (commands, data) = tokio::time::timeout(Duration::from_secs(5), sink).await.unwrap().unwrap();
+        assert!(commands.contains(&"MAIL FROM:<weather-station@localhost>".to_string()), "{:?}", commands);
+        assert!(commands.contains(&"RCPT TO:<shed@example.com>".to_string()), "{:?}", commands);
+        assert!(commands.contains(&"RCPT TO:<pat@example.com>".to_string()), "{:?}", commands);
+        assert!(data.contains("Subject: [Firing] frost"), "{}", data);
+        assert!(data.contains("Rule: frost"), "{}", data);
+    }
+}
@@ -0,0 +1,126 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::ApiState;
+use crate::data::Metric;
+use crate::metrics::SourceCounters;
+use crate::sources::SourceHealth;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// `GET /metrics`: the latest value of every reading field per station (source name) and
+/// the health of the ingest path, in the Prometheus text exposition format.
+pub(super) async fn metrics(State(state): State<ApiState>) -> impl IntoResponse {
+    let counters = state.ingest.snapshot();
+    let (health, errors) = {
+        let collector = state.collector.lock().await;
+        (collector.source_health(), collector.source_errors())
+    };
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], render(&counters, &health, &errors, state.ingest.queue_depth(), Utc::now()))
+}
+
+fn render(counters: &BTreeMap<String, SourceCounters>, health: &[(String, SourceHealth)], errors: &[(String, u64)], queue_depth: u64, now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+
+    for metric in Metric::ALL {
+        let (name, help) = gauge(metric);
+        family(&mut out, name, "gauge", help);
+        for (station, counters) in counters {
+            if let Some(latest) = counters.latest.as_ref().filter(|latest| latest.has(metric)) {
+                sample(&mut out, name, Some(station), metric.value(latest) as f64);
+            }
+        }
+    }
+
+    family(&mut out, "weather_last_reading_age_seconds", "gauge", "Seconds since each source's latest reading");
+    for (station, counters) in counters {
+        if let Some(latest) = &counters.latest {
+            sample(&mut out, "weather_last_reading_age_seconds", Some(station), (now - latest.timestamp).num_milliseconds() as f64 / 1000.0);
+        }
+    }
+
+    family(&mut out, "weather_readings_received_total", "counter", "Readings stored since startup");
+    for (station, counters) in counters {
+        sample(&mut out, "weather_readings_received_total", Some(station), counters.received as f64);
+    }
+
+    family(&mut out, "weather_source_errors_total", "counter", "Times a source failed to start, read or connect");
+    for (station, count) in errors {
+        sample(&mut out, "weather_source_errors_total", Some(station), *count as f64);
+    }
+
+    family(&mut out, "weather_source_healthy", "gauge", "1 if the source is delivering readings, 0 otherwise");
+    for (station, health) in health {
+        sample(&mut out, "weather_source_healthy", Some(station), if health.is_healthy() { 1.0 } else { 0.0 });
+    }
+
+    family(&mut out, "weather_ingest_queue_depth", "gauge", "Readings sent by sources but not yet stored");
+    sample(&mut out, "weather_ingest_queue_depth", None, queue_depth as f64);
+
+    out
+}
+
+fn gauge(metric: Metric) -> (&'static str, &'static str) {
+    match metric {
+        Metric::Temperature => ("weather_temperature_celsius", "Air temperature"),
+        Metric::Humidity => ("weather_humidity_percent", "Relative humidity"),
+        Metric::Pressure => ("weather_pressure_hectopascals", "Barometric pressure"),
+        Metric::WindSpeed => ("weather_wind_speed_meters_per_second", "Wind speed"),
+        Metric::WindDirection => ("weather_wind_direction_degrees", "Direction the wind blows from"),
+        Metric::RainRate => ("weather_rain_rate_millimeters_per_hour", "Rain rate"),
+        Metric::UvIndex => ("weather_uv_index", "UV index"),
+        Metric::SolarRadiation => ("weather_solar_radiation_watts_per_square_meter", "Solar radiation"),
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, station: Option<&str>, value: f64) {
+    match station {
+        Some(station) => {
+            let station = station.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            let _ = writeln!(out, "{}{{station=\"{}\"}} {}", name, station, value);
+        }
+        None => {
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::WeatherData;
+    use chrono::Duration;
+
+    #[test]
+    fn renders_families_with_station_labels() {
+        let now = Utc::now();
+        let latest = WeatherData::new(21.5, 40.0, 1012.0, 3.0, f32::NAN, 0.0, 2.0, 300.0).with_timestamp(now - Duration::seconds(30));
+        let counters = BTreeMap::from([("roof \"north\"".to_string(), SourceCounters { sent: 5, received: 3, latest: Some(latest) })]);
+        let health = vec![("roof \"north\"".to_string(), SourceHealth::Healthy), ("garden".to_string(), SourceHealth::Failed("gone".to_string()))];
+        let errors = vec![("garden".to_string(), 4)];
+
+        let out = render(&counters, &health, &errors, 2, now);
+
+        assert!(out.contains("# TYPE weather_temperature_celsius gauge\n"));
+        assert!(out.contains("weather_temperature_celsius{station=\"roof \\\"north\\\"\"} 21.5\n"));
+        assert!(out.contains("# TYPE weather_wind_direction_degrees gauge\n"));
+        assert!(!out.contains("weather_wind_direction_degrees{"));
+        assert!(out.contains("weather_last_reading_age_seconds{station=\"roof \\\"north\\\"\"} 30\n"));
+        assert!(out.contains("# TYPE weather_readings_received_total counter\n"));
+        assert!(out.contains("weather_readings_received_total{station=\"roof \\\"north\\\"\"} 3\n"));
+        assert!(out.contains("weather_source_errors_total{station=\"garden\"} 4\n"));
+        assert!(out.contains("weather_source_healthy{station=\"garden\"} 0\n"));
+        assert!(out.contains("weather_source_healthy{station=\"roof \\\"north\\\"\"} 1\n"));
+        assert!(out.contains("weather_ingest_queue_depth 2\n"));
+        assert!(!out.contains("dropped"));
+    }
+}
@@ -0,0 +1,470 @@
+use anyhow::{anyhow, Context, Result};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch, Mutex};
+
+use crate::config::ApiConfig;
+use crate::data::{Metric, TelemetrySystem, WeatherData};
+use crate::metrics::IngestMetrics;
+use crate::rollup::Aggregate;
+use crate::storage::WeatherStore;
+use crate::telemetry::TelemetryCollector;
+
+mod metrics;
+mod stream;
+
+// Windows used when a request has no `from`.
+const DEFAULT_HISTORY_HOURS: i64 = 1;
+const DEFAULT_STATS_HOURS: i64 = 24;
+// Larger answers have to ask for a coarser resolution or a shorter range.
+const MAX_HISTORY_POINTS: usize = 50_000;
+// /health reports the station as stale once the newest reading is this old.
+const STALE_AFTER_SECS: i64 = 600;
+
+/// What the API serves, shared with the running station.
+#[derive(Clone)]
+pub struct ApiState {
+    pub telemetry_system: Arc<Mutex<TelemetrySystem>>,
+    pub collector: Arc<Mutex<TelemetryCollector>>,
+    pub ingest: Arc<IngestMetrics>,
+    /// A separate read connection to the station's database for `/history` and `/stats`,
+    /// queried on a blocking thread so long ranges never hold `telemetry_system`; without
+    /// one they are served from the in-memory history.
+    pub store: Option<Arc<std::sync::Mutex<dyn WeatherStore>>>,
+    /// Every new reading, for the WebSocket subscribers.
+    pub readings: broadcast::Sender<WeatherData>,
+    /// Subscribers are sent a close frame, and [`serve`] returns, once this becomes true.
+    pub shutdown: watch::Receiver<bool>,
+}
+
+/// Read-only JSON endpoints over the telemetry system:
+///
+/// - `GET /current?fields=` the newest reading
+/// - `GET /history?from=&to=&fields=&resolution=` readings in a time range, raw or averaged
+/// - `GET /stats?from=&to=&fields=` min/max/avg/last of every field over a time range
+/// - `GET /health` whether readings are arriving
+/// - `GET /stream?fields=&throttle=&replay=` a WebSocket pushing each new reading
+/// - `GET /metrics` the latest readings and ingest health in Prometheus text format
+///
+/// Times are RFC 3339 (`2024-05-01T12:00:00Z`), `fields` is a comma-separated list of
+/// reading fields, and `resolution` is `raw` or a bucket size such as `300`, `5m` or `1h`.
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/current", get(current))
+        .route("/history", get(history))
+        .route("/stats", get(stats))
+        .route("/health", get(health))
+        .route("/stream", get(stream::stream))
+        .route("/metrics", get(metrics::metrics))
+        .layer(axum::middleware::map_response(allow_any_origin))
+        .with_state(state)
+}
+
+/// Serves [`router`] on `config.bind` until `state.shutdown` becomes true.
+pub async fn serve(config: &ApiConfig, state: ApiState) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(&config.bind).await
+        .with_context(|| format!("failed to listen on {}", config.bind))?;
+    tracing::info!("REST API listening on http://{}", listener.local_addr()?);
+    let mut stop = state.shutdown.clone();
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move {
+            let _ = stop.wait_for(|stop| *stop).await;
+        })
+        .await?;
+    Ok(())
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        tracing::error!("Failed to read stored weather history: {:#}", e);
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, "failed to read stored readings".to_string())
+    }
+}
+
+fn bad_request(message: String) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, message)
+}
+
+fn too_many_points(count: usize) -> ApiError {
+    bad_request(format!(
+        "the range holds {} readings, more than {}; use a coarser resolution or a shorter range",
+        count, MAX_HISTORY_POINTS
+    ))
+}
+
+#[derive(Deserialize)]
+struct FieldsQuery {
+    fields: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RangeQuery {
+    from: Option<String>,
+    to: Option<String>,
+    fields: Option<String>,
+    resolution: Option<String>,
+}
+
+impl RangeQuery {
+    fn window(&self, default_hours: i64) -> Result<(DateTime<Utc>, DateTime<Utc>), ApiError> {
+        let to = match &self.to {
+            Some(to) => parse_time("to", to)?,
+            None => Utc::now(),
+        };
+        let from = match &self.from {
+            Some(from) => parse_time("from", from)?,
+            None => to - Duration::hours(default_hours),
+        };
+        if from >= to {
+            return Err(bad_request("from: must be before to".to_string()));
+        }
+        Ok((from, to))
+    }
+}
+
+async fn current(State(state): State<ApiState>, Query(query): Query<FieldsQuery>) -> Result<Json<Value>, ApiError> {
+    let fields = parse_fields(query.fields.as_deref())?;
+    let system = state.telemetry_system.lock().await;
+    let latest = system.get_latest_weather()
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, "no readings yet".to_string()))?;
+    Ok(Json(reading_json(latest, &fields)))
+}
+
+async fn history(State(state): State<ApiState>, Query(query): Query<RangeQuery>) -> Result<Json<Value>, ApiError> {
+    let (from, to) = query.window(DEFAULT_HISTORY_HOURS)?;
+    let fields = parse_fields(query.fields.as_deref())?;
+    let resolution = query.resolution.as_deref().map(parse_resolution).transpose()?.flatten();
+
+    // Checked before anything is loaded: the bucket count follows from the span, raw
+    // readings are counted first.
+    if let Some(resolution) = resolution {
+        let buckets = ((to - from).num_milliseconds() / resolution.num_milliseconds()) as usize + 1;
+        if buckets > MAX_HISTORY_POINTS {
+            return Err(too_many_points(buckets));
+        }
+    }
+    let readings = match &state.store {
+        Some(store) => read_store(store, move |store| match resolution {
+            Some(resolution) => Ok(store.bucket_means(from, to, resolution)?),
+            None => match store.count_range(from, to)? {
+                count if count > MAX_HISTORY_POINTS => Err(too_many_points(count)),
+                _ => Ok(store.query_range(from, to)?),
+            },
+        }).await?,
+        None => {
+            let history = state.telemetry_system.lock().await.snapshot();
+            let readings: Vec<_> = history.window(from, to).cloned().collect();
+            match resolution {
+                Some(resolution) => bucket_means(&readings, resolution),
+                None if readings.len() > MAX_HISTORY_POINTS => return Err(too_many_points(readings.len())),
+                None => readings,
+            }
+        }
+    };
+
+    Ok(Json(json!({
+        "from": from,
+        "to": to,
+        "resolution_secs": resolution.map(|resolution| resolution.num_seconds()),
+        "readings": readings.iter().map(|data| reading_json(data, &fields)).collect::<Vec<_>>(),
+    })))
+}
+
+async fn stats(State(state): State<ApiState>, Query(query): Query<RangeQuery>) -> Result<Json<Value>, ApiError> {
+    let (from, to) = query.window(DEFAULT_STATS_HOURS)?;
+    let fields = parse_fields(query.fields.as_deref())?;
+    let aggregate = match &state.store {
+        Some(store) => read_store(store, move |store| Ok(store.range_stats(from, to)?)).await?,
+        None => Aggregate::from_readings(state.telemetry_system.lock().await.snapshot().window(from, to)),
+    };
+
+    let mut stats = Map::new();
+    if let Some(aggregate) = &aggregate {
+        for metric in fields {
+            let field = aggregate.stats(metric);
+            stats.insert(metric.key().to_string(), json!({
+                "min": field.min,
+                "max": field.max,
+                "avg": aggregate.mean(metric),
+                "last": field.last,
+            }));
+        }
+    }
+    Ok(Json(json!({
+        "from": from,
+        "to": to,
+        "count": aggregate.as_ref().map_or(0, |aggregate| aggregate.count),
+        "fields": stats,
+    })))
+}
+
+async fn health(State(state): State<ApiState>) -> (StatusCode, Json<Value>) {
+    let last_reading = state.telemetry_system.lock().await.get_latest_weather().map(|data| data.timestamp);
+    let age = last_reading.map(|timestamp| (Utc::now() - timestamp).num_seconds().max(0));
+    let (code, status) = match age {
+        Some(age) if age <= STALE_AFTER_SECS => (StatusCode::OK, "ok"),
+        Some(_) => (StatusCode::SERVICE_UNAVAILABLE, "stale"),
+        None => (StatusCode::SERVICE_UNAVAILABLE, "waiting"),
+    };
+    (code, Json(json!({
+        "status": status,
+        "last_reading": last_reading,
+        "last_reading_age_secs": age,
+    })))
+}
+
+// Runs `query` against the API's own connection on a blocking thread, so neither ingest
+// nor the dashboard waits for a long range.
+async fn read_store<T: Send + 'static>(
+    store: &Arc<std::sync::Mutex<dyn WeatherStore>>,
+    query: impl FnOnce(&dyn WeatherStore) -> Result<T, ApiError> + Send + 'static,
+) -> Result<T, ApiError> {
+    let store = store.clone();
+    tokio::task::spawn_blocking(move || {
+        let store = store.lock().map_err(|_| anyhow!("the database connection was poisoned"))?;
+        query(&*store)
+    })
+    .await
+    .map_err(anyhow::Error::from)?
+}
+
+async fn allow_any_origin(mut response: Response) -> Response {
+    response.headers_mut().insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+    response
+}
+
+// The reading as JSON with only the requested measurement fields; id, timestamp and
+// source are always included.
+fn reading_json(data: &WeatherData, fields: &[Metric]) -> Value {
+    let mut value = serde_json::to_value(data).unwrap_or_default();
+    if let Value::Object(map) = &mut value {
+        map.retain(|key, _| Metric::from_key(key).is_none_or(|metric| fields.contains(&metric)));
+    }
+    value
+}
+
+fn parse_time(name: &str, value: &str) -> Result<DateTime<Utc>, ApiError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| bad_request(format!("{}: '{}' is not an RFC 3339 time like 2024-05-01T12:00:00Z", name, value)))
+}
+
+fn parse_fields(fields: Option<&str>) -> Result<Vec<Metric>, ApiError> {
+    let Some(fields) = fields.filter(|fields| !fields.trim().is_empty()) else {
+        return Ok(Metric::ALL.to_vec());
+    };
+    fields.split(',')
+        .map(|key| {
+            let key = key.trim();
+            Metric::from_key(key).ok_or_else(|| bad_request(format!(
+                "fields: unknown field '{}', expected some of {}",
+                key, Metric::ALL.map(Metric::key).join(", ")
+            )))
+        })
+        .collect()
+}
+
+// `raw` (None) or a bucket size.
+fn parse_resolution(value: &str) -> Result<Option<Duration>, ApiError> {
+    if value == "raw" {
+        return Ok(None);
+    }
+    parse_duration(value)
+        .map(Some)
+        .ok_or_else(|| bad_request(format!("resolution: '{}' is not raw or a duration like 300, 5m or 1h", value)))
+}
+
+// A positive number of seconds, optionally suffixed with s, m, h or d.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, unit_secs) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1),
+        Some((i, 'm')) => (&value[..i], 60),
+        Some((i, 'h')) => (&value[..i], 3600),
+        Some((i, 'd')) => (&value[..i], 86400),
+        _ => (value, 1),
+    };
+    number.parse::<i64>().ok()
+        .filter(|number| *number > 0)
+        .and_then(|number| number.checked_mul(unit_secs))
+        .and_then(Duration::try_seconds)
+}
+
+// Averages readings (oldest first) into buckets aligned to multiples of `resolution`.
+fn bucket_means(readings: &[WeatherData], resolution: Duration) -> Vec<WeatherData> {
+    let resolution_ms = resolution.num_milliseconds();
+    let mut buckets: Vec<Aggregate> = Vec::new();
+    for data in readings {
+        let millis = data.timestamp.timestamp_millis();
+        let start = DateTime::from_timestamp_millis(millis - millis.rem_euclid(resolution_ms)).unwrap_or(data.timestamp);
+        match buckets.last_mut() {
+            Some(bucket) if bucket.start == start => bucket.add(data),
+            _ => buckets.push(Aggregate::new(start, data)),
+        }
+    }
+    buckets.iter().map(Aggregate::to_weather_data).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStore;
+
+    fn state() -> ApiState {
+        let (collector, _) = TelemetryCollector::new();
+        ApiState {
+            telemetry_system: Arc::new(Mutex::new(TelemetrySystem::new())),
+            collector: Arc::new(Mutex::new(collector)),
+            ingest: Arc::new(IngestMetrics::new()),
+            store: None,
+            readings: broadcast::channel(16).0,
+            shutdown: watch::channel(false).1,
+        }
+    }
+
+    fn range(resolution: &str) -> RangeQuery {
+        RangeQuery {
+            from: Some("2024-05-01T12:00:00Z".to_string()),
+            to: Some("2024-05-01T13:00:00Z".to_string()),
+            fields: None,
+            resolution: Some(resolution.to_string()),
+        }
+    }
+
+    fn reading(time: &str, temperature: f32) -> WeatherData {
+        WeatherData::new(temperature, 50.0, 1013.0, 2.0, 90.0, 0.0, 1.0, 100.0)
+            .with_timestamp(time.parse().unwrap())
+    }
+
+    #[test]
+    fn parses_durations_with_units() {
+        assert_eq!(parse_duration("300"), Some(Duration::seconds(300)));
+        assert_eq!(parse_duration("45s"), Some(Duration::seconds(45)));
+        assert_eq!(parse_duration("5m"), Some(Duration::minutes(5)));
+        assert_eq!(parse_duration("1h"), Some(Duration::hours(1)));
+        assert_eq!(parse_duration("2d"), Some(Duration::days(2)));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        for value in ["", "m", "0", "0m", "-5m", "5x", "5 m", "1.5h", "h5", "5mm", "5é", "99999999999999999999", "9223372036854775807d", "99999999999999d"] {
+            assert_eq!(parse_duration(value), None, "{:?}", value);
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_resolution_is_a_bad_request() {
+        for value in ["5x", "0", "-1h", "99999999999999d", ""] {
+            let Err(error) = history(State(state()), Query(range(value))).await else {
+                panic!("resolution {:?} was accepted", value);
+            };
+            assert_eq!(error.0, StatusCode::BAD_REQUEST);
+            assert!(error.1.starts_with("resolution: "), "{}", error.1);
+            assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn raw_and_valid_resolutions_are_served() {
+        let state = state();
+        state.telemetry_system.lock().await.add_weather_data(reading("2024-05-01T12:10:00Z", 20.0));
+        let Json(raw) = history(State(state.clone()), Query(range("raw"))).await.ok().unwrap();
+        assert_eq!(raw["resolution_secs"], Value::Null);
+        let Json(bucketed) = history(State(state), Query(range("15m"))).await.ok().unwrap();
+        assert_eq!(bucketed["resolution_secs"], 900);
+        assert_eq!(bucketed["readings"].as_array().unwrap().len(), 1);
+    }
+
+    fn state_with_store(readings: &[WeatherData]) -> ApiState {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store.insert_new(readings).unwrap();
+        ApiState { store: Some(Arc::new(std::sync::Mutex::new(store))), ..state() }
+    }
+
+    #[tokio::test]
+    async fn stored_history_and_stats_are_served_from_the_read_connection() {
+        let state = state_with_store(&[reading("2024-05-01T12:10:00Z", 20.0), reading("2024-05-01T12:20:00Z", 24.0)]);
+        // Held throughout: the store is read without it.
+        let telemetry_system = state.telemetry_system.clone();
+        let _telemetry = telemetry_system.lock().await;
+
+        let Json(raw) = history(State(state.clone()), Query(range("raw"))).await.ok().unwrap();
+        assert_eq!(raw["readings"].as_array().unwrap().len(), 2);
+        let Json(bucketed) = history(State(state.clone()), Query(range("1h"))).await.ok().unwrap();
+        assert_eq!(bucketed["readings"][0]["temperature"], 22.0);
+        let Json(stats) = super::stats(State(state), Query(range("raw"))).await.ok().unwrap();
+        assert_eq!(stats["count"], 2);
+        assert_eq!(stats["fields"]["temperature"]["last"], 24.0);
+    }
+
+    #[tokio::test]
+    async fn oversized_raw_ranges_are_rejected_after_counting() {
+        let start: DateTime<Utc> = "2024-05-01T12:00:00Z".parse().unwrap();
+        let readings: Vec<_> = (1..=MAX_HISTORY_POINTS as i64 + 1)
+            .map(|i| reading("2024-05-01T12:00:00Z", 20.0).with_timestamp(start + Duration::milliseconds(i * 50)))
+            .collect();
+        let Err(error) = history(State(state_with_store(&readings)), Query(range("raw"))).await else {
+            panic!("oversized range was served");
+        };
+        assert_eq!(error.0, StatusCode::BAD_REQUEST);
+        assert!(error.1.contains(&format!("holds {} readings", MAX_HISTORY_POINTS + 1)), "{}", error.1);
+    }
+
+    #[tokio::test]
+    async fn resolutions_giving_too_many_buckets_are_rejected_up_front() {
+        let query = RangeQuery { from: Some("2023-05-01T12:00:00Z".to_string()), ..range("1s") };
+        let Err(error) = history(State(state()), Query(query)).await else {
+            panic!("too fine a resolution was served");
+        };
+        assert_eq!(error.0, StatusCode::BAD_REQUEST);
+        assert!(error.1.starts_with("the range holds"), "{}", error.1);
+    }
+
+    #[test]
+    fn no_readings_give_no_buckets() {
+        assert!(bucket_means(&[], Duration::minutes(5)).is_empty());
+    }
+
+    #[test]
+    fn buckets_without_readings_are_left_out() {
+        let readings = [
+            reading("2024-05-01T12:01:00Z", 10.0),
+            reading("2024-05-01T12:04:00Z", 14.0),
+            // Nothing between 12:05 and 12:20.
+            reading("2024-05-01T12:21:00Z", 20.0),
+        ];
+        let buckets = bucket_means(&readings, Duration::minutes(5));
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].timestamp, "2024-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(buckets[0].temperature, 12.0);
+        assert_eq!(buckets[1].timestamp, "2024-05-01T12:20:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(buckets[1].temperature, 20.0);
+    }
+
+    #[test]
+    fn fields_never_measured_in_a_bucket_stay_missing() {
+        let mut first = reading("2024-05-01T12:01:00Z", 10.0);
+        first.uv_index = f32::NAN;
+        let mut second = reading("2024-05-01T12:02:00Z", 12.0);
+        second.uv_index = f32::NAN;
+        let buckets = bucket_means(&[first, second], Duration::minutes(5));
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].temperature, 11.0);
+        assert!(buckets[0].uv_index.is_nan());
+        assert_eq!(reading_json(&buckets[0], &[Metric::UvIndex])["uv_index"], Value::Null);
+    }
+}
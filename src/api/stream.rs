@@ -0,0 +1,229 @@
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use serde::Deserialize;
+use tokio::sync::{broadcast, watch};
+use tokio::time::Instant;
+
+use super::{bad_request, parse_duration, parse_fields, reading_json, ApiError, ApiState};
+use crate::data::{Metric, WeatherData};
+
+// Most readings a client can ask to be replayed on connect.
+const MAX_REPLAY: usize = 1000;
+// WebSocket close code for "going away", sent when the station shuts down.
+const CLOSE_GOING_AWAY: u16 = 1001;
+
+#[derive(Deserialize)]
+pub(super) struct StreamQuery {
+    fields: Option<String>,
+    throttle: Option<String>,
+    replay: Option<usize>,
+}
+
+/// `GET /stream?fields=&throttle=&replay=` upgrades to a WebSocket that sends every new
+/// reading as a JSON text message, shaped like `/current`. `replay` first sends up to that
+/// many recent readings, oldest first; `throttle` sends at most one reading per interval,
+/// the newest one when the interval ends.
+pub(super) async fn stream(State(state): State<ApiState>, Query(query): Query<StreamQuery>, upgrade: WebSocketUpgrade) -> Result<Response, ApiError> {
+    let fields = parse_fields(query.fields.as_deref())?;
+    let throttle = query.throttle.as_deref()
+        .map(|throttle| parse_duration(throttle)
+            .and_then(|throttle| throttle.to_std().ok())
+            .ok_or_else(|| bad_request(format!("throttle: '{}' is not a duration like 10, 30s or 1m", throttle))))
+        .transpose()?;
+    let replay = query.replay.unwrap_or(0).min(MAX_REPLAY);
+
+    // Subscribing under the lock the ingest task broadcasts under means no reading is
+    // both replayed and streamed, and none falls between the two.
+    let (readings, replayed) = {
+        let system = state.telemetry_system.lock().await;
+        let readings = state.readings.subscribe();
+        let snapshot = system.snapshot();
+        let mut replayed: Vec<WeatherData> = snapshot.iter().rev().take(replay).cloned().collect();
+        replayed.reverse();
+        (readings, replayed)
+    };
+    let shutdown = state.shutdown.clone();
+
+    Ok(upgrade.on_upgrade(move |socket| async move {
+        let subscriber = Subscriber { socket, fields, throttle, next_send: Instant::now() };
+        subscriber.run(replayed, readings, shutdown).await;
+    }))
+}
+
+struct Subscriber {
+    socket: WebSocket,
+    fields: Vec<Metric>,
+    throttle: Option<std::time::Duration>,
+    next_send: Instant,
+}
+
+impl Subscriber {
+    async fn run(mut self, replayed: Vec<WeatherData>, mut readings: broadcast::Receiver<WeatherData>, mut shutdown: watch::Receiver<bool>) {
+        for data in &replayed {
+            if !self.send(data).await {
+                return;
+            }
+        }
+
+        // Under a throttle, readings arriving early replace each other until the interval ends.
+        let mut pending: Option<WeatherData> = None;
+        loop {
+            tokio::select! {
+                received = readings.recv() => match received {
+                    Ok(data) if self.throttle.is_some() && Instant::now() < self.next_send => pending = Some(data),
+                    Ok(data) => {
+                        if !self.send(&data).await {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::debug!("WebSocket subscriber fell behind, skipped {} reading(s)", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = tokio::time::sleep_until(self.next_send), if pending.is_some() => {
+                    if let Some(data) = pending.take() {
+                        if !self.send(&data).await {
+                            return;
+                        }
+                    }
+                }
+                message = self.socket.recv() => match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    // Clients have nothing to say; pings are answered by axum.
+                    Some(Ok(_)) => {}
+                },
+                _ = shutdown.changed() => break,
+            }
+        }
+
+        let _ = self.socket.send(Message::Close(Some(CloseFrame {
+            code: CLOSE_GOING_AWAY,
+            reason: "station shutting down".into(),
+        }))).await;
+    }
+
+    // False once the client is gone.
+    async fn send(&mut self, data: &WeatherData) -> bool {
+        if let Some(throttle) = self.throttle {
+            self.next_send = Instant::now() + throttle;
+        }
+        let text = reading_json(data, &self.fields).to_string();
+        self.socket.send(Message::Text(text.into())).await.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TelemetrySystem;
+    use crate::metrics::IngestMetrics;
+    use crate::telemetry::TelemetryCollector;
+    use futures_util::StreamExt;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::TcpStream;
+    use tokio::sync::Mutex;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message as ClientMessage;
+    use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    struct Server {
+        state: ApiState,
+        stop: watch::Sender<bool>,
+        address: std::net::SocketAddr,
+    }
+
+    async fn serve() -> Server {
+        let (collector, _) = TelemetryCollector::new();
+        let (stop, shutdown) = watch::channel(false);
+        let state = ApiState {
+            telemetry_system: Arc::new(Mutex::new(TelemetrySystem::new())),
+            collector: Arc::new(Mutex::new(collector)),
+            ingest: Arc::new(IngestMetrics::new()),
+            store: None,
+            readings: broadcast::channel(16).0,
+            shutdown,
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = super::super::router(state.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        Server { state, stop, address }
+    }
+
+    impl Server {
+        async fn connect(&self, query: &str) -> Client {
+            tokio_tungstenite::connect_async(format!("ws://{}/stream?{}", self.address, query)).await.unwrap().0
+        }
+    }
+
+    fn reading(temperature: f32) -> WeatherData {
+        WeatherData::new(temperature, 50.0, 1013.0, 2.0, 90.0, 0.0, 1.0, 100.0)
+    }
+
+    async fn next_temperature(client: &mut Client) -> f64 {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next()).await.expect("no message within 5 seconds");
+        let ClientMessage::Text(text) = message.unwrap().unwrap() else {
+            panic!("expected a text message");
+        };
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        value["temperature"].as_f64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_recent_readings_before_live_ones() {
+        let server = serve().await;
+        for temperature in [1.0, 2.0, 3.0] {
+            server.state.telemetry_system.lock().await.add_weather_data(reading(temperature));
+        }
+        let mut client = server.connect("replay=2&fields=temperature").await;
+        server.state.readings.send(reading(4.0)).unwrap();
+
+        assert_eq!(next_temperature(&mut client).await, 2.0);
+        assert_eq!(next_temperature(&mut client).await, 3.0);
+        assert_eq!(next_temperature(&mut client).await, 4.0);
+    }
+
+    #[tokio::test]
+    async fn throttle_sends_the_newest_reading_once_the_interval_ends() {
+        let server = serve().await;
+        let mut client = server.connect("throttle=1s").await;
+
+        server.state.readings.send(reading(1.0)).unwrap();
+        assert_eq!(next_temperature(&mut client).await, 1.0);
+        let sent = std::time::Instant::now();
+        for temperature in [2.0, 3.0, 4.0] {
+            server.state.readings.send(reading(temperature)).unwrap();
+        }
+        assert_eq!(next_temperature(&mut client).await, 4.0);
+        assert!(sent.elapsed() >= Duration::from_millis(900), "sent after {:?}", sent.elapsed());
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_subscribers_with_going_away() {
+        let server = serve().await;
+        let mut client = server.connect("").await;
+        server.stop.send(true).unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next()).await.expect("no close frame within 5 seconds");
+        let ClientMessage::Close(Some(frame)) = message.unwrap().unwrap() else {
+            panic!("expected a close frame");
+        };
+        assert_eq!(frame.code, CloseCode::Away);
+        assert_eq!(u16::from(frame.code), CLOSE_GOING_AWAY);
+    }
+
+    #[tokio::test]
+    async fn malformed_throttle_is_a_bad_request() {
+        let server = serve().await;
+        let error = tokio_tungstenite::connect_async(format!("ws://{}/stream?throttle=soon", server.address)).await.unwrap_err();
+        let tokio_tungstenite::tungstenite::Error::Http(response) = error else {
+            panic!("expected an HTTP error, got {}", error);
+        };
+        assert_eq!(response.status(), 400);
+    }
+}
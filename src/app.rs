@@ -1,71 +1,115 @@
 use eframe::egui;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use crate::data::TelemetrySystem;
+use crate::alerts::{AlertEvent, AlertStatus};
+use crate::config::Config;
+use crate::data::{TelemetrySystem, WeatherData};
+use crate::export::export;
+use crate::import::{read_file, ImportReport};
+use crate::history::WeatherHistory;
+use crate::rain::RainReport;
+use crate::rollup::Aggregate;
+use crate::station::Station;
 use crate::telemetry::TelemetryCollector;
-use crate::ui::DashboardUI;
+use crate::ui::widgets::SettingsWidget;
+use crate::ui::{DashboardData, DashboardUI};
+
+const MAX_CHART_POINTS: usize = 1000;
+// Imported readings stored per hold of the telemetry lock.
+const IMPORT_BATCH: usize = 2000;
+
+// What the dashboard draws, rebuilt only when new data arrives or the time range changes
+// rather than copied out of the telemetry system every frame.
+struct ChartView {
+    version: u64,
+    hours: u32,
+    history: Arc<WeatherHistory>,
+    series: Vec<WeatherData>,
+    series_is_raw: bool,
+    summary: Option<Aggregate>,
+    rain: RainReport,
+    alerts: Vec<AlertStatus>,
+    alert_history: Vec<AlertEvent>,
+}
 
 pub struct WeatherApp {
+    station: Station,
     telemetry_system: Arc<Mutex<TelemetrySystem>>,
+    collector: Arc<Mutex<TelemetryCollector>>,
     dashboard_ui: DashboardUI,
+    view: Option<ChartView>,
     runtime: Arc<tokio::runtime::Runtime>,
+    export_task: Option<tokio::task::JoinHandle<anyhow::Result<(usize, PathBuf)>>>,
+    import_task: Option<tokio::task::JoinHandle<anyhow::Result<ImportReport>>>,
 }
 
 impl WeatherApp {
-    pub fn new() -> Self {
+    pub fn new(config: &Config, settings_widget: SettingsWidget) -> Self {
         let runtime = Arc::new(
             tokio::runtime::Runtime::new()
                 .expect("Failed to create tokio runtime")
         );
 
-        let telemetry_system = Arc::new(Mutex::new(TelemetrySystem::new()));
-        let dashboard_ui = DashboardUI::new();
-
-        let (collector, mut weather_rx) = TelemetryCollector::new();
-
-        let telemetry_clone = telemetry_system.clone();
-        runtime.spawn(async move {
-            while let Some(data) = weather_rx.recv().await {
-                let mut system = telemetry_clone.lock().await;
-                system.add_weather_data(data);
-            }
-        });
-
-        runtime.spawn(async move {
-            if let Err(e) = collector.start_weather_simulation().await {
-                tracing::error!("Failed to start weather simulation: {}", e);
-            }
-        });
+        let mut station = Station::new(config);
+        {
+            let _guard = runtime.enter();
+            station.start();
+        }
 
         Self {
-            telemetry_system,
-            dashboard_ui,
+            telemetry_system: station.telemetry_system(),
+            collector: station.collector(),
+            station,
+            dashboard_ui: DashboardUI::new(config, settings_widget),
+            view: None,
             runtime,
+            export_task: None,
+            import_task: None,
         }
     }
-
 }
 
 impl eframe::App for WeatherApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let telemetry_data = {
-            if let Ok(system) = self.telemetry_system.try_lock() {
-                Some((
-                    system.get_latest_weather().cloned(),
-                    system.weather_history.clone(),
-                ))
-            } else {
-                None
+        let hours = self.dashboard_ui.selected_hours();
+        if let Ok(system) = self.telemetry_system.try_lock() {
+            let stale = self.view
+                .as_ref()
+                .is_none_or(|view| view.version != system.version() || view.hours != hours);
+            if stale {
+                self.view = Some(ChartView {
+                    version: system.version(),
+                    hours,
+                    history: system.snapshot(),
+                    series: system.get_weather_series(hours, MAX_CHART_POINTS),
+                    series_is_raw: system.series_is_raw(hours, MAX_CHART_POINTS),
+                    summary: system.get_stats(hours),
+                    rain: system.rain_report(hours),
+                    alerts: system.alert_statuses(),
+                    alert_history: system.alert_history(),
+                });
             }
-        };
+        }
+
+        let source_health = self.collector
+            .try_lock()
+            .map(|collector| collector.source_health())
+            .unwrap_or_default();
 
-        if let Some((weather, weather_history)) = telemetry_data {
-            self.dashboard_ui.render(
-                ctx,
-                weather.as_ref(),
-                &weather_history,
-            );
+        if let Some(view) = &self.view {
+            self.dashboard_ui.render(ctx, &DashboardData {
+                current: view.history.latest(),
+                recent: &view.history,
+                history: &view.series,
+                history_is_raw: view.series_is_raw,
+                summary: view.summary.as_ref(),
+                rain: &view.rain,
+                alerts: &view.alerts,
+                alert_history: &view.alert_history,
+                source_health: &source_health,
+            });
         } else {
             egui::CentralPanel::default().show(ctx, |ui| {
                 ui.centered_and_justified(|ui| {
@@ -74,6 +118,67 @@ impl eframe::App for WeatherApp {
             });
         }
 
+        if let Some(config) = self.dashboard_ui.take_saved_config() {
+            let mut system = self.runtime.block_on(self.telemetry_system.lock());
+            system.set_storm_gap(chrono::Duration::hours(config.rain.storm_gap_hours));
+            system.set_rain_timezone(config.rain.timezone());
+            system.set_units(config.units);
+        }
+
+        // Exports query the store and write the file off the UI thread.
+        if let Some(request) = self.dashboard_ui.take_export_request() {
+            let telemetry_system = self.telemetry_system.clone();
+            self.export_task = Some(self.runtime.spawn(async move {
+                let readings = telemetry_system.lock().await.get_weather_between(request.from, request.to);
+                tokio::task::spawn_blocking(move || {
+                    let count = export(&readings, &request.options, &request.path)?;
+                    Ok((count, request.path))
+                }).await?
+            }));
+        }
+        if self.export_task.as_ref().is_some_and(|task| task.is_finished()) {
+            if let Some(task) = self.export_task.take() {
+                let result = self.runtime.block_on(task).unwrap_or_else(|e| Err(e.into()));
+                self.dashboard_ui.export_finished(result);
+            }
+        }
+
+        // Files are parsed off the UI thread, then stored and folded into the charts.
+        if let Some(request) = self.dashboard_ui.take_import_request() {
+            let telemetry_system = self.telemetry_system.clone();
+            self.import_task = Some(self.runtime.spawn(async move {
+                let progress = request.progress;
+                let (readings, mut report) = tokio::task::spawn_blocking(move || {
+                    read_file(&request.path, &request.options, &mut |update| {
+                        if let Ok(mut progress) = progress.lock() {
+                            *progress = update;
+                        }
+                    })
+                }).await??;
+                // In batches, so live readings and the charts get the lock in between.
+                let mut imported = 0;
+                for batch in readings.chunks(IMPORT_BATCH) {
+                    imported += telemetry_system.lock().await.import(batch)?;
+                    tokio::task::yield_now().await;
+                }
+                if let Some(newest) = readings.iter().map(|data| data.timestamp).max().filter(|_| imported > 0) {
+                    telemetry_system.lock().await.refresh_rain(newest)?;
+                }
+                report.record_stored(imported);
+                Ok(report)
+            }));
+        }
+        if self.import_task.as_ref().is_some_and(|task| task.is_finished()) {
+            if let Some(task) = self.import_task.take() {
+                let result = self.runtime.block_on(task).unwrap_or_else(|e| Err(e.into()));
+                self.dashboard_ui.import_finished(result);
+            }
+        }
+
         ctx.request_repaint();
     }
-}
\ No newline at end of file
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.runtime.block_on(self.station.shutdown());
+    }
+}
@@ -0,0 +1,480 @@
+use crate::alerts::notify::NotificationConfig;
+use crate::alerts::{default_rules, AlertRule};
+use crate::data::DEFAULT_HISTORY_SIZE;
+use crate::timezone::DisplayTimezone;
+use crate::rain::DEFAULT_STORM_GAP_HOURS;
+use crate::rollup::{default_tiers, TierConfig};
+use crate::sources::{SimulatorConfig, SourceConfig};
+use crate::units::UnitPreferences;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table};
+
+/// Time ranges the dashboard offers, in hours.
+pub const TIME_RANGE_HOURS: [u32; 4] = [1, 6, 24, 168];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// SQLite database every reading is written to.
+    pub database: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            database: "weather.db".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// Raw readings kept in memory for charts of recent data.
+    pub history_size: usize,
+    /// Rollup tiers; longer chart ranges are drawn from the coarsest tier that fits.
+    pub tiers: Vec<TierConfig>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            history_size: DEFAULT_HISTORY_SIZE,
+            tiers: default_tiers(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RainConfig {
+    /// A dry spell this long ends a storm.
+    pub storm_gap_hours: i64,
+    /// `local`, `utc` or an offset like `+05:30`; days, months and years start at its midnight.
+    pub timezone: String,
+}
+
+impl Default for RainConfig {
+    fn default() -> Self {
+        Self {
+            storm_gap_hours: DEFAULT_STORM_GAP_HOURS,
+            timezone: "local".to_string(),
+        }
+    }
+}
+
+impl RainConfig {
+    /// The configured zone; `validate` has rejected anything that does not parse.
+    pub fn timezone(&self) -> DisplayTimezone {
+        self.timezone.parse().unwrap_or(DisplayTimezone::Local)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    /// Time range selected when the dashboard opens; one of `TIME_RANGE_HOURS`.
+    pub time_range_hours: u32,
+    pub window_width: f32,
+    pub window_height: f32,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            time_range_hours: 6,
+            window_width: 1400.0,
+            window_height: 900.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Serves readings over HTTP; see [`crate::api`].
+    pub enabled: bool,
+    /// Address to listen on; use `0.0.0.0:8080` to reach it from other machines.
+    pub bind: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:8080".to_string(),
+        }
+    }
+}
+
+/// Everything the station reads from `weather.toml`. Missing keys fall back to the
+/// defaults, so an empty file runs the simulator with the built-in alert rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub storage: StorageConfig,
+    pub retention: RetentionConfig,
+    pub rain: RainConfig,
+    pub ui: UiConfig,
+    pub units: UnitPreferences,
+    pub api: ApiConfig,
+    pub sources: Vec<SourceConfig>,
+    pub alerts: Vec<AlertRule>,
+    pub notifications: NotificationConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            storage: StorageConfig::default(),
+            retention: RetentionConfig::default(),
+            rain: RainConfig::default(),
+            ui: UiConfig::default(),
+            units: UnitPreferences::default(),
+            api: ApiConfig::default(),
+            sources: vec![SourceConfig::Simulator(SimulatorConfig::default())],
+            alerts: default_rules(),
+            notifications: NotificationConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads `path`, or returns the defaults if it does not exist yet. Syntax errors and
+    /// unknown keys are reported with the line they occur on.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            tracing::info!("No config file at {}, using defaults", path.display());
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&contents).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    /// Writes the config to `path`. An existing file is edited in place: only keys whose
+    /// value changed are touched, so comments, key order and keys left at their defaults
+    /// stay as they are. The new file replaces the old one in a single rename.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        let contents = match std::fs::read_to_string(path) {
+            Ok(existing) => self.edit(&existing).map_err(|e| anyhow!("{}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::to_string_pretty(self)?,
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        write_atomically(path, &contents).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// The sections that differ from `running` and are only read at startup. Rain and unit
+    /// changes are applied while running, and the default time range is just a default.
+    pub fn restart_needed(&self, running: &Config) -> Vec<&'static str> {
+        [
+            ("storage", self.storage != running.storage),
+            ("retention", self.retention != running.retention),
+            ("window size", (self.ui.window_width, self.ui.window_height) != (running.ui.window_width, running.ui.window_height)),
+            ("sources", !same_toml(&self.sources, &running.sources)),
+            ("alerts", !same_toml(&self.alerts, &running.alerts)),
+            ("notifications", !same_toml(&self.notifications, &running.notifications)),
+            ("API", self.api != running.api),
+        ]
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(section, _)| section)
+        .collect()
+    }
+
+    // `existing` with every value that differs between what it currently means and `self` updated.
+    fn edit(&self, existing: &str) -> Result<String> {
+        let mut document: DocumentMut = existing.parse()?;
+        let current: Config = toml::from_str(existing)?;
+        let old: DocumentMut = toml::to_string(&current)?.parse()?;
+        let new: DocumentMut = toml::to_string(self)?.parse()?;
+        update_table(document.as_table_mut(), Some(old.as_table()), new.as_table());
+        Ok(document.to_string())
+    }
+
+    /// Checks values that parse but make no sense. Errors start with the offending key,
+    /// e.g. `retention.history_size: must be at least 1`.
+    pub fn validate(&self) -> Result<()> {
+        if self.storage.database.trim().is_empty() {
+            bail!("storage.database: must not be empty");
+        }
+
+        if self.retention.history_size == 0 {
+            bail!("retention.history_size: must be at least 1");
+        }
+        for (i, tier) in self.retention.tiers.iter().enumerate() {
+            if tier.resolution_secs == 0 {
+                bail!("retention.tiers[{}].resolution_secs: must be at least 1", i);
+            }
+            if tier.retention_hours.saturating_mul(3600) < tier.resolution_secs {
+                bail!("retention.tiers[{}].retention_hours: must cover at least one bucket", i);
+            }
+        }
+
+        if self.rain.storm_gap_hours < 1 {
+            bail!("rain.storm_gap_hours: must be at least 1");
+        }
+        self.rain.timezone.parse::<DisplayTimezone>().map_err(|e| anyhow!("rain.timezone: {}", e))?;
+
+        if !TIME_RANGE_HOURS.contains(&self.ui.time_range_hours) {
+            bail!("ui.time_range_hours: must be one of {:?}", TIME_RANGE_HOURS);
+        }
+        if self.ui.window_width <= 0.0 {
+            bail!("ui.window_width: must be positive");
+        }
+        if self.ui.window_height <= 0.0 {
+            bail!("ui.window_height: must be positive");
+        }
+
+        if self.api.bind.parse::<SocketAddr>().is_err() {
+            bail!("api.bind: '{}' is not an address like 127.0.0.1:8080", self.api.bind);
+        }
+
+        if self.sources.is_empty() {
+            bail!("sources: at least one source is required");
+        }
+        for (i, source) in self.sources.iter().enumerate() {
+            match source {
+                SourceConfig::Simulator(config) => {
+                    if config.interval_secs == 0 {
+                        bail!("sources[{}].interval_secs: must be at least 1", i);
+                    }
+                    if !(-90.0..=90.0).contains(&config.latitude) {
+                        bail!("sources[{}].latitude: must be between -90 and 90", i);
+                    }
+                    if !(-180.0..=180.0).contains(&config.longitude) {
+                        bail!("sources[{}].longitude: must be between -180 and 180", i);
+                    }
+                    if config.speed <= 0.0 {
+                        bail!("sources[{}].speed: must be positive", i);
+                    }
+                }
+                SourceConfig::Ecowitt(config) => config.validate().map_err(|e| anyhow!("sources[{}].{}", i, e))?,
+                _ => {}
+            }
+        }
+
+        for (i, rule) in self.alerts.iter().enumerate() {
+            rule.validate().map_err(|e| anyhow!("alerts[{}].{}", i, e))?;
+        }
+        self.notifications.validate().map_err(|e| anyhow!("notifications.{}", e))?;
+        Ok(())
+    }
+}
+
+fn update_table(document: &mut Table, old: Option<&Table>, new: &Table) {
+    for (key, new_item) in new.iter() {
+        let old_item = old.and_then(|old| old.get(key));
+        match document.get_mut(key) {
+            Some(item) => update_item(item, old_item, new_item),
+            // Not in the file yet: add only what differs from the default it stood for.
+            None if new_item.is_table() => {
+                let mut item = Item::Table(Table::new());
+                update_item(&mut item, old_item, new_item);
+                if item.as_table().is_some_and(|table| !table.is_empty()) {
+                    document.insert(key, item);
+                }
+            }
+            None if !old_item.is_some_and(|old_item| same(old_item, new_item)) => {
+                document.insert(key, new_item.clone());
+            }
+            None => {}
+        }
+    }
+    // Keys the config no longer serializes, such as options that were cleared.
+    let cleared: Vec<String> = document.iter().map(|(key, _)| key.to_string()).filter(|key| !new.contains_key(key)).collect();
+    for key in cleared {
+        document.remove(&key);
+    }
+}
+
+fn update_item(document: &mut Item, old: Option<&Item>, new: &Item) {
+    if old.is_some_and(|old| same(old, new)) {
+        return;
+    }
+    // A plain or inline value keeps the comment and spacing around it.
+    if let Item::Value(value) = document {
+        if let Ok(mut replacement) = new.clone().into_value() {
+            *replacement.decor_mut() = value.decor().clone();
+            *value = replacement;
+            return;
+        }
+    }
+    match (document, new) {
+        (Item::Table(table), Item::Table(new)) => update_table(table, old.and_then(Item::as_table), new),
+        (Item::ArrayOfTables(tables), Item::ArrayOfTables(new)) if tables.len() == new.len() => {
+            let old = old.and_then(Item::as_array_of_tables);
+            for (i, new) in new.iter().enumerate() {
+                if let Some(table) = tables.get_mut(i) {
+                    update_table(table, old.and_then(|old| old.get(i)), new);
+                }
+            }
+        }
+        (document, new) => *document = new.clone(),
+    }
+}
+
+// Both sides come from the same serializer, so equal values print the same.
+fn same(a: &Item, b: &Item) -> bool {
+    a.to_string().trim() == b.to_string().trim()
+}
+
+// Written next to `path` and renamed over it, so a crash never leaves a half-written file.
+fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let temporary = path.with_file_name(format!(".{}.tmp", name));
+    let mut file = std::fs::File::create(&temporary)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&temporary, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
+
+// Sources, alerts and notifications have no `PartialEq`, so their TOML is compared.
+fn same_toml<T: Serialize>(a: &T, b: &T) -> bool {
+    toml::Value::try_from(a).ok() == toml::Value::try_from(b).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXISTING: &str = r#"# Station on the shed roof
+[storage]
+database = "shed.db"   # next to the binary
+
+[rain]
+# Long dry spells here, so storms are split late.
+storm_gap_hours = 12
+
+[[sources]]
+type = "simulator"
+name = "sim"
+interval_secs = 5      # fast for testing
+"#;
+
+    // A scratch directory removed again when the test ends.
+    struct Scratch(std::path::PathBuf);
+
+    impl Scratch {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("weather-config-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn assert_error_key(key: &str, change: impl FnOnce(&mut Config)) {
+        let mut config = Config::default();
+        change(&mut config);
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.starts_with(key), "'{}' does not start with '{}'", error, key);
+    }
+
+    #[test]
+    fn validate_names_the_offending_key() {
+        assert!(Config::default().validate().is_ok());
+        assert_error_key("storage.database:", |config| config.storage.database = " ".to_string());
+        assert_error_key("retention.history_size:", |config| config.retention.history_size = 0);
+        assert_error_key("retention.tiers[1].retention_hours:", |config| config.retention.tiers[1].retention_hours = 0);
+        assert_error_key("rain.storm_gap_hours:", |config| config.rain.storm_gap_hours = 0);
+        assert_error_key("rain.timezone:", |config| config.rain.timezone = "Mars/Olympus".to_string());
+        assert_error_key("ui.time_range_hours:", |config| config.ui.time_range_hours = 5);
+        assert_error_key("api.bind:", |config| config.api.bind = "localhost".to_string());
+        assert_error_key("sources:", |config| config.sources.clear());
+        assert_error_key("sources[0].latitude:", |config| {
+            if let SourceConfig::Simulator(simulator) = &mut config.sources[0] {
+                simulator.latitude = 91.0;
+            }
+        });
+        assert_error_key("alerts[2].for_secs:", |config| config.alerts[2].for_secs = -1);
+    }
+
+    #[test]
+    fn save_edits_the_file_in_place() {
+        let scratch = Scratch::new();
+        let path = scratch.0.join("weather.toml");
+        std::fs::write(&path, EXISTING).unwrap();
+        let mut config = Config::load(&path).unwrap();
+        config.rain.storm_gap_hours = 6;
+        if let SourceConfig::Simulator(simulator) = &mut config.sources[0] {
+            simulator.interval_secs = 10;
+        }
+        config.save(&path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(saved, EXISTING.replace("storm_gap_hours = 12", "storm_gap_hours = 6").replace("interval_secs = 5", "interval_secs = 10"));
+        assert_eq!(Config::load(&path).unwrap().rain.storm_gap_hours, 6);
+        // Written beside the file and renamed over it.
+        let leftovers: Vec<_> = std::fs::read_dir(path.parent().unwrap()).unwrap().collect();
+        assert_eq!(leftovers.len(), 1);
+    }
+
+    #[test]
+    fn save_adds_changed_defaults_only() {
+        let scratch = Scratch::new();
+        let path = scratch.0.join("weather.toml");
+        std::fs::write(&path, EXISTING).unwrap();
+        let mut config = Config::load(&path).unwrap();
+        config.api.enabled = true;
+        config.save(&path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.starts_with(EXISTING));
+        assert_eq!(&saved[EXISTING.len()..], "\n[api]\nenabled = true\n");
+    }
+
+    #[test]
+    fn restart_is_needed_for_sections_read_at_startup_only() {
+        let running = Config::default();
+        let mut config = running.clone();
+        config.rain.storm_gap_hours = 3;
+        config.units.temperature = crate::units::TemperatureUnit::Fahrenheit;
+        config.ui.time_range_hours = 48;
+        assert!(config.restart_needed(&running).is_empty());
+
+        config.retention.history_size = 10;
+        config.alerts.pop();
+        if let SourceConfig::Simulator(simulator) = &mut config.sources[0] {
+            simulator.interval_secs = 10;
+        }
+        assert_eq!(config.restart_needed(&running), ["retention", "sources", "alerts"]);
+    }
+
+    #[test]
+    fn save_writes_a_new_file_in_full() {
+        let scratch = Scratch::new();
+        let path = scratch.0.join("new.toml");
+        let mut config = Config::default();
+        config.rain.timezone = "+05:30".to_string();
+        config.save(&path).unwrap();
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.rain, config.rain);
+        assert_eq!(saved.sources.len(), 1);
+        assert_eq!(saved.alerts.len(), config.alerts.len());
+    }
+
+    #[test]
+    fn invalid_config_is_not_saved() {
+        let scratch = Scratch::new();
+        let path = scratch.0.join("weather.toml");
+        std::fs::write(&path, EXISTING).unwrap();
+        let mut config = Config::load(&path).unwrap();
+        config.rain.storm_gap_hours = 0;
+        assert!(config.save(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), EXISTING);
+    }
+}
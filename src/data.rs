@@ -1,22 +1,49 @@
-use chrono::{DateTime, Utc};
+use crate::alerts::{AlertEngine, AlertEvent, AlertRule, AlertStatus};
+use crate::timezone::DisplayTimezone;
+use crate::history::WeatherHistory;
+use crate::rain::{RainAccumulator, RainReport, RainTotals, DEFAULT_STORM_GAP_HOURS};
+use crate::rollup::{default_tiers, Aggregate, Rollups, TierConfig};
+use crate::storage::WeatherStore;
+use crate::units::{Dimension, UnitPreferences};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use uuid::Uuid;
 
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// One reading in metric units: °C, %, hPa, m/s, degrees, mm/h, UV index and W/m².
+///
+/// A field no sensor has reported is NaN. It is stored as NULL, serialized as `null`,
+/// and skipped by statistics, rollups, charts and alerts.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WeatherData {
+    #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub timestamp: DateTime<Utc>,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub temperature: f32,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub humidity: f32,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub pressure: f32,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub wind_speed: f32,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub wind_direction: f32,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub rain_rate: f32,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub uv_index: f32,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub solar_radiation: f32,
+    #[serde(default)]
+    pub source: String,
 }
 
 impl WeatherData {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         temperature: f32,
         humidity: f32,
@@ -38,70 +65,489 @@ impl WeatherData {
             rain_rate,
             uv_index,
             solar_radiation,
+            source: String::new(),
         }
     }
+
+    /// Whether `metric` was measured, i.e. is not NaN.
+    pub fn has(&self, metric: Metric) -> bool {
+        !metric.value(self).is_nan()
+    }
+
+    /// For readings whose time is known, e.g. from a recording or a station's own clock.
+    pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+}
+
+/// A single field of [`WeatherData`], used to pick what to chart or alert on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Temperature,
+    Humidity,
+    Pressure,
+    WindSpeed,
+    WindDirection,
+    RainRate,
+    UvIndex,
+    SolarRadiation,
 }
 
+impl Metric {
+    pub const ALL: [Metric; 8] = [
+        Metric::Temperature,
+        Metric::Humidity,
+        Metric::Pressure,
+        Metric::WindSpeed,
+        Metric::WindDirection,
+        Metric::RainRate,
+        Metric::UvIndex,
+        Metric::SolarRadiation,
+    ];
+
+    /// The field name, as used in JSON and the config file.
+    pub fn key(self) -> &'static str {
+        match self {
+            Metric::Temperature => "temperature",
+            Metric::Humidity => "humidity",
+            Metric::Pressure => "pressure",
+            Metric::WindSpeed => "wind_speed",
+            Metric::WindDirection => "wind_direction",
+            Metric::RainRate => "rain_rate",
+            Metric::UvIndex => "uv_index",
+            Metric::SolarRadiation => "solar_radiation",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Metric> {
+        Metric::ALL.into_iter().find(|metric| metric.key() == key)
+    }
+
+    pub fn dimension(self) -> Dimension {
+        match self {
+            Metric::Temperature => Dimension::Temperature,
+            Metric::Humidity => Dimension::Fixed("%"),
+            Metric::Pressure => Dimension::Pressure,
+            Metric::WindSpeed => Dimension::Speed,
+            Metric::WindDirection => Dimension::Fixed("°"),
+            Metric::RainRate => Dimension::RainRate,
+            Metric::UvIndex => Dimension::Fixed(""),
+            Metric::SolarRadiation => Dimension::Fixed("W/m²"),
+        }
+    }
+
+    pub fn value(self, data: &WeatherData) -> f32 {
+        match self {
+            Metric::Temperature => data.temperature,
+            Metric::Humidity => data.humidity,
+            Metric::Pressure => data.pressure,
+            Metric::WindSpeed => data.wind_speed,
+            Metric::WindDirection => data.wind_direction,
+            Metric::RainRate => data.rain_rate,
+            Metric::UvIndex => data.uv_index,
+            Metric::SolarRadiation => data.solar_radiation,
+        }
+    }
+}
+
+/// A reading where any field may be missing, as produced by feeds that publish
+/// sensors separately. Missing fields are carried forward from the previous reading.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartialReading {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub temperature: Option<f32>,
+    pub humidity: Option<f32>,
+    pub pressure: Option<f32>,
+    pub wind_speed: Option<f32>,
+    pub wind_direction: Option<f32>,
+    pub rain_rate: Option<f32>,
+    pub uv_index: Option<f32>,
+    pub solar_radiation: Option<f32>,
+}
+
+impl PartialReading {
+    pub fn is_empty(&self) -> bool {
+        self.temperature.is_none()
+            && self.humidity.is_none()
+            && self.pressure.is_none()
+            && self.wind_speed.is_none()
+            && self.wind_direction.is_none()
+            && self.rain_rate.is_none()
+            && self.uv_index.is_none()
+            && self.solar_radiation.is_none()
+    }
+
+    pub fn get(&self, metric: Metric) -> Option<f32> {
+        match metric {
+            Metric::Temperature => self.temperature,
+            Metric::Humidity => self.humidity,
+            Metric::Pressure => self.pressure,
+            Metric::WindSpeed => self.wind_speed,
+            Metric::WindDirection => self.wind_direction,
+            Metric::RainRate => self.rain_rate,
+            Metric::UvIndex => self.uv_index,
+            Metric::SolarRadiation => self.solar_radiation,
+        }
+    }
+
+    pub fn get_mut(&mut self, metric: Metric) -> &mut Option<f32> {
+        match metric {
+            Metric::Temperature => &mut self.temperature,
+            Metric::Humidity => &mut self.humidity,
+            Metric::Pressure => &mut self.pressure,
+            Metric::WindSpeed => &mut self.wind_speed,
+            Metric::WindDirection => &mut self.wind_direction,
+            Metric::RainRate => &mut self.rain_rate,
+            Metric::UvIndex => &mut self.uv_index,
+            Metric::SolarRadiation => &mut self.solar_radiation,
+        }
+    }
 
-#[derive(Debug, Clone)]
+    /// Fields present in `other` win.
+    pub fn merge(&mut self, other: PartialReading) {
+        self.timestamp = other.timestamp.or(self.timestamp);
+        self.temperature = other.temperature.or(self.temperature);
+        self.humidity = other.humidity.or(self.humidity);
+        self.pressure = other.pressure.or(self.pressure);
+        self.wind_speed = other.wind_speed.or(self.wind_speed);
+        self.wind_direction = other.wind_direction.or(self.wind_direction);
+        self.rain_rate = other.rain_rate.or(self.rain_rate);
+        self.uv_index = other.uv_index.or(self.uv_index);
+        self.solar_radiation = other.solar_radiation.or(self.solar_radiation);
+    }
+
+    /// Fields missing here and in `previous` are NaN, i.e. not measured.
+    pub fn complete(&self, previous: Option<&WeatherData>) -> WeatherData {
+        let fill = |value: Option<f32>, field: fn(&WeatherData) -> f32| {
+            value.or_else(|| previous.map(field)).unwrap_or(f32::NAN)
+        };
+
+        let data = WeatherData::new(
+            fill(self.temperature, |d| d.temperature),
+            fill(self.humidity, |d| d.humidity),
+            fill(self.pressure, |d| d.pressure),
+            fill(self.wind_speed, |d| d.wind_speed),
+            fill(self.wind_direction, |d| d.wind_direction),
+            fill(self.rain_rate, |d| d.rain_rate),
+            fill(self.uv_index, |d| d.uv_index),
+            fill(self.solar_radiation, |d| d.solar_radiation),
+        );
+        match self.timestamp {
+            Some(timestamp) => data.with_timestamp(timestamp),
+            None => data,
+        }
+    }
+}
+
+/// Recent history, rollups, rain totals and alert state over every reading, optionally
+/// backed by a [`WeatherStore`] so they survive restarts.
 pub struct TelemetrySystem {
-    pub weather_history: Vec<WeatherData>,
-    pub max_history_size: usize,
+    // Copy-on-write: snapshots handed to readers stay valid while new data arrives.
+    weather_history: Arc<WeatherHistory>,
+    version: u64,
+    rollups: Rollups,
+    rain: RainAccumulator,
+    storm_gap: Duration,
+    alerts: AlertEngine,
+    units: UnitPreferences,
+    store: Option<Box<dyn WeatherStore>>,
 }
 
 impl TelemetrySystem {
     pub fn new() -> Self {
+        Self::with_retention(DEFAULT_HISTORY_SIZE, default_tiers())
+    }
+
+    /// Keeps the last `history_size` raw readings in memory and aggregates into `tiers`.
+    pub fn with_retention(history_size: usize, tiers: Vec<TierConfig>) -> Self {
         Self {
-            weather_history: Vec::new(),
-            max_history_size: 1000,
+            weather_history: Arc::new(WeatherHistory::new(history_size)),
+            version: 0,
+            rollups: Rollups::new(tiers),
+            rain: RainAccumulator::new(),
+            storm_gap: Duration::hours(DEFAULT_STORM_GAP_HOURS),
+            alerts: AlertEngine::new(Vec::new()),
+            units: UnitPreferences::default(),
+            store: None,
+        }
+    }
+
+    /// Persists every reading to `store` and warms the in-memory history from it.
+    pub fn with_store(mut self, store: Box<dyn WeatherStore>) -> Result<Self> {
+        let history = Arc::make_mut(&mut self.weather_history);
+        let recent = store.load_recent(history.capacity())?;
+        history.extend(recent);
+        self.rebuild_aggregates(store.as_ref())?;
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    fn rebuild_aggregates(&mut self, store: &dyn WeatherStore) -> Result<()> {
+        let now = Utc::now();
+        for_each_stored(store, now - self.rollups.max_retention(), now, |data| {
+            self.rollups.push(data);
+            self.rain.push(data);
+        })
+    }
+
+    /// Stores readings from another program's history that aren't stored yet for the same
+    /// source and time, and adds them to the rollups and recent history without evaluating
+    /// alerts. Returns how many were new. Large imports go in batches so live readings
+    /// aren't held up; call [`refresh_rain`](Self::refresh_rain) after the last one.
+    pub fn import(&mut self, readings: &[WeatherData]) -> Result<usize> {
+        let Some(store) = self.store.as_mut() else {
+            anyhow::bail!("there is no database to import into");
+        };
+        let inserted = store.insert_new(readings)?;
+        if inserted.is_empty() {
+            return Ok(0);
+        }
+
+        let history = Arc::make_mut(&mut self.weather_history);
+        for data in &inserted {
+            self.rollups.push(data);
+            history.push((*data).clone());
+        }
+
+        self.version += 1;
+        Ok(inserted.len())
+    }
+
+    /// Brings the rain totals up to date with imported readings up to `newest`. The rain
+    /// accumulator skips readings older than its newest one, so a backfill within its
+    /// window means integrating that window again.
+    pub fn refresh_rain(&mut self, newest: DateTime<Utc>) -> Result<()> {
+        let Some(store) = self.store.as_deref() else {
+            return Ok(());
+        };
+        let latest = self.rain.latest().map_or(newest, |latest| latest.max(newest));
+        let from = latest - RainAccumulator::retention();
+        if newest > from {
+            let mut rain = RainAccumulator::with_timezone(self.rain.timezone());
+            for_each_stored(store, from, latest.max(Utc::now()), |data| rain.push(data))?;
+            self.rain = rain;
+            self.version += 1;
         }
+        Ok(())
     }
 
-    pub fn add_weather_data(&mut self, data: WeatherData) {
-        self.weather_history.push(data);
-        if self.weather_history.len() > self.max_history_size {
-            self.weather_history.remove(0);
+    /// Returns the alert state changes the reading caused.
+    pub fn add_weather_data(&mut self, data: WeatherData) -> Vec<AlertEvent> {
+        if let Some(store) = self.store.as_mut() {
+            if let Err(e) = store.insert(&data) {
+                tracing::error!("Failed to persist weather reading: {}", e);
+            }
+        }
+
+        self.rollups.push(&data);
+        self.rain.push(&data);
+        let rain = self.alerts.uses_rain().then(|| self.rain_totals());
+        let events = self.alerts.evaluate(&data, rain.as_ref());
+        Arc::make_mut(&mut self.weather_history).push(data);
+        self.version += 1;
+        events
+    }
+
+    pub fn flush(&mut self) {
+        if let Some(store) = self.store.as_mut() {
+            if let Err(e) = store.flush() {
+                tracing::error!("Failed to flush weather store: {}", e);
+            }
         }
     }
 
     pub fn get_latest_weather(&self) -> Option<&WeatherData> {
-        self.weather_history.last()
+        self.weather_history.latest()
     }
 
-    pub fn get_weather_range(&self, hours: u32) -> Vec<&WeatherData> {
-        let cutoff = Utc::now() - chrono::Duration::hours(hours as i64);
-        self.weather_history
-            .iter()
-            .filter(|data| data.timestamp > cutoff)
-            .collect()
+    pub fn snapshot(&self) -> Arc<WeatherHistory> {
+        self.weather_history.clone()
     }
 
-    pub fn get_average_temperature(&self, hours: u32) -> Option<f32> {
-        let data = self.get_weather_range(hours);
-        if data.is_empty() {
-            return None;
+    /// Bumped on every new reading so readers can skip recomputing unchanged views.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn set_alert_rules(&mut self, rules: Vec<AlertRule>) {
+        self.alerts = AlertEngine::new(rules);
+        self.alerts.set_units(self.units);
+    }
+
+    /// Units for alert descriptions and messages; stored readings are always SI.
+    pub fn set_units(&mut self, units: UnitPreferences) {
+        self.units = units;
+        self.alerts.set_units(units);
+        self.version += 1;
+    }
+
+    pub fn alert_statuses(&self) -> Vec<AlertStatus> {
+        self.alerts.statuses()
+    }
+
+    pub fn alert_history(&self) -> Vec<AlertEvent> {
+        self.alerts.history()
+    }
+
+    /// How long it has to stay dry before a storm is considered over.
+    pub fn set_storm_gap(&mut self, gap: Duration) {
+        self.storm_gap = gap;
+    }
+
+    /// The zone whose midnights start rain days, months and years.
+    pub fn set_rain_timezone(&mut self, timezone: DisplayTimezone) {
+        self.rain.set_timezone(timezone);
+    }
+
+    pub fn rain_totals(&self) -> RainTotals {
+        self.rain.totals(self.storm_gap)
+    }
+
+    pub fn rain_report(&self, hours: u32) -> RainReport {
+        let (bucket_secs, buckets) = self.rain.buckets(hours);
+        RainReport {
+            totals: self.rain_totals(),
+            bucket_secs,
+            buckets,
         }
-        let sum: f32 = data.iter().map(|d| d.temperature).sum();
-        Some(sum / data.len() as f32)
     }
 
-    pub fn get_average_humidity(&self, hours: u32) -> Option<f32> {
-        let data = self.get_weather_range(hours);
-        if data.is_empty() {
-            return None;
+    // Start of the window covering the last `hours` of data, anchored on the newest reading.
+    fn range_cutoff(&self, hours: u32) -> DateTime<Utc> {
+        let latest = self.get_latest_weather().map(|data| data.timestamp).unwrap_or_else(Utc::now);
+        latest - Duration::hours(hours as i64)
+    }
+
+    // Raw readings are used while they cover the window (or are all there is) and fit in
+    // `max_points`; otherwise the finest rollup tier that does.
+    fn raw_covers(&self, hours: u32, max_points: usize) -> Option<Vec<&WeatherData>> {
+        let cutoff = self.range_cutoff(hours);
+        let raw: Vec<&WeatherData> = self.weather_history.since(cutoff).collect();
+        let oldest_raw = self.weather_history.oldest().map(|data| data.timestamp);
+        let covers_span = oldest_raw.is_some_and(|oldest| oldest <= cutoff);
+        let tier_has_older = self.rollups
+            .select_tier(Duration::hours(hours as i64), max_points)
+            .is_some_and(|tier| {
+                tier.range(cutoff, Utc::now())
+                    .next()
+                    .is_some_and(|bucket| oldest_raw.is_none_or(|oldest| bucket.start + tier.resolution() <= oldest))
+            });
+
+        (raw.len() <= max_points && (covers_span || !tier_has_older)).then_some(raw)
+    }
+
+    /// Chart-sized series for the last `hours`: raw readings or rollup means.
+    pub fn get_weather_series(&self, hours: u32, max_points: usize) -> Vec<WeatherData> {
+        if let Some(raw) = self.raw_covers(hours, max_points) {
+            return raw.into_iter().cloned().collect();
+        }
+
+        let cutoff = self.range_cutoff(hours);
+        self.rollups
+            .select_tier(Duration::hours(hours as i64), max_points)
+            .map(|tier| {
+                tier.range(cutoff, Utc::now())
+                    .filter(|bucket| bucket.start >= cutoff)
+                    .map(Aggregate::to_weather_data)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether `get_weather_series` with the same arguments returns raw readings rather than rollup means.
+    pub fn series_is_raw(&self, hours: u32, max_points: usize) -> bool {
+        self.raw_covers(hours, max_points).is_some()
+    }
+
+    /// Min/max/mean/last of every field over the last `hours`, from raw data or rollups.
+    pub fn get_stats(&self, hours: u32) -> Option<Aggregate> {
+        const MAX_STATS_POINTS: usize = 5000;
+
+        if let Some(raw) = self.raw_covers(hours, MAX_STATS_POINTS) {
+            return Aggregate::from_readings(raw);
         }
-        let sum: f32 = data.iter().map(|d| d.humidity).sum();
-        Some(sum / data.len() as f32)
+
+        let cutoff = self.range_cutoff(hours);
+        let tier = self.rollups.select_tier(Duration::hours(hours as i64), MAX_STATS_POINTS)?;
+        let mut buckets = tier.range(cutoff, Utc::now()).filter(|bucket| bucket.start >= cutoff);
+        let mut aggregate = buckets.next()?.clone();
+        for bucket in buckets {
+            aggregate.merge(bucket);
+        }
+        Some(aggregate)
+    }
+
+    pub fn get_weather_range(&self, hours: u32) -> Vec<WeatherData> {
+        let now = Utc::now();
+        self.get_weather_between(now - Duration::hours(hours as i64), now)
+    }
+
+    /// Readings with `from < timestamp <= to`, from the store if there is one, otherwise
+    /// from the in-memory history.
+    pub fn get_weather_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<WeatherData> {
+        if let Some(store) = self.store.as_ref() {
+            match store.query_range(from, to) {
+                Ok(readings) => return readings,
+                Err(e) => tracing::error!("Failed to query stored weather history: {}", e),
+            }
+        }
+
+        self.weather_history.window(from, to).cloned().collect()
+    }
+
+    /// Min/max/mean/last of every field over every reading between `from` and `to`.
+    pub fn get_range_stats(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Option<Aggregate> {
+        if let Some(store) = self.store.as_ref() {
+            match store.range_stats(from, to) {
+                Ok(stats) => return stats,
+                Err(e) => tracing::error!("Failed to query stored weather statistics: {}", e),
+            }
+        }
+
+        Aggregate::from_readings(self.weather_history.window(from, to))
+    }
+
+    pub fn get_average_temperature(&self, hours: u32) -> Option<f32> {
+        self.get_recent_stats(hours).map(|stats| stats.mean(Metric::Temperature))
+    }
+
+    pub fn get_average_humidity(&self, hours: u32) -> Option<f32> {
+        self.get_recent_stats(hours).map(|stats| stats.mean(Metric::Humidity))
     }
 
     pub fn get_min_max_temperature(&self, hours: u32) -> Option<(f32, f32)> {
-        let data = self.get_weather_range(hours);
-        if data.is_empty() {
-            return None;
+        let stats = self.get_recent_stats(hours)?;
+        let temperature = stats.stats(Metric::Temperature);
+        Some((temperature.min, temperature.max))
+    }
+
+    fn get_recent_stats(&self, hours: u32) -> Option<Aggregate> {
+        let now = Utc::now();
+        self.get_range_stats(now - Duration::hours(hours as i64), now)
+    }
+}
+
+impl Default for TelemetrySystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Replays stored readings a day at a time so a long retention never has to fit in memory.
+fn for_each_stored(store: &dyn WeatherStore, mut from: DateTime<Utc>, until: DateTime<Utc>, mut f: impl FnMut(&WeatherData)) -> Result<()> {
+    while from < until {
+        let to = (from + Duration::days(1)).min(until);
+        for data in store.query_range(from, to)? {
+            f(&data);
         }
-        let temps: Vec<f32> = data.iter().map(|d| d.temperature).collect();
-        let min = temps.iter().fold(f32::INFINITY, |a, &b| a.min(b));
-        let max = temps.iter().fold(f32::NEG_INFINITY, |a, &b| a.max(b));
-        Some((min, max))
+        from = to;
     }
+    Ok(())
+}
+
+// `null` in JSON and an empty cell in CSV both mean the field was not measured.
+fn missing_as_nan<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    Ok(Option::<f32>::deserialize(deserializer)?.unwrap_or(f32::NAN))
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+use crate::data::WeatherData;
+use crate::units;
+
+// Magnus coefficients (Sonntag 1990) over water, valid from -45 °C to 60 °C.
+const MAGNUS_A: f32 = 17.62;
+const MAGNUS_B: f32 = 243.12;
+
+/// Saturation vapour pressure in hPa.
+pub fn saturation_vapor_pressure(temperature_c: f32) -> f32 {
+    6.112 * (MAGNUS_A * temperature_c / (MAGNUS_B + temperature_c)).exp()
+}
+
+pub fn dew_point(temperature_c: f32, humidity: f32) -> f32 {
+    let humidity = humidity.clamp(1.0, 100.0);
+    let gamma = (humidity / 100.0).ln() + MAGNUS_A * temperature_c / (MAGNUS_B + temperature_c);
+    MAGNUS_B * gamma / (MAGNUS_A - gamma)
+}
+
+/// NWS heat index: Steadman's simple form below 80 °F, otherwise the Rothfusz
+/// regression with the low- and high-humidity adjustments.
+pub fn heat_index(temperature_c: f32, humidity: f32) -> f32 {
+    let t = temperature_c * 9.0 / 5.0 + 32.0;
+    let rh = humidity.clamp(0.0, 100.0);
+
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    if (simple + t) / 2.0 < 80.0 {
+        return units::fahrenheit_to_celsius(simple);
+    }
+
+    let mut hi = -42.379 + 2.049_015_3 * t + 10.143_332 * rh
+        - 0.224_755_4 * t * rh
+        - 0.006_837_83 * t * t
+        - 0.054_817_17 * rh * rh
+        + 0.001_228_74 * t * t * rh
+        + 0.000_852_82 * t * rh * rh
+        - 0.000_001_99 * t * t * rh * rh;
+
+    if rh < 13.0 && (80.0..=112.0).contains(&t) {
+        hi -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+    } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+        hi += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+    }
+
+    units::fahrenheit_to_celsius(hi)
+}
+
+/// NWS 2001 wind chill; defined only at or below 10 °C with wind above 4.8 km/h,
+/// otherwise the air temperature is returned.
+pub fn wind_chill(temperature_c: f32, wind_speed_ms: f32) -> f32 {
+    let wind_kmh = wind_speed_ms * 3.6;
+    if temperature_c > 10.0 || wind_kmh <= 4.8 {
+        return temperature_c;
+    }
+    let v = wind_kmh.powf(0.16);
+    13.12 + 0.6215 * temperature_c - 11.37 * v + 0.3965 * temperature_c * v
+}
+
+/// Steadman apparent temperature (shade, no radiation) as used by the Australian BoM.
+pub fn apparent_temperature(temperature_c: f32, humidity: f32, wind_speed_ms: f32) -> f32 {
+    let vapor_pressure = humidity.clamp(0.0, 100.0) / 100.0 * saturation_vapor_pressure(temperature_c);
+    temperature_c + 0.33 * vapor_pressure - 0.70 * wind_speed_ms - 4.0
+}
+
+/// NWS-style "feels like": wind chill when cold and windy, heat index when hot,
+/// the air temperature in between.
+pub fn feels_like(temperature_c: f32, humidity: f32, wind_speed_ms: f32) -> f32 {
+    if temperature_c <= 10.0 && wind_speed_ms * 3.6 > 4.8 {
+        wind_chill(temperature_c, wind_speed_ms)
+    } else if temperature_c >= 26.7 {
+        heat_index(temperature_c, humidity)
+    } else {
+        temperature_c
+    }
+}
+
+/// Water vapour density in g/m³.
+pub fn absolute_humidity(temperature_c: f32, humidity: f32) -> f32 {
+    let vapor_pressure = humidity.clamp(0.0, 100.0) / 100.0 * saturation_vapor_pressure(temperature_c);
+    216.7 * vapor_pressure / (273.15 + temperature_c)
+}
+
+impl WeatherData {
+    pub fn dew_point(&self) -> f32 {
+        dew_point(self.temperature, self.humidity)
+    }
+
+    pub fn heat_index(&self) -> f32 {
+        heat_index(self.temperature, self.humidity)
+    }
+
+    pub fn wind_chill(&self) -> f32 {
+        wind_chill(self.temperature, self.wind_speed)
+    }
+
+    pub fn apparent_temperature(&self) -> f32 {
+        apparent_temperature(self.temperature, self.humidity, self.wind_speed)
+    }
+
+    pub fn feels_like(&self) -> f32 {
+        feels_like(self.temperature, self.humidity, self.wind_speed)
+    }
+
+    pub fn absolute_humidity(&self) -> f32 {
+        absolute_humidity(self.temperature, self.humidity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f32, expected: f32, tolerance: f32) {
+        assert!((actual - expected).abs() <= tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+    }
+
+    fn fahrenheit(celsius: f32) -> f32 {
+        celsius * 9.0 / 5.0 + 32.0
+    }
+
+    #[test]
+    fn saturation_and_dew_point() {
+        close(saturation_vapor_pressure(0.0), 6.112, 0.001);
+        close(saturation_vapor_pressure(20.0), 23.37, 0.05);
+        close(dew_point(20.0, 50.0), 9.3, 0.1);
+        close(dew_point(15.0, 100.0), 15.0, 0.01);
+    }
+
+    #[test]
+    fn heat_index_matches_nws_table() {
+        // NWS heat index chart, rounded to whole °F.
+        close(fahrenheit(heat_index(units::fahrenheit_to_celsius(90.0), 70.0)), 106.0, 0.5);
+        close(fahrenheit(heat_index(units::fahrenheit_to_celsius(100.0), 40.0)), 109.0, 0.5);
+        close(fahrenheit(heat_index(units::fahrenheit_to_celsius(80.0), 40.0)), 80.0, 0.5);
+        // Mild air takes Steadman's simple form, close to the air temperature.
+        close(heat_index(20.0, 50.0), 19.4, 0.2);
+    }
+
+    #[test]
+    fn wind_chill_matches_published_tables() {
+        // NWS chart: 0 °F in a 15 mph wind feels like -19 °F.
+        close(fahrenheit(wind_chill(units::fahrenheit_to_celsius(0.0), units::mph_to_ms(15.0))), -19.0, 0.5);
+        // Environment Canada: -20 °C at 30 km/h is -33, -10 °C at 20 km/h is -18.
+        close(wind_chill(-20.0, 30.0 / 3.6), -33.0, 0.5);
+        close(wind_chill(-10.0, 20.0 / 3.6), -18.0, 0.5);
+        // Undefined when warm or nearly calm.
+        assert_eq!(wind_chill(15.0, 10.0), 15.0);
+        assert_eq!(wind_chill(-5.0, 1.0), -5.0);
+    }
+
+    #[test]
+    fn apparent_temperature_follows_bom_formula() {
+        // 30 °C, 50 % and calm: e = 21.2 hPa, so 30 + 0.33 × 21.2 - 4.
+        close(apparent_temperature(30.0, 50.0, 0.0), 33.0, 0.05);
+        // Each m/s of wind takes off 0.7 °C.
+        close(apparent_temperature(30.0, 50.0, 0.0) - apparent_temperature(30.0, 50.0, 5.0), 3.5, 0.001);
+    }
+
+    #[test]
+    fn absolute_humidity_of_saturated_air() {
+        close(absolute_humidity(20.0, 100.0), 17.3, 0.1);
+        close(absolute_humidity(30.0, 100.0), 30.4, 0.2);
+        close(absolute_humidity(20.0, 50.0), absolute_humidity(20.0, 100.0) / 2.0, 0.001);
+    }
+
+    #[test]
+    fn feels_like_picks_the_applicable_index() {
+        assert_eq!(feels_like(-10.0, 50.0, 20.0 / 3.6), wind_chill(-10.0, 20.0 / 3.6));
+        assert_eq!(feels_like(32.0, 70.0, 3.0), heat_index(32.0, 70.0));
+        assert_eq!(feels_like(18.0, 70.0, 3.0), 18.0);
+    }
+}
@@ -0,0 +1,359 @@
+use crate::data::{Metric, WeatherData};
+use crate::timezone::DisplayTimezone;
+use crate::units::UnitPreferences;
+use anyhow::{anyhow, bail, Context, Result};
+use parquet::data_type::{ByteArray, ByteArrayType, FloatType, Int64Type};
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+// Parquet row groups are written this many readings at a time.
+const PARQUET_ROW_GROUP: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Csv, ExportFormat::JsonLines, ExportFormat::Parquet];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::JsonLines => "JSON Lines",
+            ExportFormat::Parquet => "Parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    /// Guesses the format from a file name's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "jsonl" | "ndjson" => Some(ExportFormat::JsonLines),
+            "parquet" => Some(ExportFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "ndjson" => Ok(ExportFormat::JsonLines),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(format!("unknown format '{}', expected csv, jsonl or parquet", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Columns after the timestamp and source, in this order.
+    pub fields: Vec<Metric>,
+    /// Values are converted from SI to these units.
+    pub units: UnitPreferences,
+    pub timezone: DisplayTimezone,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Csv,
+            fields: Metric::ALL.to_vec(),
+            units: UnitPreferences::default(),
+            timezone: DisplayTimezone::Utc,
+        }
+    }
+}
+
+impl ExportOptions {
+    fn value(&self, metric: Metric, data: &WeatherData) -> f32 {
+        self.units.convert(metric.dimension(), metric.value(data))
+    }
+
+    fn symbol(&self, metric: Metric) -> &'static str {
+        self.units.symbol(metric.dimension())
+    }
+}
+
+/// Writes `readings` to `path` and returns how many were written.
+///
+/// CSV headers carry the unit, e.g. `temperature (°F)`; JSON Lines use the bare field
+/// names, and Parquet files record the units and time zone in their key-value metadata.
+pub fn export(readings: &[WeatherData], options: &ExportOptions, path: &Path) -> Result<usize> {
+    if options.fields.is_empty() {
+        bail!("no fields selected");
+    }
+    let file = File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    match options.format {
+        ExportFormat::Csv => write_csv(readings, options, file),
+        ExportFormat::JsonLines => write_jsonl(readings, options, file),
+        ExportFormat::Parquet => write_parquet(readings, options, file),
+    }
+    .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(readings.len())
+}
+
+fn write_csv(readings: &[WeatherData], options: &ExportOptions, file: File) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(BufWriter::new(file));
+    let mut header = vec!["timestamp".to_string(), "source".to_string()];
+    header.extend(options.fields.iter().map(|metric| match options.symbol(*metric) {
+        "" => metric.key().to_string(),
+        symbol => format!("{} ({})", metric.key(), symbol),
+    }));
+    writer.write_record(&header)?;
+
+    for data in readings {
+        let mut record = vec![options.timezone.format(data.timestamp), data.source.clone()];
+        // Fields that were not measured are left empty.
+        record.extend(options.fields.iter().map(|metric| match options.value(*metric, data) {
+            value if value.is_nan() => String::new(),
+            value => value.to_string(),
+        }));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// One JSON object per reading, keeping the column order and f32 precision.
+struct JsonRow<'a> {
+    data: &'a WeatherData,
+    options: &'a ExportOptions,
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.options.fields.len() + 2))?;
+        map.serialize_entry("timestamp", &self.options.timezone.format(self.data.timestamp))?;
+        map.serialize_entry("source", &self.data.source)?;
+        for metric in &self.options.fields {
+            map.serialize_entry(metric.key(), &self.options.value(*metric, self.data))?;
+        }
+        map.end()
+    }
+}
+
+fn write_jsonl(readings: &[WeatherData], options: &ExportOptions, file: File) -> Result<()> {
+    let mut writer = BufWriter::new(file);
+    for data in readings {
+        serde_json::to_writer(&mut writer, &JsonRow { data, options })?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_parquet(readings: &[WeatherData], options: &ExportOptions, file: File) -> Result<()> {
+    // UTC exports are instants; other zones are written as that zone's wall-clock time.
+    let adjusted_to_utc = options.timezone == DisplayTimezone::Utc;
+    let mut message = format!(
+        "message weather {{ REQUIRED INT64 timestamp (TIMESTAMP(MILLIS,{})); REQUIRED BYTE_ARRAY source (UTF8);",
+        adjusted_to_utc
+    );
+    for metric in &options.fields {
+        message.push_str(&format!(" OPTIONAL FLOAT {};", metric.key()));
+    }
+    message.push_str(" }");
+    let schema = Arc::new(parse_message_type(&message)?);
+
+    let units: serde_json::Map<String, serde_json::Value> = options.fields.iter()
+        .map(|metric| (metric.key().to_string(), options.symbol(*metric).into()))
+        .collect();
+    let properties = WriterProperties::builder()
+        .set_key_value_metadata(Some(vec![
+            KeyValue::new("units".to_string(), serde_json::Value::Object(units).to_string()),
+            KeyValue::new("timezone".to_string(), options.timezone.label()),
+        ]))
+        .build();
+    let mut writer = SerializedFileWriter::new(BufWriter::new(file), schema, Arc::new(properties))?;
+
+    for chunk in readings.chunks(PARQUET_ROW_GROUP) {
+        let mut row_group = writer.next_row_group()?;
+
+        let timestamps: Vec<i64> = chunk.iter()
+            .map(|data| {
+                let offset = options.timezone.offset_at(data.timestamp).local_minus_utc() as i64;
+                data.timestamp.timestamp_millis() + if adjusted_to_utc { 0 } else { offset * 1000 }
+            })
+            .collect();
+        let mut column = row_group.next_column()?.ok_or_else(|| anyhow!("missing timestamp column"))?;
+        column.typed::<Int64Type>().write_batch(&timestamps, None, None)?;
+        column.close()?;
+
+        let sources: Vec<ByteArray> = chunk.iter().map(|data| ByteArray::from(data.source.as_str())).collect();
+        let mut column = row_group.next_column()?.ok_or_else(|| anyhow!("missing source column"))?;
+        column.typed::<ByteArrayType>().write_batch(&sources, None, None)?;
+        column.close()?;
+
+        for metric in &options.fields {
+            // Unmeasured values are nulls: definition level 0 and no entry in `values`.
+            let values: Vec<f32> = chunk.iter().map(|data| options.value(*metric, data)).filter(|value| !value.is_nan()).collect();
+            let levels: Vec<i16> = chunk.iter().map(|data| i16::from(data.has(*metric))).collect();
+            let mut column = row_group.next_column()?.ok_or_else(|| anyhow!("missing {} column", metric.key()))?;
+            column.typed::<FloatType>().write_batch(&values, Some(&levels), None)?;
+            column.close()?;
+        }
+        row_group.close()?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::{read_file, ImportOptions};
+    use crate::sources::replay::load_recording;
+    use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use std::path::PathBuf;
+
+    fn readings() -> Vec<WeatherData> {
+        let mut readings: Vec<WeatherData> = (0..3)
+            .map(|i| {
+                let mut data = WeatherData::new(20.0 + i as f32, 50.0, 1013.25, 3.5, 180.0, 0.0, 2.0, 400.0)
+                    .with_timestamp(Utc.timestamp_opt(1_700_000_000 + 60 * i, 0).unwrap());
+                data.source = "roof".to_string();
+                data
+            })
+            .collect();
+        readings[0].uv_index = f32::NAN;
+        readings[1].temperature = f32::NAN;
+        readings[2].wind_direction = f32::NAN;
+        readings
+    }
+
+    fn temp_path(format: ExportFormat) -> PathBuf {
+        std::env::temp_dir().join(format!("export-{}.{}", uuid::Uuid::new_v4(), format.extension()))
+    }
+
+    fn assert_same(read: &[WeatherData], written: &[WeatherData]) {
+        assert_eq!(read.len(), written.len());
+        for (read, written) in read.iter().zip(written) {
+            assert_eq!((read.timestamp, read.source.as_str()), (written.timestamp, written.source.as_str()));
+            for metric in Metric::ALL {
+                let (value, expected) = (metric.value(read), metric.value(written));
+                assert!(value == expected || value.is_nan() && expected.is_nan() || (value - expected).abs() < 1e-3, "{:?}: {} != {}", metric, value, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn csv_round_trips_through_import_with_units_and_offsets() {
+        let readings = readings();
+        let path = temp_path(ExportFormat::Csv);
+        let offset = DisplayTimezone::Offset(FixedOffset::east_opt(2 * 3600).unwrap());
+        let options = ExportOptions { units: UnitPreferences::imperial(), timezone: offset, ..ExportOptions::default() };
+        assert_eq!(export(&readings, &options, &path).unwrap(), 3);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("timestamp,source,temperature (°F),"), "{}", lines[0]);
+        assert!(lines[1].starts_with("2023-11-15T00:13:20+02:00,roof,68,"), "{}", lines[1]);
+        // The missing temperature is an empty cell.
+        assert!(lines[2].starts_with("2023-11-15T00:14:20+02:00,roof,,"), "{}", lines[2]);
+
+        let (read, report) = read_file(&path, &ImportOptions::default(), &mut |_| {}).unwrap();
+        assert_eq!((report.rows, report.readings, report.failed), (3, 3, 0));
+        // Import carries an earlier row's value into an empty cell; without one it stays missing.
+        let mut expected = readings.clone();
+        expected[1].temperature = readings[0].temperature;
+        expected[2].wind_direction = readings[1].wind_direction;
+        assert_same(&read, &expected);
+        assert!(read[0].uv_index.is_nan());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn csv_keeps_only_the_selected_fields_in_order() {
+        let path = temp_path(ExportFormat::Csv);
+        let options = ExportOptions { fields: vec![Metric::Pressure, Metric::Temperature], ..ExportOptions::default() };
+        export(&readings(), &options, &path).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().next(), Some("timestamp,source,pressure (hPa),temperature (°C)"));
+        let (read, _) = read_file(&path, &ImportOptions::default(), &mut |_| {}).unwrap();
+        assert_eq!((read[0].pressure, read[0].temperature), (1013.25, 20.0));
+        assert!(read[0].humidity.is_nan() && read[0].wind_speed.is_nan());
+        std::fs::remove_file(&path).unwrap();
+        assert!(export(&readings(), &ExportOptions { fields: Vec::new(), ..ExportOptions::default() }, &path).is_err());
+    }
+
+    #[test]
+    fn json_lines_round_trip_with_nulls_for_missing_values() {
+        let readings = readings();
+        let path = temp_path(ExportFormat::JsonLines);
+        let options = ExportOptions { format: ExportFormat::JsonLines, ..ExportOptions::default() };
+        assert_eq!(export(&readings, &options, &path).unwrap(), 3);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let second: serde_json::Value = serde_json::from_str(text.lines().nth(1).unwrap()).unwrap();
+        assert_eq!(second["timestamp"], "2023-11-14T22:14:20Z");
+        assert_eq!(second["temperature"], serde_json::Value::Null);
+        assert_same(&load_recording(&path).unwrap(), &readings);
+        std::fs::remove_file(&path).unwrap();
+
+        let options = ExportOptions { fields: vec![Metric::WindDirection], ..options };
+        export(&readings, &options, &path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().nth(2), Some(r#"{"timestamp":"2023-11-14T22:15:20Z","source":"roof","wind_direction":null}"#));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parquet_has_nulls_for_missing_values_and_wall_clock_times_for_offsets() {
+        let readings = readings();
+        let path = temp_path(ExportFormat::Parquet);
+        let offset = DisplayTimezone::Offset(FixedOffset::east_opt(2 * 3600).unwrap());
+        let options = ExportOptions {
+            format: ExportFormat::Parquet,
+            fields: vec![Metric::Temperature, Metric::WindDirection],
+            timezone: offset,
+            ..ExportOptions::default()
+        };
+        assert_eq!(export(&readings, &options, &path).unwrap(), 3);
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        let columns: Vec<_> = metadata.schema_descr().columns().iter().map(|column| column.name().to_string()).collect();
+        assert_eq!(columns, ["timestamp", "source", "temperature", "wind_direction"]);
+        let timezone = metadata.key_value_metadata().unwrap().iter().find(|pair| pair.key == "timezone").unwrap();
+        assert_eq!(timezone.value.as_deref(), Some("+02:00"));
+
+        let rows: Vec<_> = reader.get_row_iter(None).unwrap().map(|row| row.unwrap()).collect();
+        assert_eq!(rows.len(), 3);
+        let wall_clock = rows[0].get_timestamp_millis(0).unwrap();
+        assert_eq!(DateTime::from_timestamp_millis(wall_clock).unwrap(), readings[0].timestamp + chrono::Duration::hours(2));
+        assert_eq!(rows[0].get_string(1).unwrap(), "roof");
+        assert_eq!(rows[0].get_float(2).unwrap(), 20.0);
+        assert!(rows[1].get_float(2).is_err(), "missing temperature should be null");
+        assert_eq!(rows[1].get_float(3).unwrap(), 180.0);
+        assert!(rows[2].get_float(3).is_err(), "missing wind direction should be null");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use crate::data::WeatherData;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+/// Fixed-capacity ring buffer of readings kept in timestamp order, so time windows
+/// are found by binary search instead of scanning.
+#[derive(Debug, Clone)]
+pub struct WeatherHistory {
+    readings: VecDeque<WeatherData>,
+    capacity: usize,
+}
+
+impl WeatherHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            readings: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// O(1) for in-order readings; a late reading is inserted at its place in time.
+    pub fn push(&mut self, data: WeatherData) {
+        match self.readings.back() {
+            Some(last) if data.timestamp < last.timestamp => {
+                let index = self.readings.partition_point(|d| d.timestamp <= data.timestamp);
+                self.readings.insert(index, data);
+            }
+            _ => self.readings.push_back(data),
+        }
+
+        while self.readings.len() > self.capacity {
+            self.readings.pop_front();
+        }
+    }
+
+    /// Every reading, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &WeatherData> {
+        self.readings.iter()
+    }
+
+    pub fn latest(&self) -> Option<&WeatherData> {
+        self.readings.back()
+    }
+
+    pub fn oldest(&self) -> Option<&WeatherData> {
+        self.readings.front()
+    }
+
+    /// Readings with `from < timestamp <= to`.
+    pub fn window(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> impl DoubleEndedIterator<Item = &WeatherData> {
+        let start = self.readings.partition_point(|d| d.timestamp <= from);
+        let end = self.readings.partition_point(|d| d.timestamp <= to).max(start);
+        self.readings.range(start..end)
+    }
+
+    pub fn since(&self, from: DateTime<Utc>) -> impl DoubleEndedIterator<Item = &WeatherData> {
+        let start = self.readings.partition_point(|d| d.timestamp <= from);
+        self.readings.range(start..)
+    }
+}
+
+impl Extend<WeatherData> for WeatherHistory {
+    fn extend<I: IntoIterator<Item = WeatherData>>(&mut self, readings: I) {
+        for data in readings {
+            self.push(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn time(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn at(secs: i64) -> WeatherData {
+        WeatherData::new(secs as f32, 50.0, 1013.0, 1.0, 90.0, 0.0, 1.0, 100.0).with_timestamp(time(secs))
+    }
+
+    fn seconds<'a>(readings: impl Iterator<Item = &'a WeatherData>) -> Vec<i64> {
+        readings.map(|data| (data.timestamp - time(0)).num_seconds()).collect()
+    }
+
+    #[test]
+    fn evicts_oldest_beyond_capacity() {
+        let mut history = WeatherHistory::new(3);
+        history.extend((0..5).map(|i| at(i * 10)));
+        assert_eq!(seconds(history.iter()), vec![20, 30, 40]);
+        assert_eq!(history.oldest().unwrap().timestamp, time(20));
+        assert_eq!(history.latest().unwrap().timestamp, time(40));
+
+        // Older than everything kept: inserted at the front and evicted straight away.
+        history.push(at(5));
+        assert_eq!(seconds(history.iter()), vec![20, 30, 40]);
+        assert_eq!(WeatherHistory::new(0).capacity(), 1);
+    }
+
+    #[test]
+    fn late_readings_keep_timestamp_order() {
+        let mut history = WeatherHistory::new(10);
+        let mut duplicate = at(30);
+        duplicate.temperature = 99.0;
+        history.extend([at(0), at(30), at(60), at(20), at(45), duplicate]);
+        assert_eq!(seconds(history.iter()), vec![0, 20, 30, 30, 45, 60]);
+        // A duplicate timestamp goes after the readings already there.
+        let thirties: Vec<f32> = history.iter().filter(|data| data.timestamp == time(30)).map(|data| data.temperature).collect();
+        assert_eq!(thirties, vec![30.0, 99.0]);
+        assert_eq!(history.latest().unwrap().timestamp, time(60));
+    }
+
+    #[test]
+    fn window_is_exclusive_from_inclusive_to() {
+        let mut history = WeatherHistory::new(10);
+        history.extend([at(0), at(50), at(10), at(40), at(20), at(30)]);
+        assert_eq!(seconds(history.window(time(10), time(30))), vec![20, 30]);
+        assert_eq!(seconds(history.window(time(-5), time(0))), vec![0]);
+        assert_eq!(seconds(history.window(time(50), time(100))), Vec::<i64>::new());
+        // An inverted range is empty rather than a panic.
+        assert_eq!(seconds(history.window(time(40), time(10))), Vec::<i64>::new());
+        assert_eq!(seconds(history.since(time(30))), vec![40, 50]);
+        assert_eq!(seconds(history.since(time(30) - Duration::milliseconds(1))), vec![30, 40, 50]);
+        assert_eq!(seconds(history.window(time(15), time(45)).rev()), vec![40, 30, 20]);
+    }
+}
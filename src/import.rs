@@ -0,0 +1,934 @@
+use crate::data::{Metric, PartialReading, WeatherData};
+use crate::timezone::DisplayTimezone;
+use crate::units::{to_si, PressureUnit, RainUnit, SpeedUnit, TemperatureUnit, UnitPreferences};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+// Rows that fail beyond this many are counted but not listed in the report.
+const MAX_LISTED_ERRORS: usize = 100;
+// Progress is reported every this many rows.
+const PROGRESS_ROWS: usize = 1000;
+// Cell values other programs write for "no reading".
+const MISSING: [&str; 5] = ["", "null", "n/a", "-", "---"];
+
+/// Column layouts of the programs historical data usually comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportPreset {
+    /// A CSV dump of the WeeWX archive table, with `dateTime`, `usUnits`, `outTemp`, ...
+    WeeWx,
+    /// Cumulus monthly log files (`May24log.txt`), without a header.
+    Cumulus,
+    /// Weather Display log files (`52024lg.txt`), whitespace separated.
+    WeatherDisplay,
+    /// A CSV with a `timestamp` column and fields named like `WeatherData`, such as
+    /// this program's own exports.
+    Generic,
+}
+
+impl ImportPreset {
+    pub const ALL: [ImportPreset; 4] = [ImportPreset::WeeWx, ImportPreset::Cumulus, ImportPreset::WeatherDisplay, ImportPreset::Generic];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportPreset::WeeWx => "WeeWX",
+            ImportPreset::Cumulus => "Cumulus",
+            ImportPreset::WeatherDisplay => "Weather Display",
+            ImportPreset::Generic => "Generic CSV",
+        }
+    }
+
+    /// Source name imported readings are tagged with unless another is chosen.
+    pub fn default_source(&self) -> &'static str {
+        match self {
+            ImportPreset::WeeWx => "weewx",
+            ImportPreset::Cumulus => "cumulus",
+            ImportPreset::WeatherDisplay => "weather-display",
+            ImportPreset::Generic => "import",
+        }
+    }
+
+    /// Guesses the preset from the first line of a file.
+    pub fn detect(first_line: &str) -> Self {
+        let line = first_line.trim_start_matches('\u{feff}').trim();
+        let words: Vec<String> = line.split([',', ';', ' ', '\t'])
+            .map(|word| word.trim().trim_matches('"').to_ascii_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        if words.iter().any(|word| word == "datetime") && words.iter().any(|word| word == "usunits") {
+            ImportPreset::WeeWx
+        } else if words.starts_with(&["day".to_string(), "month".to_string(), "year".to_string()]) {
+            ImportPreset::WeatherDisplay
+        } else if words.first().is_some_and(|word| parse_cumulus_date(word).is_some()) {
+            ImportPreset::Cumulus
+        } else {
+            ImportPreset::Generic
+        }
+    }
+
+    // Wind and rain units can't be told from the values, so these are assumed unless the
+    // file or the user says otherwise.
+    fn assumed_units(&self) -> UnitPreferences {
+        match self {
+            ImportPreset::WeeWx => UnitPreferences::imperial(),
+            ImportPreset::Cumulus => UnitPreferences { wind_speed: SpeedUnit::KilometersPerHour, ..UnitPreferences::metric() },
+            ImportPreset::WeatherDisplay => UnitPreferences { wind_speed: SpeedUnit::Knots, ..UnitPreferences::metric() },
+            ImportPreset::Generic => UnitPreferences::metric(),
+        }
+    }
+}
+
+impl FromStr for ImportPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "weewx" => Ok(ImportPreset::WeeWx),
+            "cumulus" => Ok(ImportPreset::Cumulus),
+            "weather-display" | "wd" => Ok(ImportPreset::WeatherDisplay),
+            "generic" => Ok(ImportPreset::Generic),
+            _ => Err(format!("unknown preset '{}', expected weewx, cumulus, weather-display or generic", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Detected from the first line when `None`.
+    pub preset: Option<ImportPreset>,
+    /// Tags every reading; defaults to the file's `source` column or the preset's name.
+    pub source: Option<String>,
+    /// Zone of wall-clock times in the file. Unix timestamps and RFC 3339 times with an
+    /// offset don't need one.
+    pub timezone: DisplayTimezone,
+    /// Units of quantities the file doesn't state itself; detected or assumed when `None`.
+    pub units: Option<UnitPreferences>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            source: None,
+            timezone: DisplayTimezone::Local,
+            units: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportProgress {
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+    pub rows: usize,
+}
+
+impl ImportProgress {
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        (self.bytes_read as f64 / self.total_bytes as f64).min(1.0) as f32
+    }
+}
+
+/// What an import read, how it read it and what went wrong.
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    pub preset: ImportPreset,
+    /// Data rows in the file, header excluded.
+    pub rows: usize,
+    /// Rows turned into readings.
+    pub readings: usize,
+    /// Readings that were not stored yet; set by [`record_stored`](Self::record_stored).
+    pub imported: usize,
+    /// Readings already stored, or repeated in the file, for the same source and time.
+    pub duplicates: usize,
+    // Rows dropped for repeating an earlier one's source and time.
+    repeated: usize,
+    /// Rows skipped because of an error.
+    pub failed: usize,
+    /// The first errors, as `line N: message`.
+    pub errors: Vec<String>,
+    /// How the unit of each quantity was decided.
+    pub units: Vec<String>,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+}
+
+impl ImportReport {
+    fn new(preset: ImportPreset) -> Self {
+        Self {
+            preset,
+            rows: 0,
+            readings: 0,
+            imported: 0,
+            duplicates: 0,
+            repeated: 0,
+            failed: 0,
+            errors: Vec::new(),
+            units: Vec::new(),
+            first: None,
+            last: None,
+        }
+    }
+
+    pub fn record_stored(&mut self, imported: usize) {
+        self.imported = imported;
+        self.duplicates = self.repeated + self.readings.saturating_sub(imported);
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Imported {} of {} rows ({} duplicate{}, {} failed)",
+            self.imported, self.rows, self.duplicates, if self.duplicates == 1 { "" } else { "s" }, self.failed
+        )
+    }
+
+    fn fail(&mut self, line: usize, message: String) {
+        self.failed += 1;
+        if self.errors.len() < MAX_LISTED_ERRORS {
+            self.errors.push(format!("line {}: {}", line, message));
+        }
+    }
+}
+
+/// Reads a data file from another weather program into readings in SI units, oldest
+/// first. Rows that can't be read are skipped and listed in the report, and rows repeating
+/// an earlier one's source and time are dropped. Fields a row leaves empty carry over from
+/// the previous row, except the rain rate, which is left unmeasured. Nothing is stored: pass the readings
+/// to [`TelemetrySystem::import`](crate::data::TelemetrySystem::import) or
+/// [`WeatherStore::insert_new`](crate::storage::WeatherStore::insert_new), then
+/// [`ImportReport::record_stored`].
+pub fn read_file(path: &Path, options: &ImportOptions, progress: &mut dyn FnMut(ImportProgress)) -> Result<(Vec<WeatherData>, ImportReport)> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let total_bytes = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let mut reader = BufReader::new(file);
+    let first_line = {
+        let buffer = reader.fill_buf()?;
+        let end = buffer.iter().position(|byte| *byte == b'\n').unwrap_or(buffer.len());
+        String::from_utf8_lossy(&buffer[..end]).into_owned()
+    };
+    let preset = options.preset.unwrap_or_else(|| ImportPreset::detect(&first_line));
+    let delimiter = if preset != ImportPreset::WeatherDisplay && first_line.contains(';') { b';' } else { b',' };
+    let mut records = Records::new(reader, preset, delimiter);
+
+    let mut report = ImportReport::new(preset);
+    let layout = match preset {
+        ImportPreset::Cumulus => Layout::cumulus(),
+        _ => {
+            let Some((_, header)) = records.next_record().with_context(|| format!("failed to read {}", path.display()))? else {
+                bail!("{} is empty", path.display());
+            };
+            Layout::from_header(preset, &header)?
+        }
+    };
+
+    let mut rows: Vec<Row> = Vec::new();
+    while let Some((line, record)) = records.next_record().with_context(|| format!("failed to read {}", path.display()))? {
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        report.rows += 1;
+        match layout.parse(&record, options.timezone, delimiter == b';') {
+            Ok(row) => rows.push(row),
+            Err(message) => report.fail(line, message),
+        }
+        if report.rows.is_multiple_of(PROGRESS_ROWS) {
+            progress(ImportProgress { bytes_read: records.position(), total_bytes, rows: report.rows });
+        }
+    }
+    progress(ImportProgress { bytes_read: total_bytes, total_bytes, rows: report.rows });
+
+    let (units, notes) = resolve_units(preset, &layout, options.units, &rows);
+    report.units = notes;
+    rows.sort_by_key(|row| row.reading.timestamp);
+
+    let mut readings: Vec<WeatherData> = Vec::with_capacity(rows.len());
+    let mut seen = HashSet::new();
+    for row in rows {
+        let units = row.units.unwrap_or(units);
+        let mut reading = row.reading;
+        for metric in Metric::ALL {
+            let value = reading.get_mut(metric);
+            *value = value.map(|value| to_si(metric.dimension(), units.symbol(metric.dimension()), value, false).unwrap_or(value));
+        }
+        // Only state carries over: a rain rate from the previous row would invent rain that
+        // was never measured.
+        let previous = readings.last().map(|last| WeatherData { rain_rate: f32::NAN, ..last.clone() });
+        let mut data = reading.complete(previous.as_ref());
+        data.source = options.source.clone()
+            .or(row.source)
+            .unwrap_or_else(|| preset.default_source().to_string());
+        if !seen.insert((data.timestamp, data.source.clone())) {
+            report.repeated += 1;
+            continue;
+        }
+        readings.push(data);
+    }
+
+    report.readings = readings.len();
+    report.first = readings.first().map(|data| data.timestamp);
+    report.last = readings.last().map(|data| data.timestamp);
+    Ok((readings, report))
+}
+
+// Data rows of a delimited or whitespace-separated file, with their line numbers.
+enum Records {
+    Delimited(csv::Reader<BufReader<File>>),
+    Whitespace { lines: BufReader<File>, line: usize, position: u64 },
+}
+
+impl Records {
+    fn new(reader: BufReader<File>, preset: ImportPreset, delimiter: u8) -> Self {
+        match preset {
+            ImportPreset::WeatherDisplay => Records::Whitespace { lines: reader, line: 0, position: 0 },
+            _ => Records::Delimited(csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .trim(csv::Trim::All)
+                .delimiter(delimiter)
+                .from_reader(reader)),
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<(usize, Vec<String>)>> {
+        match self {
+            Records::Delimited(reader) => {
+                let mut record = csv::StringRecord::new();
+                if !reader.read_record(&mut record)? {
+                    return Ok(None);
+                }
+                let line = record.position().map_or(0, |position| position.line() as usize);
+                Ok(Some((line, record.iter().map(|field| field.trim_start_matches('\u{feff}').to_string()).collect())))
+            }
+            Records::Whitespace { lines, line, position } => {
+                let mut text = String::new();
+                let read = lines.read_line(&mut text)?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                *line += 1;
+                *position += read as u64;
+                Ok(Some((*line, text.split_whitespace().map(str::to_string).collect())))
+            }
+        }
+    }
+
+    fn position(&self) -> u64 {
+        match self {
+            Records::Delimited(reader) => reader.position().byte(),
+            Records::Whitespace { position, .. } => *position,
+        }
+    }
+}
+
+// A parsed row, still in the file's units.
+struct Row {
+    reading: PartialReading,
+    source: Option<String>,
+    // Set when the row names its own unit system (WeeWX `usUnits`).
+    units: Option<UnitPreferences>,
+}
+
+enum TimeColumns {
+    // Unix seconds.
+    Epoch(usize),
+    // RFC 3339, wall-clock or Unix time.
+    Text(usize),
+    // Cumulus `dd/mm/yy` and `HH:MM`.
+    DateAndTime(usize, usize),
+    // Weather Display's separate day, month, year, hour and minute columns.
+    Parts([usize; 5]),
+}
+
+// Which columns hold the time and each field.
+struct Layout {
+    time: TimeColumns,
+    // Column and the factor bringing it to a per-hour rate or plain value.
+    fields: Vec<(Metric, usize, f32)>,
+    source: Option<usize>,
+    us_units: Option<usize>,
+    declared: DeclaredUnits,
+}
+
+// Units named in the header, e.g. `temperature (°F)`.
+#[derive(Default)]
+struct DeclaredUnits {
+    temperature: Option<TemperatureUnit>,
+    wind_speed: Option<SpeedUnit>,
+    pressure: Option<PressureUnit>,
+    rain: Option<RainUnit>,
+}
+
+impl Layout {
+    fn cumulus() -> Self {
+        Self {
+            time: TimeColumns::DateAndTime(0, 1),
+            fields: vec![
+                (Metric::Temperature, 2, 1.0),
+                (Metric::Humidity, 3, 1.0),
+                (Metric::WindSpeed, 5, 1.0),
+                (Metric::WindDirection, 7, 1.0),
+                (Metric::RainRate, 8, 1.0),
+                (Metric::Pressure, 10, 1.0),
+                (Metric::UvIndex, 17, 1.0),
+                (Metric::SolarRadiation, 18, 1.0),
+            ],
+            source: None,
+            us_units: None,
+            declared: DeclaredUnits::default(),
+        }
+    }
+
+    fn from_header(preset: ImportPreset, header: &[String]) -> Result<Self> {
+        let names: Vec<String> = header.iter().map(|name| name.trim().trim_matches('"').to_string()).collect();
+        let find = |wanted: &str| names.iter().position(|name| name.eq_ignore_ascii_case(wanted));
+        let mut layout = Self {
+            time: TimeColumns::Epoch(0),
+            fields: Vec::new(),
+            source: None,
+            us_units: None,
+            declared: DeclaredUnits::default(),
+        };
+
+        match preset {
+            ImportPreset::WeeWx => {
+                layout.time = TimeColumns::Epoch(find("dateTime").context("the header has no dateTime column")?);
+                layout.us_units = find("usUnits");
+                for (metric, name) in [
+                    (Metric::Temperature, "outTemp"),
+                    (Metric::Humidity, "outHumidity"),
+                    (Metric::Pressure, "barometer"),
+                    (Metric::WindSpeed, "windSpeed"),
+                    (Metric::WindDirection, "windDir"),
+                    (Metric::RainRate, "rainRate"),
+                    (Metric::UvIndex, "UV"),
+                    (Metric::SolarRadiation, "radiation"),
+                ] {
+                    if let Some(column) = find(name) {
+                        layout.fields.push((metric, column, 1.0));
+                    }
+                }
+            }
+            ImportPreset::WeatherDisplay => {
+                let mut parts = [0; 5];
+                for (part, name) in parts.iter_mut().zip(["day", "month", "year", "hour", "minute"]) {
+                    *part = find(name).with_context(|| format!("the header has no {} column", name))?;
+                }
+                layout.time = TimeColumns::Parts(parts);
+                for (metric, name, factor) in [
+                    (Metric::Temperature, "temperature", 1.0),
+                    (Metric::Humidity, "humidity", 1.0),
+                    (Metric::Pressure, "barometer", 1.0),
+                    (Metric::WindSpeed, "windspeed", 1.0),
+                    (Metric::WindDirection, "direction", 1.0),
+                    // Rain in the last minute, as a rate per hour.
+                    (Metric::RainRate, "rainlastmin", 60.0),
+                ] {
+                    if let Some(column) = find(name) {
+                        layout.fields.push((metric, column, factor));
+                    }
+                }
+            }
+            // Cumulus logs have no header; their columns are fixed.
+            ImportPreset::Cumulus => return Ok(Self::cumulus()),
+            ImportPreset::Generic => {
+                let time = ["timestamp", "time", "datetime", "date"].iter()
+                    .find_map(|name| find(name))
+                    .context("the header has no timestamp column")?;
+                layout.time = TimeColumns::Text(time);
+                layout.source = find("source");
+                for (column, name) in names.iter().enumerate() {
+                    let (key, unit) = split_unit(name);
+                    let Some(metric) = Metric::from_key(&key.to_ascii_lowercase()) else {
+                        continue;
+                    };
+                    layout.fields.push((metric, column, 1.0));
+                    if let Some(unit) = unit {
+                        layout.declare(metric, unit)?;
+                    }
+                }
+            }
+        }
+
+        if layout.fields.is_empty() {
+            bail!("the header names none of the {} columns", preset.label());
+        }
+        Ok(layout)
+    }
+
+    fn declare(&mut self, metric: Metric, unit: &str) -> Result<()> {
+        let unknown = || anyhow::anyhow!("unknown unit '{}' for {}", unit, metric.key());
+        let declared = &mut self.declared;
+        match metric {
+            Metric::Temperature => declared.temperature = Some(TemperatureUnit::ALL.into_iter().find(|option| option.symbol() == unit).ok_or_else(unknown)?),
+            Metric::WindSpeed => declared.wind_speed = Some(SpeedUnit::ALL.into_iter().find(|option| option.symbol() == unit).ok_or_else(unknown)?),
+            Metric::Pressure => declared.pressure = Some(PressureUnit::ALL.into_iter().find(|option| option.symbol() == unit).ok_or_else(unknown)?),
+            Metric::RainRate => {
+                let unit = unit.strip_suffix("/h").unwrap_or(unit);
+                declared.rain = Some(RainUnit::ALL.into_iter().find(|option| option.symbol() == unit).ok_or_else(unknown)?);
+            }
+            // Other fields have a single unit.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn timestamp(&self, record: &[String], timezone: DisplayTimezone) -> Option<DateTime<Utc>> {
+        let cell = |column: usize| record.get(column).map(String::as_str).unwrap_or("");
+        match &self.time {
+            TimeColumns::Epoch(column) => parse_epoch(cell(*column)),
+            TimeColumns::Text(column) => timezone.parse(cell(*column)).or_else(|| parse_epoch(cell(*column))),
+            TimeColumns::DateAndTime(date, time) => {
+                let date = parse_cumulus_date(cell(*date))?;
+                let time = NaiveTime::parse_from_str(cell(*time), "%H:%M").ok()?;
+                timezone.to_utc(date.and_time(time))
+            }
+            TimeColumns::Parts(parts) => {
+                let [day, month, year, hour, minute] = parts.map(|column| cell(column).parse::<u32>().ok());
+                let date = NaiveDate::from_ymd_opt(year? as i32, month?, day?)?;
+                timezone.to_utc(date.and_hms_opt(hour?, minute?, 0)?)
+            }
+        }
+    }
+
+    fn parse(&self, record: &[String], timezone: DisplayTimezone, decimal_comma: bool) -> Result<Row, String> {
+        let cell = |column: usize| record.get(column).map(String::as_str).unwrap_or("");
+        let timestamp = self.timestamp(record, timezone).ok_or_else(|| {
+            let text = match &self.time {
+                TimeColumns::Epoch(column) | TimeColumns::Text(column) => cell(*column).to_string(),
+                TimeColumns::DateAndTime(date, time) => format!("{} {}", cell(*date), cell(*time)),
+                TimeColumns::Parts(parts) => parts.map(cell).join(" "),
+            };
+            format!("'{}' is not a valid time", text)
+        })?;
+
+        let us_units = match self.us_units {
+            Some(column) => Some(match cell(column).trim() {
+                "1" => UnitSystem::Us,
+                "16" => UnitSystem::Metric,
+                "17" => UnitSystem::MetricWx,
+                other => return Err(format!("unknown usUnits '{}'", other)),
+            }),
+            None => None,
+        };
+
+        let mut reading = PartialReading { timestamp: Some(timestamp), ..PartialReading::default() };
+        for (metric, column, factor) in &self.fields {
+            let value = parse_number(cell(*column), decimal_comma)
+                .map_err(|message| format!("{}: {}", metric.key(), message))?;
+            // WeeWX's METRIC system measures rain in centimetres.
+            let factor = if *metric == Metric::RainRate && us_units == Some(UnitSystem::Metric) { factor * 10.0 } else { *factor };
+            *reading.get_mut(*metric) = value.map(|value| value * factor);
+        }
+        if reading.is_empty() {
+            return Err("no readings in the row".to_string());
+        }
+
+        Ok(Row {
+            reading,
+            source: self.source.map(|column| cell(column).to_string()).filter(|source| !source.is_empty()),
+            units: us_units.map(UnitSystem::units),
+        })
+    }
+}
+
+// WeeWX `usUnits` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnitSystem {
+    Us,
+    Metric,
+    MetricWx,
+}
+
+impl UnitSystem {
+    fn units(self) -> UnitPreferences {
+        match self {
+            UnitSystem::Us => UnitPreferences { rain: RainUnit::Inches, ..UnitPreferences::imperial() },
+            UnitSystem::Metric => UnitPreferences { wind_speed: SpeedUnit::KilometersPerHour, ..UnitPreferences::metric() },
+            UnitSystem::MetricWx => UnitPreferences::metric(),
+        }
+    }
+}
+
+// Decides the unit of every quantity rows don't set themselves: stated in the header,
+// else chosen by the user, else detected from the values (temperature and pressure) or
+// assumed for the preset.
+fn resolve_units(preset: ImportPreset, layout: &Layout, chosen: Option<UnitPreferences>, rows: &[Row]) -> (UnitPreferences, Vec<String>) {
+    if layout.us_units.is_some() {
+        return (preset.assumed_units(), vec!["all: from the usUnits column".to_string()]);
+    }
+    let values = |metric: Metric| -> Vec<f32> { rows.iter().filter_map(|row| row.reading.get(metric)).collect() };
+    let assumed = preset.assumed_units();
+    let assumption = format!("assumed for {}", preset.label());
+    let declared = &layout.declared;
+
+    let (temperature, temperature_how) = pick(declared.temperature, chosen.map(|units| units.temperature), || {
+        detect_temperature(&values(Metric::Temperature)).unwrap_or((assumed.temperature, assumption.clone()))
+    });
+    let (pressure, pressure_how) = pick(declared.pressure, chosen.map(|units| units.pressure), || {
+        detect_pressure(&values(Metric::Pressure)).unwrap_or((assumed.pressure, assumption.clone()))
+    });
+    // A file in °F most likely has its wind and rain in US units too.
+    let (assumed, assumption) = if temperature == TemperatureUnit::Fahrenheit {
+        (UnitPreferences::imperial(), "assumed, as temperature is in °F".to_string())
+    } else {
+        (assumed, assumption)
+    };
+    let (wind_speed, wind_speed_how) = pick(declared.wind_speed, chosen.map(|units| units.wind_speed), || (assumed.wind_speed, assumption.clone()));
+    let (rain, rain_how) = pick(declared.rain, chosen.map(|units| units.rain), || (assumed.rain, assumption.clone()));
+
+    let notes = [
+        (Metric::Temperature, format!("temperature: {}, {}", temperature.symbol(), temperature_how)),
+        (Metric::Pressure, format!("pressure: {}, {}", pressure.symbol(), pressure_how)),
+        (Metric::WindSpeed, format!("wind speed: {}, {}", wind_speed.symbol(), wind_speed_how)),
+        (Metric::RainRate, format!("rain rate: {}/h, {}", rain.symbol(), rain_how)),
+    ]
+    .into_iter()
+    .filter(|(metric, _)| layout.fields.iter().any(|(field, _, _)| field == metric))
+    .map(|(_, note)| note)
+    .collect();
+    (UnitPreferences { temperature, wind_speed, pressure, rain }, notes)
+}
+
+fn pick<T>(declared: Option<T>, chosen: Option<T>, otherwise: impl FnOnce() -> (T, String)) -> (T, String) {
+    match (declared, chosen) {
+        (Some(unit), _) => (unit, "from the header".to_string()),
+        (None, Some(unit)) => (unit, "as chosen".to_string()),
+        (None, None) => otherwise(),
+    }
+}
+
+// °F if the values run hotter than any °C reading on record, or average above a hot
+// summer's day in °C. Cold spells in °F can't be told from °C.
+fn detect_temperature(values: &[f32]) -> Option<(TemperatureUnit, String)> {
+    if values.is_empty() {
+        return None;
+    }
+    let max = values.iter().copied().fold(f32::MIN, f32::max);
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    if max > 57.0 || mean > 32.0 {
+        Some((TemperatureUnit::Fahrenheit, format!("detected from values (highest {:.1}, mean {:.1})", max, mean)))
+    } else {
+        Some((TemperatureUnit::Celsius, format!("detected from values (highest {:.1}, mean {:.1})", max, mean)))
+    }
+}
+
+// Sea-level pressure falls in clearly separate ranges in each unit.
+fn detect_pressure(values: &[f32]) -> Option<(PressureUnit, String)> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let median = *sorted.get(sorted.len() / 2)?;
+    let unit = match median {
+        m if (25.0..35.0).contains(&m) => PressureUnit::InchesOfMercury,
+        m if (600.0..850.0).contains(&m) => PressureUnit::MillimetersOfMercury,
+        m if (850.0..1100.0).contains(&m) => PressureUnit::Hectopascal,
+        _ => return None,
+    };
+    Some((unit, format!("detected from values (median {:.1})", median)))
+}
+
+// `temperature (°F)` -> ("temperature", Some("°F")).
+fn split_unit(name: &str) -> (&str, Option<&str>) {
+    match name.split_once('(') {
+        Some((key, unit)) => (key.trim(), unit.strip_suffix(')').map(str::trim)),
+        None => (name.trim(), None),
+    }
+}
+
+fn parse_number(cell: &str, decimal_comma: bool) -> Result<Option<f32>, String> {
+    let cell = cell.trim().trim_matches('"');
+    if MISSING.iter().any(|missing| cell.eq_ignore_ascii_case(missing)) {
+        return Ok(None);
+    }
+    let number = if decimal_comma { cell.replace(',', ".") } else { cell.to_string() };
+    match number.parse::<f32>() {
+        Ok(value) if value.is_finite() => Ok(Some(value)),
+        Ok(_) => Ok(None),
+        Err(_) => Err(format!("'{}' is not a number", cell)),
+    }
+}
+
+// Unix seconds, or milliseconds for values too large to be seconds.
+fn parse_epoch(cell: &str) -> Option<DateTime<Utc>> {
+    let value = cell.trim().parse::<f64>().ok()?;
+    if value.abs() >= 1e11 {
+        DateTime::from_timestamp_millis(value as i64)
+    } else {
+        DateTime::from_timestamp_millis((value * 1000.0) as i64)
+    }
+}
+
+// Cumulus writes day-first dates with the locale's separator and a two-digit year.
+fn parse_cumulus_date(cell: &str) -> Option<NaiveDate> {
+    let date = cell.trim().replace(['-', '.'], "/");
+    NaiveDate::parse_from_str(&date, "%d/%m/%y").ok()
+        .or_else(|| NaiveDate::parse_from_str(&date, "%d/%m/%Y").ok())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TelemetrySystem;
+    use crate::storage::SqliteStore;
+    use chrono::{Duration, FixedOffset};
+
+    fn scratch_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("weather-import-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(contents: &str, options: &ImportOptions) -> (Vec<WeatherData>, ImportReport) {
+        let dir = scratch_dir();
+        let path = dir.join("data.txt");
+        std::fs::write(&path, contents).unwrap();
+        let result = read_file(&path, options, &mut |_| {});
+        let _ = std::fs::remove_dir_all(&dir);
+        result.unwrap()
+    }
+
+    fn utc() -> ImportOptions {
+        ImportOptions { timezone: DisplayTimezone::Utc, ..ImportOptions::default() }
+    }
+
+    fn time(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.01, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn detects_presets_from_the_first_line() {
+        assert_eq!(ImportPreset::detect("dateTime,usUnits,interval,outTemp"), ImportPreset::WeeWx);
+        assert_eq!(ImportPreset::detect("\u{feff}\"dateTime\";\"usUnits\";\"outTemp\""), ImportPreset::WeeWx);
+        assert_eq!(ImportPreset::detect("01/05/24,00:05,12.3,80"), ImportPreset::Cumulus);
+        assert_eq!(ImportPreset::detect("01.05.24;00:05;12,3;80"), ImportPreset::Cumulus);
+        assert_eq!(ImportPreset::detect(" day  month  year  hour  minute  temperature"), ImportPreset::WeatherDisplay);
+        assert_eq!(ImportPreset::detect("timestamp,temperature,humidity"), ImportPreset::Generic);
+        assert_eq!("wd".parse::<ImportPreset>(), Ok(ImportPreset::WeatherDisplay));
+        assert!("weatherlink".parse::<ImportPreset>().is_err());
+    }
+
+    #[test]
+    fn reads_weewx_rows_in_their_own_unit_system() {
+        let (readings, report) = read(concat!(
+            "dateTime,usUnits,interval,outTemp,outHumidity,barometer,windSpeed,windDir,rainRate,UV,radiation\n",
+            "1714564800,1,5,68.0,55,29.92,10.0,180,0.1,3,500\n",
+            "1714565100,16,5,20.0,55,1013.2,36.0,180,0.1,3,500\n",
+            "1714565400,17,5,20.0,55,1013.2,10.0,180,1.0,NULL,500\n",
+        ), &utc());
+
+        assert_eq!(report.preset, ImportPreset::WeeWx);
+        assert_eq!((report.rows, report.readings, report.failed), (3, 3, 0));
+        assert_eq!(report.units, ["all: from the usUnits column"]);
+        assert_eq!(readings[0].timestamp, time("2024-05-01T12:00:00Z"));
+        assert_eq!(readings[0].source, "weewx");
+        // US: °F, inHg, mph and in/h.
+        assert_close(readings[0].temperature, 20.0);
+        assert_close(readings[0].pressure, 1013.2);
+        assert_close(readings[0].wind_speed, 4.4704);
+        assert_close(readings[0].rain_rate, 2.54);
+        // METRIC: km/h and cm/h.
+        assert_close(readings[1].wind_speed, 10.0);
+        assert_close(readings[1].rain_rate, 1.0);
+        // METRICWX: m/s and mm/h; the UV index carries over.
+        assert_close(readings[2].wind_speed, 10.0);
+        assert_close(readings[2].rain_rate, 1.0);
+        assert_eq!(readings[2].uv_index, 3.0);
+    }
+
+    #[test]
+    fn reads_cumulus_logs_by_position_in_the_given_zone() {
+        let options = ImportOptions { timezone: DisplayTimezone::Offset(FixedOffset::east_opt(3600).unwrap()), ..ImportOptions::default() };
+        let (readings, report) = read(concat!(
+            "01/05/24,12:00,15.2,70,9.8,18.0,25.0,225,0.0,1.2,1015.3,10.0,21.0,45,22.0,15.2,15.2,3.0,450\n",
+            "01/05/24,12:10,15.6,68,9.7,36.0,40.0,230,1.8,1.5,1015.1,10.3,21.0,45,38.0,15.6,15.6,3.2,470\n",
+        ), &options);
+
+        assert_eq!(report.preset, ImportPreset::Cumulus);
+        assert_eq!(readings.len(), 2);
+        assert_eq!(readings[0].timestamp, time("2024-05-01T11:00:00Z"));
+        assert_close(readings[0].temperature, 15.2);
+        assert_close(readings[0].humidity, 70.0);
+        // Wind is assumed to be in km/h.
+        assert_close(readings[0].wind_speed, 5.0);
+        assert_close(readings[1].wind_speed, 10.0);
+        assert_close(readings[1].wind_direction, 230.0);
+        assert_close(readings[1].rain_rate, 1.8);
+        assert_close(readings[1].pressure, 1015.1);
+        assert_close(readings[1].uv_index, 3.2);
+        assert_close(readings[1].solar_radiation, 470.0);
+    }
+
+    #[test]
+    fn reads_cumulus_logs_with_decimal_commas() {
+        let (readings, report) = read(
+            "01.05.24;12:00;15,2;70;9,8;18,0;25,0;225;0,6;1,2;1015,3;10,0;21,0;45;22,0;15,2;15,2;3,0;450\n",
+            &utc(),
+        );
+        assert_eq!((report.preset, report.failed), (ImportPreset::Cumulus, 0));
+        assert_eq!(readings[0].timestamp, time("2024-05-01T12:00:00Z"));
+        assert_close(readings[0].temperature, 15.2);
+        assert_close(readings[0].rain_rate, 0.6);
+        assert_close(readings[0].pressure, 1015.3);
+    }
+
+    #[test]
+    fn reads_weather_display_logs() {
+        let (readings, report) = read(concat!(
+            " day  month  year  hour  minute  temperature  humidity  dewpoint  barometer  windspeed  gustspeed  direction  rainlastmin  dailyrain\n",
+            "  1    5     2024   12     0       15.0        70        9.6     1015.0      10.0       15.0       270        0.2         3.4\n",
+            "  1    5     2024   12     1       15.1        70        9.6     1015.0      12.0       15.0       275        0.0         3.4\n",
+        ), &utc());
+
+        assert_eq!(report.preset, ImportPreset::WeatherDisplay);
+        assert_eq!(readings[0].source, "weather-display");
+        assert_eq!(readings[1].timestamp, time("2024-05-01T12:01:00Z"));
+        // Wind is assumed to be in knots, and rain in the last minute is a rate per hour.
+        assert_close(readings[0].wind_speed, 5.144);
+        assert_close(readings[0].rain_rate, 12.0);
+        assert_close(readings[1].rain_rate, 0.0);
+        assert_close(readings[1].wind_direction, 275.0);
+    }
+
+    #[test]
+    fn reads_generic_csv_with_units_in_the_header() {
+        let (readings, report) = read(concat!(
+            "timestamp,temperature (°F),humidity,pressure (inHg),wind_speed (mph),rain_rate (in/h),source\n",
+            "2024-05-01T12:05:00Z,50.0,80,30.00,5.0,0.10,garden\n",
+            "2024-05-01 12:00:00,32.0,85,29.90,0.0,0.00,\n",
+            "2024-05-01T12:10:00Z,not a number,80,30.00,5.0,0.10,garden\n",
+        ), &utc());
+
+        assert_eq!(report.preset, ImportPreset::Generic);
+        assert_eq!((report.rows, report.readings, report.failed), (3, 2, 1));
+        assert_eq!(report.errors, ["line 4: temperature: 'not a number' is not a number"]);
+        assert!(report.units.iter().all(|note| note.ends_with("from the header")), "{:?}", report.units);
+        // Sorted by time; rows without a source get the preset's.
+        assert_eq!(readings[0].timestamp, time("2024-05-01T12:00:00Z"));
+        assert_eq!(readings[0].source, "import");
+        assert_eq!(readings[1].source, "garden");
+        assert_close(readings[0].temperature, 0.0);
+        assert_close(readings[1].temperature, 10.0);
+        assert_close(readings[1].pressure, 1015.92);
+        assert_close(readings[1].wind_speed, 2.235);
+        assert_close(readings[1].rain_rate, 2.54);
+        // The file has no such columns.
+        assert!(readings[1].uv_index.is_nan());
+    }
+
+    #[test]
+    fn empty_cells_carry_state_but_not_rain() {
+        let (readings, _) = read(concat!(
+            "timestamp,temperature,humidity,rain_rate\n",
+            "2024-05-01T12:00:00Z,10.0,80,4.0\n",
+            "2024-05-01T12:05:00Z,10.5,,\n",
+            "2024-05-01T12:10:00Z,11.0,n/a,0.0\n",
+        ), &utc());
+
+        assert_eq!(readings[1].temperature, 10.5);
+        assert_eq!(readings[1].humidity, 80.0);
+        assert!(readings[1].rain_rate.is_nan());
+        assert_eq!(readings[2].humidity, 80.0);
+        assert_eq!(readings[2].rain_rate, 0.0);
+    }
+
+    #[test]
+    fn detects_temperature_and_pressure_units_from_values() {
+        let generic = |rows: &str| read(&format!("timestamp,temperature,pressure,wind_speed\n{}", rows), &utc());
+
+        let (readings, report) = generic("2024-07-01T12:00:00Z,77.0,29.92,10\n2024-07-01T13:00:00Z,86.0,29.90,10\n");
+        assert!(report.units[0].starts_with("temperature: °F, detected from values"), "{:?}", report.units);
+        assert!(report.units[1].starts_with("pressure: inHg, detected from values"), "{:?}", report.units);
+        // Wind in a °F file is assumed to be in mph.
+        assert_eq!(report.units[2], "wind speed: mph, assumed, as temperature is in °F");
+        assert_close(readings[0].temperature, 25.0);
+        assert_close(readings[0].wind_speed, 4.4704);
+
+        let (readings, report) = generic("2024-07-01T12:00:00Z,21.0,760.0,10\n");
+        assert!(report.units[0].starts_with("temperature: °C, detected from values"), "{:?}", report.units);
+        assert!(report.units[1].starts_with("pressure: mmHg, detected from values"), "{:?}", report.units);
+        assert_eq!(report.units[2], "wind speed: m/s, assumed for Generic CSV");
+        assert_close(readings[0].pressure, 1013.25);
+
+        assert_eq!(detect_pressure(&[1012.0, 1013.0, 1015.0]).map(|(unit, _)| unit), Some(PressureUnit::Hectopascal));
+        assert_eq!(detect_pressure(&[400.0]), None);
+        assert_eq!(detect_temperature(&[]), None);
+        // A mild spell in °F averages above any usual °C mean.
+        assert_eq!(detect_temperature(&[45.0, 50.0]).map(|(unit, _)| unit), Some(TemperatureUnit::Fahrenheit));
+    }
+
+    #[test]
+    fn header_units_win_over_chosen_and_chosen_over_detected() {
+        let options = ImportOptions { units: Some(UnitPreferences::imperial()), ..utc() };
+        let (readings, report) = read("timestamp,temperature (°C),pressure\n2024-07-01T12:00:00Z,30.0,1013.0\n", &options);
+        assert_eq!(report.units[0], "temperature: °C, from the header");
+        assert_eq!(report.units[1], "pressure: inHg, as chosen");
+        assert_close(readings[0].temperature, 30.0);
+        assert_close(readings[0].pressure, 1013.0 * 33.863_89);
+    }
+
+    #[test]
+    fn unknown_header_units_are_an_error() {
+        let dir = scratch_dir();
+        let path = dir.join("data.csv");
+        std::fs::write(&path, "timestamp,temperature (K)\n2024-07-01T12:00:00Z,290\n").unwrap();
+        let error = read_file(&path, &utc(), &mut |_| {}).unwrap_err();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(error.to_string(), "unknown unit 'K' for temperature");
+    }
+
+    #[test]
+    fn repeated_rows_are_dropped_and_counted() {
+        let (readings, mut report) = read(concat!(
+            "timestamp,temperature,source\n",
+            "2024-05-01T12:00:00Z,10.0,a\n",
+            "2024-05-01T12:00:00Z,10.0,b\n",
+            "2024-05-01T12:05:00Z,11.0,a\n",
+            "2024-05-01T12:00:00Z,12.0,a\n",
+        ), &utc());
+
+        assert_eq!(report.rows, 4);
+        assert_eq!(readings.len(), 3);
+        // The first of the repeats is kept.
+        assert_eq!(readings[0].temperature, 10.0);
+        report.record_stored(2);
+        assert_eq!((report.readings, report.imported, report.duplicates), (3, 2, 2));
+        assert_eq!(report.summary(), "Imported 2 of 4 rows (2 duplicates, 0 failed)");
+    }
+
+    #[test]
+    fn importing_again_stores_nothing_new_and_refreshes_rain() {
+        let dir = scratch_dir();
+        let store = SqliteStore::open(dir.join("weather.db")).unwrap();
+        let mut system = TelemetrySystem::new().with_store(Box::new(store)).unwrap();
+
+        // An hour of 6 mm/h rain ending half an hour ago.
+        let start = Utc::now() - Duration::minutes(90);
+        let mut rows = String::from("timestamp,rain_rate\n");
+        for i in 0..=6 {
+            rows += &format!("{},6.0\n", (start + Duration::minutes(10 * i)).to_rfc3339());
+        }
+        let (readings, _) = read(&rows, &utc());
+
+        let imported: usize = readings.chunks(3).map(|batch| system.import(batch).unwrap()).sum();
+        assert_eq!(imported, 7);
+        assert_eq!(system.rain_totals().last_24h_mm, 0.0);
+        system.refresh_rain(readings.last().unwrap().timestamp).unwrap();
+        assert_close(system.rain_totals().last_24h_mm, 6.0);
+
+        assert_eq!(system.import(&readings).unwrap(), 0);
+        assert_eq!(system.get_weather_between(start - Duration::seconds(1), Utc::now()).len(), 7);
+        drop(system);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
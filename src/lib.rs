@@ -0,0 +1,56 @@
+//! Weather station telemetry: readings from hardware and network sources, persistence,
+//! rollups, derived quantities, rain accounting and threshold alerts.
+//!
+//! The egui dashboard is one consumer of this crate and is only compiled with the `gui`
+//! feature (on by default). Server tools can depend on the crate with
+//! `default-features = false` to leave `eframe`/`egui` out entirely.
+//!
+//! ```
+//! use weather_telemetry::data::{TelemetrySystem, WeatherData};
+//!
+//! let mut system = TelemetrySystem::new();
+//! system.add_weather_data(WeatherData::new(21.5, 60.0, 1013.2, 3.4, 180.0, 0.0, 4.0, 420.0));
+//! assert_eq!(system.get_latest_weather().unwrap().temperature, 21.5);
+//! ```
+
+/// Threshold alert rules, their evaluation and notification delivery.
+pub mod alerts;
+/// HTTP API serving current readings, history and statistics as JSON.
+pub mod api;
+/// The `weather.toml` configuration file.
+pub mod config;
+/// Weather readings and the [`data::TelemetrySystem`] that stores and analyses them.
+pub mod data;
+/// Dew point, heat index, wind chill and other quantities computed from a reading.
+pub mod derived;
+/// Writing history to CSV, JSON Lines and Parquet files.
+pub mod export;
+/// In-memory ring buffer of recent readings.
+pub mod history;
+/// Reading historical data files from WeeWX, Cumulus, Weather Display and CSV.
+pub mod import;
+/// Per-source reading counters for monitoring.
+pub mod metrics;
+/// Rainfall accumulation, calendar totals and storm detection.
+pub mod rain;
+/// Min/max/mean aggregates over 1-minute, 10-minute and hourly buckets.
+pub mod rollup;
+/// Weather station hardware and network sources.
+pub mod sources;
+/// Sources, storage and alerting wired together with graceful shutdown.
+pub mod station;
+/// Persistence of readings.
+pub mod storage;
+/// The collector that runs the registered sources.
+pub mod telemetry;
+/// Time zones for wall-clock times in files, settings and rain days.
+pub mod timezone;
+/// Unit conversions and per-quantity display unit preferences.
+pub mod units;
+
+/// The egui dashboard application.
+#[cfg(feature = "gui")]
+pub mod app;
+/// Dashboard layout and widgets.
+#[cfg(feature = "gui")]
+pub mod ui;
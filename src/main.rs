@@ -1,18 +1,204 @@
-use eframe::egui;
+use anyhow::{bail, Context};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::path::PathBuf;
 
-mod app;
-mod telemetry;
-mod ui;
-mod data;
+use weather_telemetry::alerts::AlertRule;
+use weather_telemetry::config::Config;
+use weather_telemetry::data::Metric;
+use weather_telemetry::export::{export, ExportFormat, ExportOptions};
+use weather_telemetry::timezone::DisplayTimezone;
+use weather_telemetry::import::{read_file, ImportOptions, ImportPreset};
+use weather_telemetry::sources::SourceConfig;
+use weather_telemetry::station::Station;
+use weather_telemetry::storage::{SqliteStore, WeatherStore};
+use weather_telemetry::units::UnitPreferences;
 
-use app::WeatherApp;
+#[derive(Parser)]
+#[command(about = "Weather station telemetry dashboard")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(long, help = "Run collection, storage and alerting without opening the dashboard window")]
+    headless: bool,
+    #[arg(long, global = true, default_value = "weather.toml", help = "Configuration file; defaults are used if it does not exist")]
+    config: PathBuf,
+    #[arg(long, global = true, help = "Overrides storage.database")]
+    database: Option<String>,
+    #[arg(long, help = "Overrides retention.history_size")]
+    history_size: Option<usize>,
+    #[arg(long, help = "Overrides rain.storm_gap_hours")]
+    storm_gap_hours: Option<i64>,
+    #[arg(long, help = "Overrides ui.time_range_hours")]
+    time_range_hours: Option<u32>,
+    #[arg(long, help = "Overrides api.bind and enables the REST API")]
+    api_bind: Option<String>,
+    #[arg(long = "source", value_parser = parse_source, help = "Replaces the configured sources, e.g. '{ type = \"mqtt\", host = \"broker\" }'; repeat for several")]
+    sources: Vec<SourceConfig>,
+    #[arg(long, value_enum, help = "Overrides [units]")]
+    units: Option<UnitSystem>,
+    #[arg(long, help = "Replaces the alert rules with the [[alerts]] of another TOML file; an empty file turns alerting off")]
+    alerts: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write stored readings to a CSV, JSON Lines or Parquet file
+    Export(ExportArgs),
+    /// Read WeeWX, Cumulus, Weather Display or CSV files into the database
+    Import(ImportArgs),
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    #[arg(long, short, help = "File to write")]
+    output: PathBuf,
+    #[arg(long, help = "csv, jsonl or parquet; guessed from the output extension by default")]
+    format: Option<ExportFormat>,
+    #[arg(long, help = "Start of the range, RFC 3339 or YYYY-MM-DD [HH:MM] in --timezone; defaults to --hours before --to")]
+    from: Option<String>,
+    #[arg(long, help = "End of the range, like --from; defaults to now")]
+    to: Option<String>,
+    #[arg(long, default_value_t = 24, help = "Length of the range when --from is not given")]
+    hours: u32,
+    #[arg(long, value_delimiter = ',', value_parser = parse_metric, help = "Comma-separated fields to include; defaults to all")]
+    fields: Vec<Metric>,
+    #[arg(long, value_enum, default_value_t = UnitSystem::Config, help = "Units values are written in")]
+    units: UnitSystem,
+    #[arg(long, default_value = "utc", help = "utc, local or an offset like +02:00, for timestamps and --from/--to")]
+    timezone: DisplayTimezone,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    #[arg(required = true, help = "Files to import, e.g. every monthly Cumulus log")]
+    files: Vec<PathBuf>,
+    #[arg(long, help = "weewx, cumulus, weather-display or generic; detected from each file's first line by default")]
+    preset: Option<ImportPreset>,
+    #[arg(long, help = "Source name for the readings; defaults to the file's source column or the preset name")]
+    source: Option<String>,
+    #[arg(long, default_value = "local", help = "utc, local or an offset like +02:00, for wall-clock times in the files")]
+    timezone: DisplayTimezone,
+    #[arg(long, value_enum, default_value_t = FileUnits::Detect, help = "Units of quantities a file doesn't state itself")]
+    units: FileUnits,
+    #[arg(long, help = "Read the files and report without storing anything")]
+    dry_run: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FileUnits {
+    /// Temperature and pressure from the values, wind and rain by preset
+    Detect,
+    /// °C, m/s, hPa and mm
+    Metric,
+    /// °F, mph, inHg and in
+    Imperial,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum UnitSystem {
+    /// °C, m/s, hPa and mm, as stored
+    Metric,
+    /// °F, mph, inHg and in
+    Imperial,
+    /// The [units] section of the config file
+    Config,
+}
+
+fn parse_metric(key: &str) -> Result<Metric, String> {
+    Metric::from_key(key.trim()).ok_or_else(|| format!("expected one of {}", Metric::ALL.map(Metric::key).join(", ")))
+}
+
+// A source as an inline TOML table, the way it is written in `sources = [...]`.
+fn parse_source(table: &str) -> Result<SourceConfig, String> {
+    #[derive(Deserialize)]
+    struct Inline {
+        source: SourceConfig,
+    }
+    toml::from_str::<Inline>(&format!("source = {}", table)).map(|inline| inline.source).map_err(|e| e.message().to_string())
+}
 
-fn main() -> Result<(), eframe::Error> {
+fn load_alerts(path: &std::path::Path) -> anyhow::Result<Vec<AlertRule>> {
+    #[derive(Deserialize)]
+    struct AlertsFile {
+        #[serde(default)]
+        alerts: Vec<AlertRule>,
+    }
+    let contents = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let file: AlertsFile = toml::from_str(&contents).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    Ok(file.alerts)
+}
+
+impl Cli {
+    fn apply(&self, config: &mut Config) -> anyhow::Result<()> {
+        if let Some(database) = &self.database {
+            config.storage.database = database.clone();
+        }
+        if let Some(history_size) = self.history_size {
+            config.retention.history_size = history_size;
+        }
+        if let Some(storm_gap_hours) = self.storm_gap_hours {
+            config.rain.storm_gap_hours = storm_gap_hours;
+        }
+        if let Some(time_range_hours) = self.time_range_hours {
+            config.ui.time_range_hours = time_range_hours;
+        }
+        if let Some(api_bind) = &self.api_bind {
+            config.api.enabled = true;
+            config.api.bind = api_bind.clone();
+        }
+        if !self.sources.is_empty() {
+            config.sources = self.sources.clone();
+        }
+        match self.units {
+            Some(UnitSystem::Metric) => config.units = UnitPreferences::metric(),
+            Some(UnitSystem::Imperial) => config.units = UnitPreferences::imperial(),
+            Some(UnitSystem::Config) | None => {}
+        }
+        if let Some(alerts) = &self.alerts {
+            config.alerts = load_alerts(alerts)?;
+        }
+        Ok(())
+    }
+
+    // The config to run with, and the file's own, which the settings window edits so
+    // overrides are never saved.
+    fn load_config(&self) -> anyhow::Result<(Config, Config)> {
+        let file_config = Config::load(&self.config)?;
+        let mut config = file_config.clone();
+        self.apply(&mut config)?;
+        config.validate().map_err(|e| anyhow::anyhow!("invalid configuration: {}", e))?;
+        Ok((config, file_config))
+    }
+}
+
+fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
+
+    let (config, file_config) = cli.load_config()?;
+
+    if let Some(Command::Export(args)) = &cli.command {
+        return run_export(&config, args);
+    }
+    if let Some(Command::Import(args)) = &cli.command {
+        return run_import(&config, args);
+    }
+    if cli.headless {
+        return run_headless(&config);
+    }
+    run_dashboard(config, cli.config, file_config)
+}
+
+#[cfg(feature = "gui")]
+fn run_dashboard(config: Config, config_path: PathBuf, file_config: Config) -> anyhow::Result<()> {
+    use eframe::egui;
+    use weather_telemetry::app::WeatherApp;
+    use weather_telemetry::ui::widgets::SettingsWidget;
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1400.0, 900.0])
+            .with_inner_size([config.ui.window_width, config.ui.window_height])
             .with_min_inner_size([1200.0, 700.0])
             .with_resizable(true),
         ..Default::default()
@@ -21,6 +207,215 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Weather Station Telemetry",
         options,
-        Box::new(|_cc| Ok(Box::new(WeatherApp::new()))),
+        Box::new(move |_cc| Ok(Box::new(WeatherApp::new(&config, SettingsWidget::new(config_path, file_config))))),
     )
+    .map_err(|e| anyhow::anyhow!("Dashboard failed: {}", e))
+}
+
+// Builds without the gui feature have no window to open.
+#[cfg(not(feature = "gui"))]
+fn run_dashboard(config: Config, _config_path: PathBuf, _file_config: Config) -> anyhow::Result<()> {
+    run_headless(&config)
+}
+
+// Reads straight from the database, so it works whether or not a station is running.
+fn run_export(config: &Config, args: &ExportArgs) -> anyhow::Result<()> {
+    let format = match args.format.or_else(|| ExportFormat::from_path(&args.output)) {
+        Some(format) => format,
+        None => bail!("cannot tell the format of {}; pass --format csv, jsonl or parquet", args.output.display()),
+    };
+    let to = match &args.to {
+        Some(to) => args.timezone.parse(to).with_context(|| format!("--to: '{}' is not a time like 2024-05-01 12:00", to))?,
+        None => chrono::Utc::now(),
+    };
+    let from = match &args.from {
+        Some(from) => args.timezone.parse(from).with_context(|| format!("--from: '{}' is not a time like 2024-05-01 12:00", from))?,
+        None => to - chrono::Duration::hours(args.hours as i64),
+    };
+    if from >= to {
+        bail!("--from must be before --to");
+    }
+    let units = match args.units {
+        UnitSystem::Metric => UnitPreferences::metric(),
+        UnitSystem::Imperial => UnitPreferences::imperial(),
+        UnitSystem::Config => config.units,
+    };
+    let fields = if args.fields.is_empty() { Metric::ALL.to_vec() } else { args.fields.clone() };
+
+    if !std::path::Path::new(&config.storage.database).exists() {
+        bail!("database {} does not exist", config.storage.database);
+    }
+    let store = SqliteStore::open(&config.storage.database)
+        .with_context(|| format!("failed to open {}", config.storage.database))?;
+    let readings = store.query_range(from, to)?;
+    let options = ExportOptions { format, fields, units, timezone: args.timezone };
+    let count = export(&readings, &options, &args.output)?;
+    tracing::info!("Wrote {} reading(s) from {} to {} to {}", count, from, to, args.output.display());
+    Ok(())
+}
+
+// A running station picks up imported readings on its next start.
+fn run_import(config: &Config, args: &ImportArgs) -> anyhow::Result<()> {
+    let mut store = if args.dry_run {
+        None
+    } else {
+        Some(SqliteStore::open(&config.storage.database).with_context(|| format!("failed to open {}", config.storage.database))?)
+    };
+    let options = ImportOptions {
+        preset: args.preset,
+        source: args.source.clone(),
+        timezone: args.timezone,
+        units: match args.units {
+            FileUnits::Detect => None,
+            FileUnits::Metric => Some(UnitPreferences::metric()),
+            FileUnits::Imperial => Some(UnitPreferences::imperial()),
+        },
+    };
+
+    for path in &args.files {
+        let mut reported = 0;
+        let (readings, mut report) = read_file(path, &options, &mut |progress| {
+            let percent = (progress.fraction() * 100.0) as u32;
+            if percent >= reported + 10 {
+                reported = percent - percent % 10;
+                tracing::info!("{}: {}% ({} rows)", path.display(), reported, progress.rows);
+            }
+        })?;
+        let imported = match store.as_mut() {
+            Some(store) => store.insert_new(&readings)?.len(),
+            None => 0,
+        };
+        report.record_stored(imported);
+
+        println!("{} ({})", path.display(), report.preset.label());
+        if args.dry_run {
+            println!("  Read {} of {} rows ({} failed), nothing stored", report.readings, report.rows, report.failed);
+        } else {
+            println!("  {}", report.summary());
+        }
+        if let (Some(first), Some(last)) = (report.first, report.last) {
+            println!("  From {} to {}", first, last);
+        }
+        for note in &report.units {
+            println!("  {}", note);
+        }
+        for error in &report.errors {
+            println!("  {}", error);
+        }
+        if report.failed > report.errors.len() {
+            println!("  ... and {} more failed rows", report.failed - report.errors.len());
+        }
+    }
+    if let Some(store) = store.as_mut() {
+        store.flush()?;
+    }
+    Ok(())
+}
+
+fn run_headless(config: &Config) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut station = Station::new(config);
+        station.start();
+        tracing::info!("Weather station running headless, press Ctrl+C to stop");
+
+        wait_for_shutdown().await?;
+        tracing::info!("Shutting down, flushing pending data");
+        station.shutdown().await;
+        tracing::info!("Weather station stopped");
+        Ok(())
+    })
+}
+
+#[cfg(unix)]
+async fn wait_for_shutdown() -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => {}
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn wait_for_shutdown() -> anyhow::Result<()> {
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"[rain]
+storm_gap_hours = 12
+
+[[sources]]
+type = "simulator"
+name = "sim"
+
+[[alerts]]
+name = "frost"
+quantity = "temperature"
+condition = { type = "below", threshold = 0.0 }
+"#;
+
+    const ALERTS: &str = r#"[[alerts]]
+name = "gale"
+quantity = "wind_speed"
+condition = { type = "above", threshold = 17.0 }
+"#;
+
+    #[test]
+    fn command_line_overrides_are_not_written_back() {
+        let dir = std::env::temp_dir().join(format!("weather-cli-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, alerts) = (dir.join("weather.toml"), dir.join("alerts.toml"));
+        std::fs::write(&path, FILE).unwrap();
+        std::fs::write(&alerts, ALERTS).unwrap();
+
+        let cli = Cli::try_parse_from([
+            "weather-telemetry".as_ref(), "--config".as_ref(), path.as_os_str(),
+            "--storm-gap-hours".as_ref(), "2".as_ref(),
+            "--api-bind".as_ref(), "0.0.0.0:9090".as_ref(),
+            "--units".as_ref(), "imperial".as_ref(),
+            "--source".as_ref(), r#"{ type = "mqtt", name = "roof", host = "broker" }"#.as_ref(),
+            "--alerts".as_ref(), alerts.as_os_str(),
+        ]).unwrap();
+        let (running, file_config) = cli.load_config().unwrap();
+        assert_eq!(running.rain.storm_gap_hours, 2);
+        assert_eq!((running.api.enabled, running.api.bind.as_str()), (true, "0.0.0.0:9090"));
+        assert_eq!(running.units, UnitPreferences::imperial());
+        assert!(matches!(&running.sources[..], [SourceConfig::Mqtt(mqtt)] if mqtt.name == "roof" && mqtt.host == "broker"));
+        assert_eq!(running.alerts.iter().map(|rule| rule.name.as_str()).collect::<Vec<_>>(), ["gale"]);
+
+        // The settings window edits and saves the file's own config.
+        let mut draft = file_config;
+        draft.ui.time_range_hours = 24;
+        draft.save(&path).unwrap();
+
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.ui.time_range_hours, 24);
+        assert_eq!(saved.rain.storm_gap_hours, 12);
+        assert!(!saved.api.enabled);
+        assert_eq!(saved.units, UnitPreferences::default());
+        assert!(matches!(&saved.sources[..], [SourceConfig::Simulator(simulator)] if simulator.name == "sim"));
+        assert_eq!(saved.alerts.iter().map(|rule| rule.name.as_str()).collect::<Vec<_>>(), ["frost"]);
+        let text = std::fs::read_to_string(&path).unwrap();
+        for override_text in ["9090", "mqtt", "gale", "[units]", "[api]"] {
+            assert!(!text.contains(override_text), "{} was written back:\n{}", override_text, text);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn malformed_overrides_are_rejected() {
+        assert!(Cli::try_parse_from(["weather-telemetry", "--source", "{ type = \"teleport\" }"]).is_err());
+        assert!(Cli::try_parse_from(["weather-telemetry", "--source", "mqtt"]).is_err());
+        assert!(Cli::try_parse_from(["weather-telemetry", "--units", "furlongs"]).is_err());
+        let cli = Cli::try_parse_from(["weather-telemetry", "--alerts", "/nonexistent/alerts.toml"]).unwrap();
+        assert!(cli.apply(&mut Config::default()).is_err());
+    }
 }
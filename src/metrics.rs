@@ -0,0 +1,54 @@
+use crate::data::WeatherData;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Readings counted for one source on their way from the source to the store.
+#[derive(Debug, Clone, Default)]
+pub struct SourceCounters {
+    /// Handed to the collector channel.
+    pub sent: u64,
+    /// Taken off the channel and stored.
+    pub received: u64,
+    pub latest: Option<WeatherData>,
+}
+
+/// Per-source reading counters shared by the sources and the ingest task, for the
+/// Prometheus endpoint.
+#[derive(Debug, Default)]
+pub struct IngestMetrics {
+    sources: Mutex<BTreeMap<String, SourceCounters>>,
+}
+
+impl IngestMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_sent(&self, source: &str) {
+        self.update(source, |counters| counters.sent += 1);
+    }
+
+    pub fn record_received(&self, data: &WeatherData) {
+        self.update(&data.source, |counters| {
+            counters.received += 1;
+            if counters.latest.as_ref().is_none_or(|latest| latest.timestamp <= data.timestamp) {
+                counters.latest = Some(data.clone());
+            }
+        });
+    }
+
+    /// Readings sent but not yet stored.
+    pub fn queue_depth(&self) -> u64 {
+        self.snapshot().values().map(|counters| counters.sent.saturating_sub(counters.received)).sum()
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, SourceCounters> {
+        self.sources.lock().map(|sources| sources.clone()).unwrap_or_default()
+    }
+
+    fn update(&self, source: &str, update: impl FnOnce(&mut SourceCounters)) {
+        if let Ok(mut sources) = self.sources.lock() {
+            update(sources.entry(source.to_string()).or_default());
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use crate::data::WeatherData;
+use crate::timezone::DisplayTimezone;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+
+// A rate is only integrated across gaps up to this long; longer gaps are missing data.
+const MAX_INTEGRATION_SECS: i64 = 1800;
+// Every time zone offset in use is a multiple of 15 minutes, so slots this size line up
+// with midnight in any zone and totals can follow whichever zone is configured.
+const SLOT_SECS: i64 = 900;
+const SLOT_RETENTION_DAYS: i64 = 366;
+pub const DEFAULT_STORM_GAP_HOURS: i64 = 8;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Storm {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub total_mm: f32,
+    pub ongoing: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RainTotals {
+    pub last_hour_mm: f32,
+    pub today_mm: f32,
+    pub last_24h_mm: f32,
+    pub month_mm: f32,
+    pub year_mm: f32,
+    /// The current storm, or the most recent one if it has ended.
+    pub storm: Option<Storm>,
+}
+
+/// Rain amounts per bucket over a chart range.
+#[derive(Debug, Clone, Default)]
+pub struct RainReport {
+    pub totals: RainTotals,
+    pub bucket_secs: i64,
+    pub buckets: Vec<(DateTime<Utc>, f32)>,
+}
+
+/// Integrates `rain_rate` (mm/h) into amounts: per reading interval for the last day,
+/// and per quarter hour for a year so month, year and storm totals survive restarts.
+/// Days, months and years start at midnight in `timezone`.
+#[derive(Debug, Clone)]
+pub struct RainAccumulator {
+    last: Option<(DateTime<Utc>, f32)>,
+    recent: VecDeque<(DateTime<Utc>, f32)>,
+    slots: BTreeMap<DateTime<Utc>, f32>,
+    timezone: DisplayTimezone,
+}
+
+impl RainAccumulator {
+    pub fn new() -> Self {
+        Self::with_timezone(DisplayTimezone::Local)
+    }
+
+    pub fn with_timezone(timezone: DisplayTimezone) -> Self {
+        Self {
+            last: None,
+            recent: VecDeque::new(),
+            slots: BTreeMap::new(),
+            timezone,
+        }
+    }
+
+    pub fn timezone(&self) -> DisplayTimezone {
+        self.timezone
+    }
+
+    pub fn set_timezone(&mut self, timezone: DisplayTimezone) {
+        self.timezone = timezone;
+    }
+
+    pub fn push(&mut self, data: &WeatherData) {
+        let timestamp = data.timestamp;
+        let previous = self.last;
+        if previous.is_some_and(|(last, _)| timestamp <= last) {
+            // Late readings would double count an interval that is already integrated.
+            return;
+        }
+        self.last = Some((timestamp, data.rain_rate.max(0.0)));
+
+        let Some((last, rate)) = previous else {
+            return;
+        };
+        let elapsed = timestamp - last;
+        if elapsed.num_seconds() > MAX_INTEGRATION_SECS || rate <= 0.0 {
+            self.prune(timestamp);
+            return;
+        }
+
+        // The previous reading's rate holds until this one arrives.
+        let amount = rate * elapsed.num_milliseconds() as f32 / 3_600_000.0;
+        self.recent.push_back((timestamp, amount));
+        // The amount fell in (last, timestamp], so one ending on a slot boundary belongs to the slot before it.
+        *self.slots.entry(slot_start(timestamp - Duration::milliseconds(1))).or_insert(0.0) += amount;
+        self.prune(timestamp);
+    }
+
+    fn prune(&mut self, latest: DateTime<Utc>) {
+        let recent_cutoff = latest - Duration::hours(25);
+        while self.recent.front().is_some_and(|(timestamp, _)| *timestamp <= recent_cutoff) {
+            self.recent.pop_front();
+        }
+        let slot_cutoff = latest - Duration::days(SLOT_RETENTION_DAYS);
+        while let Some(entry) = self.slots.first_entry() {
+            if *entry.key() >= slot_cutoff {
+                break;
+            }
+            entry.remove();
+        }
+    }
+
+    /// How far back from the newest reading amounts are kept.
+    pub fn retention() -> Duration {
+        Duration::days(SLOT_RETENTION_DAYS)
+    }
+
+    pub fn latest(&self) -> Option<DateTime<Utc>> {
+        self.last.map(|(timestamp, _)| timestamp)
+    }
+
+    fn recent_since(&self, from: DateTime<Utc>) -> f32 {
+        self.recent.iter()
+            .filter(|(timestamp, _)| *timestamp > from)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    fn slots_since(&self, from: DateTime<Utc>) -> f32 {
+        self.slots.range(slot_start(from)..).map(|(_, amount)| amount).sum()
+    }
+
+    /// Totals anchored on the newest reading; calendar periods follow `timezone`.
+    pub fn totals(&self, storm_gap: Duration) -> RainTotals {
+        let Some(latest) = self.latest() else {
+            return RainTotals::default();
+        };
+        let today = latest.with_timezone(&self.timezone.offset_at(latest)).date_naive();
+
+        RainTotals {
+            last_hour_mm: self.recent_since(latest - Duration::hours(1)),
+            today_mm: self.recent_since(self.midnight(today)),
+            last_24h_mm: self.recent_since(latest - Duration::hours(24)),
+            month_mm: self.slots_since(self.midnight(today.with_day(1).unwrap_or(today))),
+            year_mm: self.slots_since(self.midnight(today.with_ordinal(1).unwrap_or(today))),
+            storm: self.storms(latest - Duration::days(SLOT_RETENTION_DAYS), storm_gap).pop(),
+        }
+    }
+
+    // Start of `date` in `timezone`; the first valid time when midnight falls in a DST gap.
+    fn midnight(&self, date: NaiveDate) -> DateTime<Utc> {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+        (0..=4)
+            .find_map(|quarter| self.timezone.to_utc(midnight + Duration::minutes(15 * quarter)))
+            .unwrap_or_else(|| midnight.and_utc())
+    }
+
+    /// Storms with rain after `from`, oldest first. A storm ends once it has stayed dry
+    /// for `storm_gap`; boundaries are resolved to the quarter hour.
+    pub fn storms(&self, from: DateTime<Utc>, storm_gap: Duration) -> Vec<Storm> {
+        let Some(latest) = self.latest() else {
+            return Vec::new();
+        };
+
+        let mut storms: Vec<Storm> = Vec::new();
+        for (&slot, &amount) in self.slots.range(slot_start(from)..) {
+            if amount <= 0.0 {
+                continue;
+            }
+            let end = slot + Duration::seconds(SLOT_SECS);
+            match storms.last_mut() {
+                Some(storm) if slot - storm.end < storm_gap => {
+                    storm.end = end;
+                    storm.total_mm += amount;
+                }
+                _ => storms.push(Storm { start: slot, end, total_mm: amount, ongoing: false }),
+            }
+        }
+        if let Some(storm) = storms.last_mut() {
+            storm.ongoing = latest - storm.end < storm_gap;
+            storm.end = storm.end.min(latest);
+        }
+        storms
+    }
+
+    /// Amounts for the last `hours`, in buckets sized for a chart of that range.
+    pub fn buckets(&self, hours: u32) -> (i64, Vec<(DateTime<Utc>, f32)>) {
+        let Some(latest) = self.latest() else {
+            return (0, Vec::new());
+        };
+        let bucket_secs: i64 = match hours {
+            0..=1 => 300,
+            2..=6 => 900,
+            7..=24 => 3600,
+            _ => 6 * 3600,
+        };
+        let from = latest - Duration::hours(hours as i64);
+        let bucket_of = |timestamp: DateTime<Utc>| {
+            let offset = self.timezone.offset_at(timestamp).local_minus_utc() as i64;
+            let local_secs = timestamp.timestamp() + offset;
+            let start = local_secs - local_secs.rem_euclid(bucket_secs) - offset;
+            Utc.timestamp_opt(start, 0).single().unwrap_or(timestamp)
+        };
+
+        let mut buckets: BTreeMap<DateTime<Utc>, f32> = BTreeMap::new();
+        let mut bucket = bucket_of(from);
+        while bucket <= latest {
+            buckets.insert(bucket, 0.0);
+            bucket += Duration::seconds(bucket_secs);
+        }
+        if hours <= 24 {
+            for (timestamp, amount) in self.recent.iter().filter(|(timestamp, _)| *timestamp > from) {
+                *buckets.entry(bucket_of(*timestamp)).or_insert(0.0) += amount;
+            }
+        } else {
+            for (slot, amount) in self.slots.range(slot_start(from)..) {
+                *buckets.entry(bucket_of(*slot)).or_insert(0.0) += amount;
+            }
+        }
+
+        (bucket_secs, buckets.into_iter().collect())
+    }
+}
+
+impl Default for RainAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn slot_start(timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    let secs = timestamp.timestamp();
+    Utc.timestamp_opt(secs - secs.rem_euclid(SLOT_SECS), 0).single().unwrap_or(timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn reading(timestamp: DateTime<Utc>, rain_rate: f32) -> WeatherData {
+        WeatherData::new(15.0, 80.0, 1005.0, 3.0, 200.0, rain_rate, 0.0, 0.0).with_timestamp(timestamp)
+    }
+
+    // Readings every ten minutes from `from` to `to` inclusive.
+    fn rain(accumulator: &mut RainAccumulator, from: &str, to: &str, rain_rate: f32) {
+        let (mut timestamp, to) = (time(from), time(to));
+        while timestamp <= to {
+            accumulator.push(&reading(timestamp, rain_rate));
+            timestamp += Duration::minutes(10);
+        }
+    }
+
+    fn close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn integrates_the_previous_rate_over_each_interval() {
+        let mut accumulator = RainAccumulator::with_timezone(DisplayTimezone::Utc);
+        rain(&mut accumulator, "2024-06-01T10:00:00Z", "2024-06-01T11:00:00Z", 6.0);
+        let totals = accumulator.totals(Duration::hours(8));
+        close(totals.last_hour_mm, 6.0);
+        close(totals.today_mm, 6.0);
+        close(totals.month_mm, 6.0);
+
+        // A late reading would integrate an interval twice, so it is ignored.
+        accumulator.push(&reading(time("2024-06-01T10:55:00Z"), 100.0));
+        close(accumulator.totals(Duration::hours(8)).today_mm, 6.0);
+    }
+
+    #[test]
+    fn gaps_negative_and_missing_rates_add_nothing() {
+        let mut accumulator = RainAccumulator::with_timezone(DisplayTimezone::Utc);
+        accumulator.push(&reading(time("2024-06-01T10:00:00Z"), 6.0));
+        // Longer than the integration limit after 10:00: the station was off, not raining.
+        // A counter reset shows up as a negative rate and counts as none.
+        accumulator.push(&reading(time("2024-06-01T10:40:00Z"), -3.0));
+        // Not measured, so nothing is integrated up to the next reading either.
+        accumulator.push(&reading(time("2024-06-01T10:50:00Z"), f32::NAN));
+        accumulator.push(&reading(time("2024-06-01T11:00:00Z"), 6.0));
+        close(accumulator.totals(Duration::hours(8)).today_mm, 0.0);
+        assert!(accumulator.totals(Duration::hours(8)).storm.is_none());
+    }
+
+    #[test]
+    fn calendar_totals_follow_the_configured_zone() {
+        // 18:30 UTC on 31 May is midnight on 1 June in India (+05:30).
+        let mut india = RainAccumulator::with_timezone("+05:30".parse().unwrap());
+        rain(&mut india, "2024-05-31T18:00:00Z", "2024-05-31T19:00:00Z", 6.0);
+        let totals = india.totals(Duration::hours(8));
+        close(totals.today_mm, 3.0);
+        close(totals.month_mm, 3.0);
+        close(totals.year_mm, 6.0);
+        close(totals.last_24h_mm, 6.0);
+
+        let mut utc = india.clone();
+        utc.set_timezone(DisplayTimezone::Utc);
+        let totals = utc.totals(Duration::hours(8));
+        close(totals.today_mm, 6.0);
+        close(totals.month_mm, 6.0);
+
+        // The same rain on New Year's Eve splits the year.
+        let mut new_year = RainAccumulator::with_timezone("+05:30".parse().unwrap());
+        rain(&mut new_year, "2023-12-31T18:00:00Z", "2023-12-31T19:00:00Z", 6.0);
+        close(new_year.totals(Duration::hours(8)).year_mm, 3.0);
+    }
+
+    #[test]
+    fn storms_split_on_dry_gaps() {
+        let mut accumulator = RainAccumulator::with_timezone(DisplayTimezone::Utc);
+        rain(&mut accumulator, "2024-06-01T00:00:00Z", "2024-06-01T00:50:00Z", 3.0);
+        rain(&mut accumulator, "2024-06-01T01:00:00Z", "2024-06-01T09:50:00Z", 0.0);
+        rain(&mut accumulator, "2024-06-01T10:00:00Z", "2024-06-01T11:00:00Z", 6.0);
+        let from = time("2024-05-31T00:00:00Z");
+
+        // Nine dry hours end a storm with an eight hour gap...
+        let storms = accumulator.storms(from, Duration::hours(8));
+        assert_eq!(storms.len(), 2);
+        close(storms[0].total_mm, 3.0);
+        assert!(!storms[0].ongoing);
+        assert_eq!(storms[0].end, time("2024-06-01T01:00:00Z"));
+        close(storms[1].total_mm, 6.0);
+        assert_eq!(storms[1].start, time("2024-06-01T10:00:00Z"));
+        assert!(storms[1].ongoing);
+
+        // ...but not one with a twelve hour gap.
+        let storms = accumulator.storms(from, Duration::hours(12));
+        assert_eq!(storms.len(), 1);
+        close(storms[0].total_mm, 9.0);
+    }
+
+    #[test]
+    fn chart_buckets_cover_the_range() {
+        let mut accumulator = RainAccumulator::with_timezone(DisplayTimezone::Utc);
+        rain(&mut accumulator, "2024-06-01T10:00:00Z", "2024-06-01T12:00:00Z", 6.0);
+        let (bucket_secs, buckets) = accumulator.buckets(6);
+        assert_eq!(bucket_secs, 900);
+        assert_eq!(buckets.first().unwrap().0, time("2024-06-01T06:00:00Z"));
+        close(buckets.iter().map(|(_, amount)| amount).sum(), 12.0);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::data::{Metric, WeatherData};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Statistics of one field over the readings that measured it; min, max and last are
+/// NaN while `count` is 0.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FieldStats {
+    pub min: f32,
+    pub max: f32,
+    pub sum: f64,
+    pub last: f32,
+    pub count: u32,
+}
+
+impl FieldStats {
+    fn new(value: f32) -> Self {
+        let mut stats = Self {
+            min: f32::NAN,
+            max: f32::NAN,
+            sum: 0.0,
+            last: f32::NAN,
+            count: 0,
+        };
+        stats.add(value);
+        stats
+    }
+
+    // `f32::min` and `max` ignore a NaN operand, so the NaN start values fall away.
+    fn add(&mut self, value: f32) {
+        if value.is_nan() {
+            return;
+        }
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as f64;
+        self.last = value;
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: &FieldStats) {
+        if other.count == 0 {
+            return;
+        }
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.last = other.last;
+        self.count += other.count;
+    }
+
+    pub fn mean(&self) -> f32 {
+        if self.count == 0 { f32::NAN } else { (self.sum / self.count as f64) as f32 }
+    }
+}
+
+/// Min/max/mean/last of every field over one bucket of a tier.
+#[derive(Debug, Clone)]
+pub struct Aggregate {
+    pub start: DateTime<Utc>,
+    pub count: u32,
+    fields: [FieldStats; Metric::ALL.len()],
+    // Wind direction is averaged as a vector so 350° and 10° give 0°, not 180°.
+    wind_x: f64,
+    wind_y: f64,
+}
+
+impl Aggregate {
+    pub fn new(start: DateTime<Utc>, data: &WeatherData) -> Self {
+        let (wind_x, wind_y) = wind_vector(data);
+        Self {
+            start,
+            count: 1,
+            fields: Metric::ALL.map(|metric| FieldStats::new(metric.value(data))),
+            wind_x,
+            wind_y,
+        }
+    }
+
+    /// An aggregate summed up elsewhere, e.g. by the database; `wind` is the sum of the
+    /// unit vectors (sine, cosine) of every measured wind direction.
+    pub fn from_parts(start: DateTime<Utc>, count: u32, fields: [FieldStats; Metric::ALL.len()], wind: (f64, f64)) -> Self {
+        Self { start, count, fields, wind_x: wind.0, wind_y: wind.1 }
+    }
+
+    /// Aggregates `readings`, stamped at the first one; `None` if there are none.
+    pub fn from_readings<'a>(readings: impl IntoIterator<Item = &'a WeatherData>) -> Option<Self> {
+        let mut readings = readings.into_iter();
+        let first = readings.next()?;
+        let mut aggregate = Self::new(first.timestamp, first);
+        for data in readings {
+            aggregate.add(data);
+        }
+        Some(aggregate)
+    }
+
+    pub fn add(&mut self, data: &WeatherData) {
+        for metric in Metric::ALL {
+            self.fields[metric as usize].add(metric.value(data));
+        }
+        let (wind_x, wind_y) = wind_vector(data);
+        self.wind_x += wind_x;
+        self.wind_y += wind_y;
+        self.count += 1;
+    }
+
+    /// Folds a later bucket into this one.
+    pub fn merge(&mut self, other: &Aggregate) {
+        for metric in Metric::ALL {
+            self.fields[metric as usize].merge(&other.fields[metric as usize]);
+        }
+        self.wind_x += other.wind_x;
+        self.wind_y += other.wind_y;
+        self.count += other.count;
+    }
+
+    pub fn stats(&self, metric: Metric) -> &FieldStats {
+        &self.fields[metric as usize]
+    }
+
+    /// NaN when no reading in the bucket measured `metric`.
+    pub fn mean(&self, metric: Metric) -> f32 {
+        let stats = &self.fields[metric as usize];
+        if metric == Metric::WindDirection && stats.count > 0 {
+            return (self.wind_x.atan2(self.wind_y).to_degrees() as f32).rem_euclid(360.0);
+        }
+        stats.mean()
+    }
+
+    /// Represents the bucket as a single reading of mean values, stamped at the bucket start.
+    pub fn to_weather_data(&self) -> WeatherData {
+        let mut data = WeatherData::new(
+            self.mean(Metric::Temperature),
+            self.mean(Metric::Humidity),
+            self.mean(Metric::Pressure),
+            self.mean(Metric::WindSpeed),
+            self.mean(Metric::WindDirection),
+            self.mean(Metric::RainRate),
+            self.mean(Metric::UvIndex),
+            self.mean(Metric::SolarRadiation),
+        )
+        .with_timestamp(self.start);
+        data.source = "rollup".to_string();
+        data
+    }
+}
+
+fn wind_vector(data: &WeatherData) -> (f64, f64) {
+    if data.wind_direction.is_nan() {
+        return (0.0, 0.0);
+    }
+    let radians = (data.wind_direction as f64).to_radians();
+    (radians.sin(), radians.cos())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TierConfig {
+    pub resolution_secs: u64,
+    pub retention_hours: u64,
+}
+
+pub fn default_tiers() -> Vec<TierConfig> {
+    vec![
+        TierConfig { resolution_secs: 60, retention_hours: 48 },
+        TierConfig { resolution_secs: 600, retention_hours: 24 * 14 },
+        TierConfig { resolution_secs: 3600, retention_hours: 24 * 366 },
+    ]
+}
+
+pub struct RollupTier {
+    config: TierConfig,
+    buckets: VecDeque<Aggregate>,
+}
+
+impl RollupTier {
+    fn new(config: TierConfig) -> Self {
+        Self {
+            config,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    pub fn resolution(&self) -> Duration {
+        Duration::seconds(self.config.resolution_secs.max(1) as i64)
+    }
+
+    pub fn retention(&self) -> Duration {
+        Duration::hours(self.config.retention_hours as i64)
+    }
+
+    fn bucket_start(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let resolution_ms = self.resolution().num_milliseconds();
+        let ms = timestamp.timestamp_millis();
+        Utc.timestamp_millis_opt(ms - ms.rem_euclid(resolution_ms)).single().unwrap_or(timestamp)
+    }
+
+    fn push(&mut self, data: &WeatherData) {
+        let start = self.bucket_start(data.timestamp);
+
+        match self.buckets.back_mut() {
+            Some(last) if last.start == start => last.add(data),
+            Some(last) if last.start > start => {
+                // Late or backfilled reading: find its bucket instead of appending.
+                let index = self.buckets.partition_point(|bucket| bucket.start < start);
+                match self.buckets.get_mut(index) {
+                    Some(bucket) if bucket.start == start => bucket.add(data),
+                    _ => self.buckets.insert(index, Aggregate::new(start, data)),
+                }
+            }
+            _ => self.buckets.push_back(Aggregate::new(start, data)),
+        }
+
+        if let Some(newest) = self.buckets.back().map(|bucket| bucket.start) {
+            let cutoff = newest - self.retention();
+            while self.buckets.front().is_some_and(|bucket| bucket.start < cutoff) {
+                self.buckets.pop_front();
+            }
+        }
+    }
+
+    pub fn range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> impl Iterator<Item = &Aggregate> {
+        let first = self.buckets.partition_point(|bucket| bucket.start + self.resolution() <= from);
+        self.buckets.range(first..).take_while(move |bucket| bucket.start <= to)
+    }
+}
+
+pub struct Rollups {
+    tiers: Vec<RollupTier>,
+}
+
+impl Rollups {
+    pub fn new(mut configs: Vec<TierConfig>) -> Self {
+        configs.sort_by_key(|config| config.resolution_secs);
+        Self {
+            tiers: configs.into_iter().map(RollupTier::new).collect(),
+        }
+    }
+
+    pub fn push(&mut self, data: &WeatherData) {
+        for tier in self.tiers.iter_mut() {
+            tier.push(data);
+        }
+    }
+
+    pub fn max_retention(&self) -> Duration {
+        self.tiers.iter().map(|tier| tier.retention()).max().unwrap_or_else(Duration::zero)
+    }
+
+    /// Finest tier that keeps the whole span and fits it in `max_points`; falls back to the coarsest.
+    pub fn select_tier(&self, span: Duration, max_points: usize) -> Option<&RollupTier> {
+        self.tiers
+            .iter()
+            .find(|tier| {
+                tier.retention() >= span
+                    && span.num_seconds() / tier.resolution().num_seconds() <= max_points as i64
+            })
+            .or_else(|| self.tiers.last())
+    }
+}
+
+impl Default for Rollups {
+    fn default() -> Self {
+        Self::new(default_tiers())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64, temperature: f32) -> WeatherData {
+        WeatherData::new(temperature, 50.0, 1013.0, 2.0, 90.0, 0.0, 1.0, 100.0)
+            .with_timestamp(Utc.timestamp_opt(1_700_000_000 - 1_700_000_000 % 3600 + secs, 0).unwrap())
+    }
+
+    fn tier(resolution_secs: u64, retention_hours: u64) -> RollupTier {
+        RollupTier::new(TierConfig { resolution_secs, retention_hours })
+    }
+
+    #[test]
+    fn selects_finest_tier_that_fits() {
+        let rollups = Rollups::default();
+        let resolution = |span: Duration, points| rollups.select_tier(span, points).unwrap().resolution().num_seconds();
+        assert_eq!(resolution(Duration::hours(6), 1000), 60);
+        // 48 h of minutes is 2880 points.
+        assert_eq!(resolution(Duration::hours(48), 1000), 600);
+        // Beyond the minute tier's retention even with room for the points.
+        assert_eq!(resolution(Duration::hours(72), 10_000), 600);
+        assert_eq!(resolution(Duration::days(30), 1000), 3600);
+        // Nothing fits, so the coarsest tier is used.
+        assert_eq!(resolution(Duration::days(400), 100), 3600);
+        assert!(Rollups::new(Vec::new()).select_tier(Duration::hours(1), 100).is_none());
+    }
+
+    #[test]
+    fn buckets_split_on_resolution_boundaries() {
+        let mut minute = tier(60, 1);
+        for (secs, temperature) in [(0, 10.0), (30, 12.0), (59, 14.0), (60, 20.0), (119, 22.0)] {
+            minute.push(&at(secs, temperature));
+        }
+        let buckets: Vec<&Aggregate> = minute.range(at(0, 0.0).timestamp, at(120, 0.0).timestamp).collect();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].start, at(0, 0.0).timestamp);
+        assert_eq!(buckets[0].count, 3);
+        assert_eq!(buckets[1].start, at(60, 0.0).timestamp);
+
+        let stats = buckets[0].stats(Metric::Temperature);
+        assert_eq!((stats.min, stats.max, stats.last), (10.0, 14.0, 14.0));
+        assert_eq!(buckets[0].mean(Metric::Temperature), 12.0);
+        assert_eq!(buckets[1].mean(Metric::Temperature), 21.0);
+    }
+
+    #[test]
+    fn late_readings_land_in_their_bucket() {
+        let mut minute = tier(60, 1);
+        minute.push(&at(0, 10.0));
+        minute.push(&at(180, 30.0));
+        minute.push(&at(10, 20.0));
+        minute.push(&at(70, 40.0));
+
+        let starts: Vec<i64> = minute.range(at(0, 0.0).timestamp, at(300, 0.0).timestamp).map(|bucket| bucket.start.timestamp() - at(0, 0.0).timestamp.timestamp()).collect();
+        assert_eq!(starts, vec![0, 60, 180]);
+        assert_eq!(minute.buckets[0].mean(Metric::Temperature), 15.0);
+    }
+
+    #[test]
+    fn range_includes_buckets_overlapping_from() {
+        let mut minute = tier(60, 1);
+        for secs in [0, 60, 120, 180] {
+            minute.push(&at(secs, 10.0));
+        }
+        let count = minute.range(at(90, 0.0).timestamp, at(120, 0.0).timestamp).count();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn retention_prunes_old_buckets() {
+        let mut minute = tier(60, 1);
+        for minutes in 0..=90 {
+            minute.push(&at(minutes * 60, 10.0));
+        }
+        // The newest bucket plus the 60 minutes before it.
+        assert_eq!(minute.buckets.len(), 61);
+        assert_eq!(minute.buckets.front().unwrap().start, at(30 * 60, 0.0).timestamp);
+    }
+
+    #[test]
+    fn wind_direction_mean_is_circular() {
+        let mut north_west = at(0, 10.0);
+        north_west.wind_direction = 350.0;
+        let mut north_east = at(1, 10.0);
+        north_east.wind_direction = 10.0;
+        let aggregate = Aggregate::from_readings([&north_west, &north_east]).unwrap();
+        let mean = aggregate.mean(Metric::WindDirection);
+        assert!(!(0.01..=359.99).contains(&mean), "{}", mean);
+    }
+
+    #[test]
+    fn unmeasured_values_are_left_out() {
+        let mut missing = at(1, 10.0);
+        missing.pressure = f32::NAN;
+        missing.temperature = f32::NAN;
+        let aggregate = Aggregate::from_readings([&at(0, 10.0), &missing, &at(2, 16.0)]).unwrap();
+        assert_eq!(aggregate.count, 3);
+        assert_eq!(aggregate.stats(Metric::Temperature).count, 2);
+        assert_eq!(aggregate.mean(Metric::Temperature), 13.0);
+        assert_eq!(aggregate.mean(Metric::Pressure), 1013.0);
+
+        let mut none = at(0, f32::NAN);
+        none.wind_direction = f32::NAN;
+        let aggregate = Aggregate::from_readings([&none]).unwrap();
+        assert!(aggregate.mean(Metric::Temperature).is_nan());
+        assert!(aggregate.mean(Metric::WindDirection).is_nan());
+    }
+}
@@ -0,0 +1,374 @@
+use crate::data::{PartialReading, WeatherData};
+use crate::sources::{HealthCell, SourceHealth, SourceSender, WeatherSource};
+use crate::units;
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serialport::{ClearBuffer, SerialPort};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const LOOP_PACKET_SIZE: usize = 99;
+const ACK: u8 = 0x06;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopPacket {
+    Loop,
+    Loop2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DavisConfig {
+    pub name: String,
+    pub port: String,
+    pub baud_rate: u32,
+    pub poll_interval_secs: u64,
+    pub packet: LoopPacket,
+    /// Rain collector bucket size: 0.254 mm for the US 0.01" collector, 0.2 mm for metric.
+    pub rain_click_mm: f32,
+}
+
+impl Default for DavisConfig {
+    fn default() -> Self {
+        Self {
+            name: "davis".to_string(),
+            port: "/dev/ttyUSB0".to_string(),
+            baud_rate: 19200,
+            poll_interval_secs: 5,
+            packet: LoopPacket::Loop,
+            rain_click_mm: 0.254,
+        }
+    }
+}
+
+/// CRC-CCITT (XMODEM) as used by the Vantage serial protocol. Running it over a
+/// packet including its trailing CRC bytes yields 0 for a valid packet.
+pub fn crc16_ccitt(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |crc, &byte| {
+        let mut crc = crc ^ ((byte as u16) << 8);
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+fn u16_at(packet: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([packet[offset], packet[offset + 1]])
+}
+
+fn i16_at(packet: &[u8], offset: usize) -> i16 {
+    i16::from_le_bytes([packet[offset], packet[offset + 1]])
+}
+
+/// Decodes a LOOP or LOOP2 packet. Dashed (not connected) sensors come back as `None`.
+pub fn decode_loop_packet(packet: &[u8], rain_click_mm: f32) -> Result<PartialReading> {
+    if packet.len() != LOOP_PACKET_SIZE {
+        bail!("LOOP packet must be {} bytes, got {}", LOOP_PACKET_SIZE, packet.len());
+    }
+    if &packet[0..3] != b"LOO" {
+        bail!("LOOP packet does not start with \"LOO\"");
+    }
+    if crc16_ccitt(packet) != 0 {
+        bail!("LOOP packet CRC mismatch");
+    }
+
+    let barometer = u16_at(packet, 7);
+    let outside_temp = i16_at(packet, 12);
+    let wind_speed = packet[14];
+    let wind_direction = u16_at(packet, 16);
+    let outside_humidity = packet[33];
+    let rain_rate = u16_at(packet, 41);
+    let uv = packet[43];
+    let solar = u16_at(packet, 44);
+
+    let is_loop2 = match packet[4] {
+        0 => false,
+        1 => true,
+        other => bail!("unknown LOOP packet type {}", other),
+    };
+
+    // LOOP2 carries a 2-minute average in 0.1 mph, which is steadier than the instantaneous speed.
+    let wind_mph = if is_loop2 {
+        let avg_2min = u16_at(packet, 20);
+        (avg_2min != 0x7FFF).then_some(avg_2min as f32 / 10.0)
+    } else {
+        (wind_speed != 0xFF).then_some(wind_speed as f32)
+    };
+
+    Ok(PartialReading {
+        timestamp: None,
+        temperature: (outside_temp != 0x7FFF)
+            .then(|| units::fahrenheit_to_celsius(outside_temp as f32 / 10.0)),
+        humidity: (outside_humidity != 0xFF).then_some(outside_humidity as f32),
+        pressure: (barometer != 0).then(|| units::inhg_to_hpa(barometer as f32 / 1000.0)),
+        wind_speed: wind_mph.map(units::mph_to_ms),
+        // The console reports 0 when there is no direction data and 360 for north.
+        wind_direction: (wind_direction != 0 && wind_direction != 0x7FFF)
+            .then_some((wind_direction % 360) as f32),
+        rain_rate: (rain_rate != 0xFFFF).then_some(rain_rate as f32 * rain_click_mm),
+        uv_index: (uv != 0xFF).then_some(uv as f32 / 10.0),
+        solar_radiation: (solar != 0x7FFF).then_some(solar as f32),
+    })
+}
+
+struct Console {
+    port: Box<dyn SerialPort>,
+}
+
+impl Console {
+    fn open(config: &DavisConfig) -> Result<Self> {
+        let port = serialport::new(&config.port, config.baud_rate)
+            .timeout(Duration::from_millis(1200))
+            .open()?;
+        Ok(Self { port })
+    }
+
+    // The console sleeps between requests; a bare newline answered by "\n\r" wakes it.
+    fn wake(&mut self) -> Result<()> {
+        for _ in 0..3 {
+            self.port.clear(ClearBuffer::Input)?;
+            self.port.write_all(b"\n")?;
+            let mut response = [0u8; 2];
+            if self.port.read_exact(&mut response).is_ok() && &response == b"\n\r" {
+                return Ok(());
+            }
+        }
+        Err(anyhow!("console did not respond to wake-up"))
+    }
+
+    fn read_loop(&mut self, packet: LoopPacket) -> Result<[u8; LOOP_PACKET_SIZE]> {
+        self.wake()?;
+        let command: &[u8] = match packet {
+            LoopPacket::Loop => b"LOOP 1\n",
+            LoopPacket::Loop2 => b"LPS 2 1\n",
+        };
+        self.port.write_all(command)?;
+
+        let mut ack = [0u8; 1];
+        self.port.read_exact(&mut ack)?;
+        if ack[0] != ACK {
+            bail!("console answered 0x{:02X} instead of ACK", ack[0]);
+        }
+
+        let mut buffer = [0u8; LOOP_PACKET_SIZE];
+        self.port.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+fn sleep_while_running(running: &AtomicBool, duration: Duration) {
+    let step = Duration::from_millis(100);
+    let mut slept = Duration::ZERO;
+    while slept < duration && running.load(Ordering::SeqCst) {
+        std::thread::sleep(step);
+        slept += step;
+    }
+}
+
+pub struct DavisSource {
+    config: DavisConfig,
+    health: HealthCell,
+    running: Arc<AtomicBool>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl DavisSource {
+    pub fn new(config: DavisConfig) -> Self {
+        Self {
+            config,
+            health: HealthCell::new(),
+            running: Arc::new(AtomicBool::new(false)),
+            task: None,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherSource for DavisSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn start(&mut self, tx: SourceSender) -> Result<()> {
+        let config = self.config.clone();
+        let health = self.health.clone();
+        let running = self.running.clone();
+        running.store(true, Ordering::SeqCst);
+        health.set(SourceHealth::Starting);
+
+        // The serial port is blocking, so the poll loop lives on the blocking pool
+        // and checks `running` between packets.
+        self.task = Some(tokio::task::spawn_blocking(move || {
+            let interval = Duration::from_secs(config.poll_interval_secs.max(1));
+            let mut console: Option<Console> = None;
+            let mut last: Option<WeatherData> = None;
+
+            while running.load(Ordering::SeqCst) {
+                if console.is_none() {
+                    match Console::open(&config) {
+                        Ok(opened) => console = Some(opened),
+                        Err(e) => {
+                            tracing::warn!("Davis source '{}' cannot open {}: {}", config.name, config.port, e);
+                            health.set(SourceHealth::Degraded(format!("cannot open {}: {}", config.port, e)));
+                            sleep_while_running(&running, interval);
+                            continue;
+                        }
+                    }
+                }
+
+                let result = console.as_mut()
+                    .map(|c| c.read_loop(config.packet))
+                    .unwrap_or_else(|| Err(anyhow!("console not open")))
+                    .and_then(|packet| decode_loop_packet(&packet, config.rain_click_mm));
+
+                match result {
+                    Ok(reading) => {
+                        let data = reading.complete(last.as_ref());
+                        last = Some(data.clone());
+                        if !tx.send(data) {
+                            break;
+                        }
+                        health.set(SourceHealth::Healthy);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Davis source '{}' read failed: {}", config.name, e);
+                        health.set(SourceHealth::Degraded(e.to_string()));
+                        // A port error usually means the adapter went away; reopen next time.
+                        if e.downcast_ref::<std::io::Error>().is_some() || e.downcast_ref::<serialport::Error>().is_some() {
+                            console = None;
+                        }
+                    }
+                }
+
+                sleep_while_running(&running, interval);
+            }
+
+            health.set(SourceHealth::Stopped);
+        }));
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        self.health.set(SourceHealth::Stopped);
+        Ok(())
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.get()
+    }
+
+    fn error_count(&self) -> u64 {
+        self.health.error_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthesized from the protocol spec by scripts/make_davis_fixtures.py.
+    const LOOP1: &[u8] = include_bytes!("../../fixtures/davis/loop1.bin");
+    const LOOP2: &[u8] = include_bytes!("../../fixtures/davis/loop2.bin");
+
+    // Rewrites the trailing CRC after a test has edited the packet.
+    fn resign(mut packet: Vec<u8>) -> Vec<u8> {
+        let crc = crc16_ccitt(&packet[..97]);
+        packet[97..99].copy_from_slice(&crc.to_be_bytes());
+        packet
+    }
+
+    fn close(actual: Option<f32>, expected: f32) -> bool {
+        actual.is_some_and(|actual| (actual - expected).abs() < 0.01)
+    }
+
+    #[test]
+    fn crc_of_valid_packet_is_zero() {
+        assert_eq!(crc16_ccitt(LOOP1), 0);
+        assert_eq!(crc16_ccitt(LOOP2), 0);
+        // XMODEM check value.
+        assert_eq!(crc16_ccitt(b"123456789"), 0x31C3);
+    }
+
+    #[test]
+    fn decodes_loop_fixture() {
+        let reading = decode_loop_packet(LOOP1, 0.254).unwrap();
+        assert!(close(reading.temperature, 18.06));
+        assert_eq!(reading.humidity, Some(68.0));
+        assert!(close(reading.pressure, 1016.32));
+        assert!(close(reading.wind_speed, 3.13));
+        assert_eq!(reading.wind_direction, Some(225.0));
+        assert!(close(reading.rain_rate, 3.048));
+        assert_eq!(reading.uv_index, Some(3.5));
+        assert_eq!(reading.solar_radiation, Some(612.0));
+    }
+
+    #[test]
+    fn decodes_loop2_fixture_with_dashed_sensors() {
+        let reading = decode_loop_packet(LOOP2, 0.2).unwrap();
+        assert!(close(reading.temperature, 3.78));
+        assert!(close(reading.pressure, 1011.64));
+        // The 2-minute average (11.8 mph), not the instantaneous 14 mph.
+        assert!(close(reading.wind_speed, 5.275));
+        assert_eq!(reading.wind_direction, Some(0.0));
+        assert_eq!(reading.rain_rate, Some(0.0));
+        assert_eq!(reading.uv_index, None);
+        assert_eq!(reading.solar_radiation, None);
+    }
+
+    #[test]
+    fn rain_clicks_scale_with_bucket_size() {
+        let us = decode_loop_packet(LOOP1, 0.254).unwrap();
+        let metric = decode_loop_packet(LOOP1, 0.2).unwrap();
+        assert!(close(us.rain_rate, 12.0 * 0.254));
+        assert!(close(metric.rain_rate, 12.0 * 0.2));
+    }
+
+    #[test]
+    fn dashed_values_are_none() {
+        let mut packet = LOOP1.to_vec();
+        packet[12..14].copy_from_slice(&0x7FFFu16.to_le_bytes());
+        packet[33] = 0xFF;
+        packet[7..9].copy_from_slice(&0u16.to_le_bytes());
+        packet[14] = 0xFF;
+        packet[16..18].copy_from_slice(&0u16.to_le_bytes());
+        packet[41..43].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        let reading = decode_loop_packet(&resign(packet), 0.254).unwrap();
+        assert_eq!(reading.temperature, None);
+        assert_eq!(reading.humidity, None);
+        assert_eq!(reading.pressure, None);
+        assert_eq!(reading.wind_speed, None);
+        assert_eq!(reading.wind_direction, None);
+        assert_eq!(reading.rain_rate, None);
+        assert_eq!(reading.uv_index, Some(3.5));
+    }
+
+    #[test]
+    fn rejects_bad_crc() {
+        let mut packet = LOOP1.to_vec();
+        packet[12] ^= 0x01;
+        let error = decode_loop_packet(&packet, 0.254).unwrap_err();
+        assert!(error.to_string().contains("CRC"));
+    }
+
+    #[test]
+    fn rejects_malformed_packets() {
+        assert!(decode_loop_packet(&LOOP1[..98], 0.254).is_err());
+        let mut packet = LOOP1.to_vec();
+        packet[0] = b'X';
+        assert!(decode_loop_packet(&resign(packet), 0.254).is_err());
+        let mut packet = LOOP1.to_vec();
+        packet[4] = 7;
+        assert!(decode_loop_packet(&resign(packet), 0.254).unwrap_err().to_string().contains("type 7"));
+    }
+}
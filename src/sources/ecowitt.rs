@@ -0,0 +1,307 @@
+use crate::data::{PartialReading, WeatherData};
+use crate::sources::{HealthCell, SourceHealth, SourceSender, WeatherSource};
+use crate::units;
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Form, Router};
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EcowittConfig {
+    pub name: String,
+    pub bind: String,
+    pub path: String,
+    /// When set, posts carrying a different PASSKEY (Ecowitt) or MAC (Ambient) are rejected.
+    pub passkey: Option<String>,
+}
+
+impl Default for EcowittConfig {
+    fn default() -> Self {
+        Self {
+            name: "ecowitt".to_string(),
+            bind: "0.0.0.0:8081".to_string(),
+            path: "/data/report".to_string(),
+            passkey: None,
+        }
+    }
+}
+
+impl EcowittConfig {
+    /// Checks the route path, which axum would otherwise reject with a panic at startup.
+    pub fn validate(&self) -> Result<()> {
+        if !self.path.starts_with('/') {
+            bail!("path: '{}' must start with '/', e.g. /data/report", self.path);
+        }
+        Ok(())
+    }
+}
+
+/// Converts the imperial "custom server" fields shared by Ecowitt and Ambient gateways.
+pub fn parse_gateway_fields(fields: &HashMap<String, String>) -> PartialReading {
+    let number = |key: &str| fields.get(key).and_then(|v| v.trim().parse::<f32>().ok());
+
+    PartialReading {
+        timestamp: fields.get("dateutc").and_then(|value| {
+            NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|naive| Utc.from_utc_datetime(&naive))
+        }),
+        temperature: number("tempf").map(units::fahrenheit_to_celsius),
+        humidity: number("humidity"),
+        pressure: number("baromrelin")
+            .or_else(|| number("baromabsin"))
+            .map(units::inhg_to_hpa),
+        wind_speed: number("windspeedmph")
+            .or_else(|| number("windspdmph_avg10m"))
+            .map(units::mph_to_ms),
+        wind_direction: number("winddir"),
+        rain_rate: number("rainratein").map(units::inches_to_mm),
+        uv_index: number("uv"),
+        solar_radiation: number("solarradiation"),
+    }
+}
+
+struct IngestState {
+    tx: SourceSender,
+    passkey: Option<String>,
+    health: HealthCell,
+    last: Mutex<Option<WeatherData>>,
+}
+
+impl IngestState {
+    fn ingest(&self, fields: HashMap<String, String>) -> StatusCode {
+        if let Some(expected) = &self.passkey {
+            let provided = fields.get("PASSKEY").or_else(|| fields.get("MAC"));
+            if provided != Some(expected) {
+                tracing::warn!("Rejected gateway post with unknown passkey");
+                return StatusCode::FORBIDDEN;
+            }
+        }
+
+        let reading = parse_gateway_fields(&fields);
+        if reading.is_empty() {
+            return StatusCode::BAD_REQUEST;
+        }
+
+        let Ok(mut last) = self.last.lock() else {
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        };
+        let data = reading.complete(last.as_ref());
+        *last = Some(data.clone());
+
+        if self.tx.send(data) {
+            self.health.set(SourceHealth::Healthy);
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+// Ecowitt gateways POST form bodies, Ambient consoles GET with a query string.
+async fn ingest_form(State(state): State<Arc<IngestState>>, Form(fields): Form<HashMap<String, String>>) -> StatusCode {
+    state.ingest(fields)
+}
+
+async fn ingest_query(State(state): State<Arc<IngestState>>, Query(fields): Query<HashMap<String, String>>) -> StatusCode {
+    state.ingest(fields)
+}
+
+pub struct EcowittSource {
+    config: EcowittConfig,
+    health: HealthCell,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl EcowittSource {
+    pub fn new(config: EcowittConfig) -> Self {
+        Self {
+            config,
+            health: HealthCell::new(),
+            shutdown: None,
+            task: None,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherSource for EcowittSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn start(&mut self, tx: SourceSender) -> Result<()> {
+        let state = Arc::new(IngestState {
+            tx,
+            passkey: self.config.passkey.clone(),
+            health: self.health.clone(),
+            last: Mutex::new(None),
+        });
+
+        self.config.validate()?;
+        let path = self.config.path.clone();
+        let route = get(ingest_query).post(ingest_form);
+        // Gateways differ in whether they append a trailing slash, so accept both.
+        let alternate = match path.strip_suffix('/') {
+            Some("") => None,
+            Some(trimmed) => Some(trimmed.to_string()),
+            None => Some(format!("{}/", path)),
+        };
+        let mut app = Router::new().route(&path, route.clone());
+        if let Some(alternate) = alternate {
+            app = app.route(&alternate, route);
+        }
+        let app = app.with_state(state);
+
+        let listener = tokio::net::TcpListener::bind(&self.config.bind).await?;
+        tracing::info!("Gateway ingest '{}' listening on http://{}{}", self.config.name, self.config.bind, path);
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let health = self.health.clone();
+        // Healthy once the first reading arrives; until then the gateway may not be configured yet.
+        health.set(SourceHealth::Starting);
+
+        self.task = Some(tokio::spawn(async move {
+            let server = axum::serve(listener, app).with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            });
+            if let Err(e) = server.await {
+                tracing::error!("Gateway ingest server failed: {}", e);
+                health.set(SourceHealth::Failed(e.to_string()));
+            }
+        }));
+        self.shutdown = Some(shutdown_tx);
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        self.health.set(SourceHealth::Stopped);
+        Ok(())
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.get()
+    }
+
+    fn error_count(&self) -> u64 {
+        self.health.error_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::IngestMetrics;
+    use tokio::sync::mpsc;
+
+    const ECOWITT: &str = include_str!("../../fixtures/ecowitt/gw2000.form");
+    const AMBIENT: &str = include_str!("../../fixtures/ambient/ws2902.query");
+
+    // The fixtures only need `+` decoded.
+    fn fields(encoded: &str) -> HashMap<String, String> {
+        encoded.trim()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.replace('+', " ")))
+            .collect()
+    }
+
+    fn close(actual: Option<f32>, expected: f32) -> bool {
+        actual.is_some_and(|actual| (actual - expected).abs() < 0.01)
+    }
+
+    fn ingest_state(passkey: Option<&str>) -> (IngestState, mpsc::UnboundedReceiver<WeatherData>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let state = IngestState {
+            tx: SourceSender::new("gateway", tx, Arc::new(IngestMetrics::new())),
+            passkey: passkey.map(str::to_string),
+            health: HealthCell::new(),
+            last: Mutex::new(None),
+        };
+        (state, rx)
+    }
+
+    #[test]
+    fn converts_ecowitt_fixture_to_metric() {
+        let reading = parse_gateway_fields(&fields(ECOWITT));
+        assert_eq!(reading.timestamp, Some(Utc.with_ymd_and_hms(2024, 6, 1, 14, 5, 12).unwrap()));
+        assert!(close(reading.temperature, 20.5));
+        assert_eq!(reading.humidity, Some(62.0));
+        assert!(close(reading.pressure, 1013.24));
+        assert!(close(reading.wind_speed, 2.593));
+        assert_eq!(reading.wind_direction, Some(212.0));
+        assert_eq!(reading.rain_rate, Some(0.0));
+        assert_eq!(reading.uv_index, Some(4.0));
+        assert!(close(reading.solar_radiation, 512.4));
+    }
+
+    #[test]
+    fn converts_ambient_fixture_to_metric() {
+        let reading = parse_gateway_fields(&fields(AMBIENT));
+        assert!(close(reading.temperature, 21.28));
+        assert!(close(reading.pressure, 1016.32));
+        assert!(close(reading.wind_speed, 1.52));
+        assert!(close(reading.rain_rate, 3.048));
+        assert!(close(reading.solar_radiation, 622.1));
+    }
+
+    #[test]
+    fn missing_and_malformed_keys_are_none() {
+        let reading = parse_gateway_fields(&fields("humidity=55&tempf=abc&baromabsin=29.5"));
+        assert_eq!(reading.humidity, Some(55.0));
+        assert_eq!(reading.temperature, None);
+        assert_eq!(reading.wind_speed, None);
+        assert_eq!(reading.timestamp, None);
+        // Falls back to absolute pressure when the relative one is missing.
+        assert!(close(reading.pressure, 998.99));
+        assert!(parse_gateway_fields(&fields("stationtype=GW2000A")).is_empty());
+    }
+
+    #[test]
+    fn ingest_checks_passkey_and_content() {
+        let (state, mut rx) = ingest_state(Some("ABCDEF0123456789"));
+        assert_eq!(state.ingest(fields("PASSKEY=WRONG&tempf=70")), StatusCode::FORBIDDEN);
+        assert_eq!(state.ingest(fields("PASSKEY=ABCDEF0123456789&model=GW2000A")), StatusCode::BAD_REQUEST);
+        assert_eq!(state.ingest(fields(ECOWITT)), StatusCode::OK);
+        assert_eq!(rx.try_recv().unwrap().source, "gateway");
+        assert!(state.health.get().is_healthy());
+
+        let (state, _rx) = ingest_state(Some("00:0E:C6:20:0F:7B"));
+        assert_eq!(state.ingest(fields(AMBIENT)), StatusCode::OK);
+    }
+
+    #[test]
+    fn path_must_start_with_slash() {
+        let config = |path: &str| EcowittConfig { path: path.to_string(), ..Default::default() };
+        assert!(config("/").validate().is_ok());
+        assert!(config("/data/report/").validate().is_ok());
+        assert!(config("").validate().unwrap_err().to_string().starts_with("path:"));
+        assert!(config("data/report").validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn root_path_starts() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut source = EcowittSource::new(EcowittConfig { bind: "127.0.0.1:0".to_string(), path: "/".to_string(), ..Default::default() });
+        source.start(SourceSender::new("gateway", tx, Arc::new(IngestMetrics::new()))).await.unwrap();
+        source.stop().await.unwrap();
+        assert_eq!(source.health(), SourceHealth::Stopped);
+    }
+}
@@ -0,0 +1,204 @@
+pub mod davis;
+pub mod ecowitt;
+pub mod mqtt;
+pub mod replay;
+pub mod rtl433;
+pub mod simulator;
+
+pub use davis::{DavisConfig, DavisSource};
+pub use ecowitt::{EcowittConfig, EcowittSource};
+pub use mqtt::{MqttConfig, MqttSource};
+pub use replay::{ReplayConfig, ReplaySource};
+pub use rtl433::{Rtl433Config, Rtl433Source};
+pub use simulator::{SimulatorConfig, SimulatorSource};
+
+use crate::data::WeatherData;
+use crate::metrics::IngestMetrics;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceHealth {
+    Stopped,
+    Starting,
+    Healthy,
+    Degraded(String),
+    Failed(String),
+}
+
+impl SourceHealth {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SourceHealth::Stopped => "Stopped",
+            SourceHealth::Starting => "Starting",
+            SourceHealth::Healthy => "Healthy",
+            SourceHealth::Degraded(_) => "Degraded",
+            SourceHealth::Failed(_) => "Failed",
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, SourceHealth::Healthy)
+    }
+}
+
+/// Shared between a source and the task it spawns so `health()` stays cheap and sync.
+/// Every time it is set to degraded or failed counts as one error.
+#[derive(Debug, Clone)]
+pub struct HealthCell(Arc<RwLock<SourceHealth>>, Arc<AtomicU64>);
+
+impl HealthCell {
+    pub fn new() -> Self {
+        Self(Arc::new(RwLock::new(SourceHealth::Stopped)), Arc::new(AtomicU64::new(0)))
+    }
+
+    pub fn set(&self, health: SourceHealth) {
+        if matches!(health, SourceHealth::Degraded(_) | SourceHealth::Failed(_)) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Ok(mut current) = self.0.write() {
+            *current = health;
+        }
+    }
+
+    pub fn get(&self) -> SourceHealth {
+        self.0
+            .read()
+            .map(|health| health.clone())
+            .unwrap_or_else(|_| SourceHealth::Failed("health lock poisoned".to_string()))
+    }
+
+    pub fn error_count(&self) -> u64 {
+        self.1.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for HealthCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Handed to each source on start; tags every reading with the source name before
+/// forwarding it to the collector channel.
+#[derive(Debug, Clone)]
+pub struct SourceSender {
+    name: String,
+    tx: mpsc::UnboundedSender<WeatherData>,
+    metrics: Arc<IngestMetrics>,
+}
+
+impl SourceSender {
+    pub fn new(name: impl Into<String>, tx: mpsc::UnboundedSender<WeatherData>, metrics: Arc<IngestMetrics>) -> Self {
+        Self {
+            name: name.into(),
+            tx,
+            metrics,
+        }
+    }
+
+    /// Returns false once the collector side has gone away.
+    pub fn send(&self, mut data: WeatherData) -> bool {
+        data.source = self.name.clone();
+        let delivered = self.tx.send(data).is_ok();
+        if delivered {
+            self.metrics.record_sent(&self.name);
+        }
+        delivered
+    }
+}
+
+/// A station, feed or simulator producing readings.
+#[async_trait]
+pub trait WeatherSource: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Spawns the source's background work and returns once it is running.
+    async fn start(&mut self, tx: SourceSender) -> Result<()>;
+
+    async fn stop(&mut self) -> Result<()>;
+
+    fn health(&self) -> SourceHealth;
+
+    /// How often the source has failed since it was created.
+    fn error_count(&self) -> u64 {
+        0
+    }
+}
+
+/// Serialisable description of a source, tagged by `"type"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceConfig {
+    Simulator(SimulatorConfig),
+    Mqtt(Box<MqttConfig>),
+    Davis(DavisConfig),
+    Ecowitt(EcowittConfig),
+    Rtl433(Rtl433Config),
+    Replay(ReplayConfig),
+}
+
+impl SourceConfig {
+    pub fn build(&self) -> Box<dyn WeatherSource> {
+        match self {
+            SourceConfig::Simulator(config) => Box::new(SimulatorSource::new(config.clone())),
+            SourceConfig::Mqtt(config) => Box::new(MqttSource::new(config.as_ref().clone())),
+            SourceConfig::Davis(config) => Box::new(DavisSource::new(config.clone())),
+            SourceConfig::Ecowitt(config) => Box::new(EcowittSource::new(config.clone())),
+            SourceConfig::Rtl433(config) => Box::new(Rtl433Source::new(config.clone())),
+            SourceConfig::Replay(config) => Box::new(ReplaySource::new(config.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_cell_counts_degraded_and_failed_as_errors() {
+        let health = HealthCell::new();
+        assert_eq!(health.get(), SourceHealth::Stopped);
+
+        health.set(SourceHealth::Starting);
+        health.set(SourceHealth::Healthy);
+        assert!(health.get().is_healthy());
+        assert_eq!(health.error_count(), 0);
+
+        health.set(SourceHealth::Degraded("timeout".to_string()));
+        health.set(SourceHealth::Failed("gone".to_string()));
+        health.set(SourceHealth::Healthy);
+        assert_eq!(health.error_count(), 2);
+        assert_eq!(health.get(), SourceHealth::Healthy);
+    }
+
+    #[test]
+    fn health_cell_clones_share_state() {
+        let health = HealthCell::new();
+        let task_side = health.clone();
+        task_side.set(SourceHealth::Degraded("no data".to_string()));
+        assert_eq!(health.get().label(), "Degraded");
+        assert_eq!(health.error_count(), 1);
+    }
+
+    #[test]
+    fn sender_tags_readings_and_counts_delivery() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let metrics = Arc::new(IngestMetrics::new());
+        let sender = SourceSender::new("roof", tx, metrics.clone());
+
+        let mut data = WeatherData::new(20.0, 50.0, 1013.0, 1.0, 90.0, 0.0, 1.0, 100.0);
+        data.source = "spoofed".to_string();
+        assert!(sender.send(data));
+        assert_eq!(rx.try_recv().unwrap().source, "roof");
+
+        drop(rx);
+        assert!(!sender.send(WeatherData::new(20.0, 50.0, 1013.0, 1.0, 90.0, 0.0, 1.0, 100.0)));
+        let counters = &metrics.snapshot()["roof"];
+        assert_eq!((counters.sent, counters.received), (1, 0));
+    }
+}
@@ -0,0 +1,427 @@
+use crate::data::{Metric, PartialReading, WeatherData};
+use crate::sources::{HealthCell, SourceHealth, SourceSender, WeatherSource};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub topics: Vec<String>,
+    pub qos: u8,
+    pub keep_alive_secs: u64,
+    pub reconnect_min_secs: u64,
+    pub reconnect_max_secs: u64,
+    /// How long a field missing from later messages keeps its last published value before
+    /// it is reported as not measured.
+    pub max_field_age_secs: u64,
+    pub fields: FieldMapping,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            name: "mqtt".to_string(),
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "weather-telemetry".to_string(),
+            username: None,
+            password: None,
+            topics: vec!["weather/#".to_string()],
+            qos: 1,
+            keep_alive_secs: 30,
+            reconnect_min_secs: 1,
+            reconnect_max_secs: 60,
+            max_field_age_secs: 600,
+            fields: FieldMapping::default(),
+        }
+    }
+}
+
+/// Dotted paths into the JSON payload, e.g. "outdoor.temp_c" or "sensors.0.value".
+/// A field mapped to `None` is never read from the payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FieldMapping {
+    pub timestamp: Option<String>,
+    pub temperature: Option<String>,
+    pub humidity: Option<String>,
+    pub pressure: Option<String>,
+    pub wind_speed: Option<String>,
+    pub wind_direction: Option<String>,
+    pub rain_rate: Option<String>,
+    pub uv_index: Option<String>,
+    pub solar_radiation: Option<String>,
+}
+
+impl Default for FieldMapping {
+    fn default() -> Self {
+        Self {
+            timestamp: Some("timestamp".to_string()),
+            temperature: Some("temperature".to_string()),
+            humidity: Some("humidity".to_string()),
+            pressure: Some("pressure".to_string()),
+            wind_speed: Some("wind_speed".to_string()),
+            wind_direction: Some("wind_direction".to_string()),
+            rain_rate: Some("rain_rate".to_string()),
+            uv_index: Some("uv_index".to_string()),
+            solar_radiation: Some("solar_radiation".to_string()),
+        }
+    }
+}
+
+impl FieldMapping {
+    pub fn apply(&self, payload: &Value) -> PartialReading {
+        let number = |path: &Option<String>| {
+            path.as_deref()
+                .and_then(|path| lookup(payload, path))
+                .and_then(value_as_f32)
+        };
+
+        PartialReading {
+            timestamp: self.timestamp.as_deref()
+                .and_then(|path| lookup(payload, path))
+                .and_then(value_as_timestamp),
+            temperature: number(&self.temperature),
+            humidity: number(&self.humidity),
+            pressure: number(&self.pressure),
+            wind_speed: number(&self.wind_speed),
+            wind_direction: number(&self.wind_direction),
+            rain_rate: number(&self.rain_rate),
+            uv_index: number(&self.uv_index),
+            solar_radiation: number(&self.solar_radiation),
+        }
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |current, key| match current {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+fn value_as_f32(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(n) => n.as_f64().map(|n| n as f32),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+// Accepts RFC 3339 strings or unix time in seconds or milliseconds.
+fn value_as_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::String(s) => DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc)),
+        Value::Number(n) => {
+            let n = n.as_f64()?;
+            let millis = if n > 1e12 { n } else { n * 1000.0 };
+            Utc.timestamp_millis_opt(millis as i64).single()
+        }
+        _ => None,
+    }
+}
+
+// Sensors published separately are carried forward into each other's readings, but only
+// for `max_age`, so a sensor that stops reporting goes missing instead of repeating its
+// last value.
+struct CarryForward {
+    last: Option<WeatherData>,
+    measured: [Option<DateTime<Utc>>; Metric::ALL.len()],
+    max_age: chrono::Duration,
+}
+
+impl CarryForward {
+    fn new(max_age: chrono::Duration) -> Self {
+        Self {
+            last: None,
+            measured: [None; Metric::ALL.len()],
+            max_age,
+        }
+    }
+
+    fn complete(&mut self, mut reading: PartialReading) -> WeatherData {
+        let now = *reading.timestamp.get_or_insert_with(Utc::now);
+        for metric in Metric::ALL {
+            let measured = &mut self.measured[metric as usize];
+            if reading.get(metric).is_some() {
+                *measured = Some(now);
+            } else if measured.is_some_and(|measured| now - measured > self.max_age) {
+                *reading.get_mut(metric) = Some(f32::NAN);
+                *measured = None;
+            }
+        }
+        let data = reading.complete(self.last.as_ref());
+        self.last = Some(data.clone());
+        data
+    }
+}
+
+fn qos_from_level(level: u8) -> Result<QoS> {
+    match level {
+        0 => Ok(QoS::AtMostOnce),
+        1 => Ok(QoS::AtLeastOnce),
+        2 => Ok(QoS::ExactlyOnce),
+        other => Err(anyhow!("invalid MQTT QoS level {}, expected 0, 1 or 2", other)),
+    }
+}
+
+pub struct MqttSource {
+    config: MqttConfig,
+    health: HealthCell,
+    client: Option<AsyncClient>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl MqttSource {
+    pub fn new(config: MqttConfig) -> Self {
+        Self {
+            config,
+            health: HealthCell::new(),
+            client: None,
+            task: None,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherSource for MqttSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn start(&mut self, tx: SourceSender) -> Result<()> {
+        if self.config.topics.is_empty() {
+            return Err(anyhow!("no MQTT topics configured"));
+        }
+        let qos = qos_from_level(self.config.qos)?;
+
+        let mut options = MqttOptions::new(&self.config.client_id, &self.config.host, self.config.port);
+        options.set_keep_alive(Duration::from_secs(self.config.keep_alive_secs.max(5)));
+        if let (Some(username), Some(password)) = (&self.config.username, &self.config.password) {
+            options.set_credentials(username, password);
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(options, 64);
+        let config = self.config.clone();
+        let health = self.health.clone();
+        let subscriber = client.clone();
+        health.set(SourceHealth::Starting);
+
+        self.task = Some(tokio::spawn(async move {
+            let min_backoff = Duration::from_secs(config.reconnect_min_secs.max(1));
+            let max_backoff = Duration::from_secs(config.reconnect_max_secs.max(config.reconnect_min_secs.max(1)));
+            let mut backoff = min_backoff;
+            let max_field_age = i64::try_from(config.max_field_age_secs).ok().and_then(chrono::Duration::try_seconds).unwrap_or(chrono::Duration::MAX);
+            let mut carried = CarryForward::new(max_field_age);
+
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        tracing::info!("MQTT source '{}' connected to {}:{}", config.name, config.host, config.port);
+                        backoff = min_backoff;
+                        health.set(SourceHealth::Healthy);
+                        // Subscriptions do not survive a clean-session reconnect.
+                        for topic in &config.topics {
+                            if let Err(e) = subscriber.try_subscribe(topic.as_str(), qos) {
+                                tracing::warn!("MQTT source '{}' failed to subscribe to {}: {}", config.name, topic, e);
+                            }
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let reading = serde_json::from_slice::<Value>(&publish.payload)
+                            .map(|payload| config.fields.apply(&payload));
+                        match reading {
+                            Ok(reading) if !reading.is_empty() => {
+                                if !tx.send(carried.complete(reading)) {
+                                    health.set(SourceHealth::Stopped);
+                                    break;
+                                }
+                            }
+                            Ok(_) => {
+                                tracing::debug!("MQTT message on {} has no mapped fields", publish.topic);
+                            }
+                            Err(e) => {
+                                tracing::warn!("Invalid JSON payload on {}: {}", publish.topic, e);
+                            }
+                        }
+                    }
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => {
+                        health.set(SourceHealth::Stopped);
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("MQTT source '{}' connection error: {}, retrying in {:?}", config.name, e, backoff);
+                        health.set(SourceHealth::Degraded(format!("reconnecting: {}", e)));
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(max_backoff);
+                    }
+                }
+            }
+        }));
+        self.client = Some(client);
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(client) = self.client.take() {
+            let _ = client.try_disconnect();
+        }
+        if let Some(mut task) = self.task.take() {
+            if tokio::time::timeout(Duration::from_secs(2), &mut task).await.is_err() {
+                task.abort();
+            }
+        }
+        self.health.set(SourceHealth::Stopped);
+        Ok(())
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.get()
+    }
+
+    fn error_count(&self) -> u64 {
+        self.health.error_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::IngestMetrics;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn mapping_follows_dotted_paths_into_objects_and_arrays() {
+        let mapping = FieldMapping {
+            temperature: Some("outdoor.temp_c".to_string()),
+            humidity: Some("sensors.1.value".to_string()),
+            pressure: Some("baro".to_string()),
+            wind_speed: None,
+            ..FieldMapping::default()
+        };
+        let payload = json!({
+            "outdoor": { "temp_c": 18.5 },
+            "sensors": [{ "value": 1.0 }, { "value": "71" }],
+            "baro": "not a number",
+            "wind_speed": 3.0,
+        });
+        let reading = mapping.apply(&payload);
+        assert_eq!(reading.temperature, Some(18.5));
+        assert_eq!(reading.humidity, Some(71.0));
+        assert_eq!(reading.pressure, None);
+        // Unmapped, though present in the payload.
+        assert_eq!(reading.wind_speed, None);
+        assert_eq!(reading.timestamp, None);
+        assert!(FieldMapping::default().apply(&json!({ "other": 1 })).is_empty());
+        assert!(FieldMapping::default().apply(&json!([1, 2])).is_empty());
+    }
+
+    #[test]
+    fn timestamps_are_read_as_rfc3339_seconds_or_milliseconds() {
+        let timestamp = |value: Value| FieldMapping::default().apply(&json!({ "timestamp": value, "temperature": 1 })).timestamp;
+        assert_eq!(timestamp(json!("2023-11-14T22:13:20Z")), Some(at(0)));
+        assert_eq!(timestamp(json!("2023-11-14T23:13:20+01:00")), Some(at(0)));
+        assert_eq!(timestamp(json!(1_700_000_000)), Some(at(0)));
+        assert_eq!(timestamp(json!(1_700_000_000_500i64)), Some(at(0) + chrono::Duration::milliseconds(500)));
+        assert_eq!(timestamp(json!("yesterday")), None);
+        assert_eq!(timestamp(json!(true)), None);
+    }
+
+    #[test]
+    fn qos_levels_above_two_are_rejected() {
+        assert_eq!(qos_from_level(0).unwrap(), QoS::AtMostOnce);
+        assert_eq!(qos_from_level(2).unwrap(), QoS::ExactlyOnce);
+        assert!(qos_from_level(3).is_err());
+    }
+
+    #[test]
+    fn complete_carries_missing_fields_forward() {
+        let first = PartialReading { timestamp: Some(at(0)), temperature: Some(18.0), humidity: Some(70.0), ..PartialReading::default() };
+        let first = first.complete(None);
+        assert_eq!((first.timestamp, first.temperature, first.humidity), (at(0), 18.0, 70.0));
+        assert!(first.pressure.is_nan());
+
+        let second = PartialReading { timestamp: Some(at(60)), pressure: Some(1012.0), ..PartialReading::default() }.complete(Some(&first));
+        assert_eq!((second.temperature, second.humidity, second.pressure), (18.0, 70.0, 1012.0));
+        assert!(second.wind_speed.is_nan());
+    }
+
+    #[test]
+    fn carried_fields_go_missing_after_the_maximum_age() {
+        let mut carried = CarryForward::new(chrono::Duration::minutes(10));
+        let reading = |secs, temperature: Option<f32>, humidity: Option<f32>| PartialReading { timestamp: Some(at(secs)), temperature, humidity, ..PartialReading::default() };
+
+        carried.complete(reading(0, Some(18.0), Some(70.0)));
+        // The humidity sensor goes quiet.
+        let data = carried.complete(reading(300, Some(19.0), None));
+        assert_eq!((data.temperature, data.humidity), (19.0, 70.0));
+        let data = carried.complete(reading(600, Some(20.0), None));
+        assert_eq!((data.temperature, data.humidity), (20.0, 70.0));
+        let data = carried.complete(reading(660, Some(21.0), None));
+        assert_eq!(data.temperature, 21.0);
+        assert!(data.humidity.is_nan());
+        let data = carried.complete(reading(900, Some(22.0), None));
+        assert!(data.humidity.is_nan());
+
+        // And comes back.
+        let data = carried.complete(reading(960, None, Some(65.0)));
+        assert_eq!((data.temperature, data.humidity), (22.0, 65.0));
+    }
+
+    // Set MQTT_TEST_BROKER=host:port and run with `cargo test -- --ignored`.
+    #[tokio::test]
+    #[ignore = "needs an MQTT broker, see MQTT_TEST_BROKER"]
+    async fn readings_arrive_through_a_broker() {
+        let broker = std::env::var("MQTT_TEST_BROKER").unwrap_or_else(|_| "localhost:1883".to_string());
+        let (host, port) = broker.rsplit_once(':').expect("MQTT_TEST_BROKER is host:port");
+        let topic = format!("weather-telemetry-test/{}", uuid::Uuid::new_v4());
+        let mut source = MqttSource::new(MqttConfig {
+            host: host.to_string(),
+            port: port.parse().unwrap(),
+            client_id: format!("weather-telemetry-test-{}", uuid::Uuid::new_v4()),
+            topics: vec![topic.clone()],
+            fields: FieldMapping { temperature: Some("outdoor.temp_c".to_string()), ..FieldMapping::default() },
+            ..MqttConfig::default()
+        });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        source.start(SourceSender::new("mqtt", tx, Arc::new(IngestMetrics::new()))).await.unwrap();
+
+        let (publisher, mut eventloop) = AsyncClient::new(MqttOptions::new(format!("weather-telemetry-pub-{}", uuid::Uuid::new_v4()), host, port.parse().unwrap()), 10);
+        tokio::spawn(async move { while eventloop.poll().await.is_ok() {} });
+        let received = tokio::time::timeout(Duration::from_secs(10), async {
+            // Published until the source has subscribed and picks one up.
+            loop {
+                publisher.publish(topic.as_str(), QoS::AtLeastOnce, false, r#"{"outdoor": {"temp_c": 18.4}, "humidity": 71}"#).await.unwrap();
+                if let Ok(Some(data)) = tokio::time::timeout(Duration::from_millis(500), rx.recv()).await {
+                    return data;
+                }
+            }
+        }).await.expect("no reading within 10 seconds");
+
+        assert_eq!((received.source.as_str(), received.temperature, received.humidity), ("mqtt", 18.4, 71.0));
+        assert!(received.pressure.is_nan());
+        assert_eq!(source.health(), SourceHealth::Healthy);
+        source.stop().await.unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+use crate::data::WeatherData;
+use crate::sources::{HealthCell, SourceHealth, SourceSender, WeatherSource};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayConfig {
+    pub name: String,
+    pub path: String,
+    /// Playback speed multiplier; 0 replays as fast as possible.
+    pub speed: f64,
+    pub looping: bool,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            name: "replay".to_string(),
+            path: "recording.csv".to_string(),
+            speed: 1.0,
+            looping: false,
+        }
+    }
+}
+
+/// CSV files need a header naming the `WeatherData` fields; `id` and `source` are optional.
+/// Anything other than a `.csv` extension is read as JSON Lines.
+pub fn load_recording(path: &Path) -> Result<Vec<WeatherData>> {
+    let is_csv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+
+    let records = if is_csv {
+        let mut reader = csv::Reader::from_path(path)?;
+        reader
+            .deserialize()
+            .enumerate()
+            .map(|(i, record)| record.with_context(|| format!("{}: record {}", path.display(), i + 1)))
+            .collect::<Result<Vec<WeatherData>>>()?
+    } else {
+        std::fs::read_to_string(path)?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).with_context(|| format!("{}: line {}", path.display(), i + 1))
+            })
+            .collect::<Result<Vec<WeatherData>>>()?
+    };
+
+    if records.is_empty() {
+        return Err(anyhow!("{} contains no readings", path.display()));
+    }
+    Ok(records)
+}
+
+fn delay_between(previous: &WeatherData, next: &WeatherData, speed: f64) -> Option<Duration> {
+    if speed <= 0.0 {
+        return None;
+    }
+    let gap = (next.timestamp - previous.timestamp).to_std().ok()?;
+    Some(gap.div_f64(speed))
+}
+
+// How far each looped pass is shifted: the recording's span plus one average sample
+// interval, so the next pass starts one interval after the last reading.
+fn loop_period(records: &[WeatherData]) -> chrono::Duration {
+    let (Some(first), Some(last)) = (records.first(), records.last()) else {
+        return chrono::Duration::zero();
+    };
+    let span = last.timestamp - first.timestamp;
+    let interval = match records.len() {
+        1 => chrono::Duration::seconds(1),
+        n => span / (n as i32 - 1),
+    };
+    span + interval.max(chrono::Duration::milliseconds(1))
+}
+
+// A copy of `record` for pass `pass`, with its own id so looped readings are not taken for duplicates.
+fn looped(record: &WeatherData, period: chrono::Duration, pass: i32) -> WeatherData {
+    let mut data = record.clone();
+    data.timestamp += period * pass;
+    data.id = Uuid::new_v4();
+    data
+}
+
+pub struct ReplaySource {
+    config: ReplayConfig,
+    health: HealthCell,
+    task: Option<JoinHandle<()>>,
+}
+
+impl ReplaySource {
+    pub fn new(config: ReplayConfig) -> Self {
+        Self {
+            config,
+            health: HealthCell::new(),
+            task: None,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherSource for ReplaySource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn start(&mut self, tx: SourceSender) -> Result<()> {
+        let path = self.config.path.clone();
+        let records = tokio::task::spawn_blocking(move || load_recording(Path::new(&path))).await??;
+        tracing::info!("Replaying {} readings from {}", records.len(), self.config.path);
+
+        let config = self.config.clone();
+        let health = self.health.clone();
+        health.set(SourceHealth::Healthy);
+
+        self.task = Some(tokio::spawn(async move {
+            let period = loop_period(&records);
+            let mut previous: Option<WeatherData> = None;
+            'replay: for pass in 0.. {
+                for (i, record) in records.iter().enumerate() {
+                    let record = if pass == 0 { record.clone() } else { looped(record, period, pass) };
+                    match previous.as_ref().and_then(|previous| delay_between(previous, &record, config.speed)) {
+                        Some(delay) => tokio::time::sleep(delay).await,
+                        // Let the rest of the runtime breathe when replaying flat out.
+                        None if i % 256 == 0 => tokio::task::yield_now().await,
+                        None => {}
+                    }
+
+                    if !tx.send(record.clone()) {
+                        break 'replay;
+                    }
+                    previous = Some(record);
+                }
+
+                if !config.looping {
+                    break;
+                }
+            }
+
+            health.set(SourceHealth::Stopped);
+        }));
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.health.set(SourceHealth::Stopped);
+        Ok(())
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.get()
+    }
+
+    fn error_count(&self) -> u64 {
+        self.health.error_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::IngestMetrics;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn looping_moves_timestamps_forward_with_new_ids() {
+        let start = chrono::Utc::now() - chrono::Duration::hours(1);
+        let recording: Vec<WeatherData> = (0..3)
+            .map(|i| {
+                let mut data = WeatherData::new(20.0 + i as f32, 50.0, 1013.0, 1.0, 90.0, 0.0, 1.0, 100.0);
+                data.timestamp = start + chrono::Duration::seconds(60 * i);
+                data
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!("replay-{}.jsonl", Uuid::new_v4()));
+        let lines: Vec<String> = recording.iter().map(|data| serde_json::to_string(data).unwrap()).collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut source = ReplaySource::new(ReplayConfig {
+            path: path.display().to_string(),
+            speed: 0.0,
+            looping: true,
+            ..Default::default()
+        });
+        source.start(SourceSender::new("replay", tx, Arc::new(IngestMetrics::new()))).await.unwrap();
+        let mut received = Vec::new();
+        while received.len() < 6 {
+            received.push(rx.recv().await.unwrap());
+        }
+        source.stop().await.unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(received.windows(2).all(|pair| pair[1].timestamp > pair[0].timestamp));
+        // Span of two minutes plus one one-minute interval.
+        assert_eq!(received[3].timestamp, recording[0].timestamp + chrono::Duration::seconds(180));
+        assert_eq!(received[5].temperature, 22.0);
+        assert_eq!(received[0].id, recording[0].id);
+        assert!(received[3..].iter().all(|data| recording.iter().all(|original| original.id != data.id)));
+    }
+
+    #[test]
+    fn single_reading_loops_one_second_apart() {
+        let recording = vec![WeatherData::new(20.0, 50.0, 1013.0, 1.0, 90.0, 0.0, 1.0, 100.0)];
+        assert_eq!(loop_period(&recording), chrono::Duration::seconds(1));
+    }
+}
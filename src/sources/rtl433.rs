@@ -0,0 +1,516 @@
+use crate::data::{PartialReading, WeatherData};
+use crate::sources::{HealthCell, SourceHealth, SourceSender, WeatherSource};
+use crate::units;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rtl433Config {
+    pub name: String,
+    /// "-" for stdin, "tcp://host:port" for a socket, anything else is a file path.
+    pub input: String,
+    pub window_secs: u64,
+    /// "model:id" or bare ids to accept; empty accepts every recognized weather sensor.
+    pub sensors: Vec<String>,
+}
+
+impl Default for Rtl433Config {
+    fn default() -> Self {
+        Self {
+            name: "rtl433".to_string(),
+            input: "-".to_string(),
+            window_secs: 60,
+            sensors: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SensorFamily {
+    Acurite,
+    FineOffset,
+    LaCrosse,
+}
+
+impl SensorFamily {
+    pub fn from_model(model: &str) -> Option<Self> {
+        let model = model.to_ascii_lowercase();
+        if model.starts_with("acurite") {
+            Some(SensorFamily::Acurite)
+        } else if model.starts_with("fineoffset") || model.starts_with("fine offset") {
+            Some(SensorFamily::FineOffset)
+        } else if model.starts_with("lacrosse") {
+            Some(SensorFamily::LaCrosse)
+        } else {
+            None
+        }
+    }
+}
+
+/// One decoded rtl_433 line that belongs to a recognized weather sensor.
+#[derive(Debug, Clone)]
+pub struct SensorMessage {
+    pub sensor_key: String,
+    pub timestamp: DateTime<Utc>,
+    pub reading: PartialReading,
+    /// Cumulative rain counter; most sensors report a total rather than a rate.
+    pub rain_total_mm: Option<f32>,
+}
+
+fn number(message: &Value, key: &str) -> Option<f32> {
+    message.get(key).and_then(|v| v.as_f64()).map(|v| v as f32)
+}
+
+// rtl_433 prints local time by default and ISO 8601 with `-M time:iso:utc`.
+fn parse_time(message: &Value) -> Option<DateTime<Utc>> {
+    let time = message.get("time")?.as_str()?;
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(time) {
+        return Some(parsed.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S"))
+        .ok()?;
+    Local.from_local_datetime(&naive).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+pub fn parse_line(line: &str) -> Option<SensorMessage> {
+    let message: Value = serde_json::from_str(line).ok()?;
+    let model = message.get("model")?.as_str()?;
+    SensorFamily::from_model(model)?;
+
+    let id = message.get("id").map(|id| match id {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }).unwrap_or_default();
+    let channel = message.get("channel").map(|c| c.to_string().trim_matches('"').to_string());
+    let sensor_key = match channel {
+        Some(channel) => format!("{}:{}:{}", model, id, channel),
+        None => format!("{}:{}", model, id),
+    };
+
+    let reading = PartialReading {
+        timestamp: None,
+        temperature: number(&message, "temperature_C")
+            .or_else(|| number(&message, "temperature_F").map(units::fahrenheit_to_celsius)),
+        humidity: number(&message, "humidity"),
+        pressure: number(&message, "pressure_hPa")
+            .or_else(|| number(&message, "pressure_inHg").map(units::inhg_to_hpa)),
+        wind_speed: number(&message, "wind_avg_m_s")
+            .or_else(|| number(&message, "wind_avg_km_h").map(|kmh| kmh / 3.6))
+            .or_else(|| number(&message, "wind_avg_mi_h").map(units::mph_to_ms)),
+        wind_direction: number(&message, "wind_dir_deg"),
+        rain_rate: number(&message, "rain_rate_mm_h")
+            .or_else(|| number(&message, "rain_rate_in_h").map(units::inches_to_mm)),
+        // Fine Offset's `uv` is the raw sensor count, not an index.
+        uv_index: number(&message, "uvi"),
+        // Daylight approximation used by Fine Offset consoles.
+        solar_radiation: number(&message, "light_lux").map(|lux| lux / 126.7),
+    };
+
+    let rain_total_mm = number(&message, "rain_mm")
+        .or_else(|| number(&message, "rain_in").map(units::inches_to_mm));
+
+    if reading.is_empty() && rain_total_mm.is_none() {
+        return None;
+    }
+
+    Some(SensorMessage {
+        sensor_key,
+        timestamp: parse_time(&message).unwrap_or_else(Utc::now),
+        reading,
+        rain_total_mm,
+    })
+}
+
+/// Merges partial readings from several sensors into one reading per time window.
+/// When several sensors report the same field, the one listed first in `sensors` wins,
+/// then the one heard first in the window.
+pub struct WindowMerger {
+    window: chrono::Duration,
+    sensors: Vec<String>,
+    current_window: Option<DateTime<Utc>>,
+    pending: Vec<(String, PartialReading)>,
+    rain_totals: HashMap<String, (DateTime<Utc>, f32)>,
+    last: Option<WeatherData>,
+}
+
+impl WindowMerger {
+    pub fn new(window: Duration, sensors: Vec<String>) -> Self {
+        Self {
+            window: chrono::Duration::from_std(window).unwrap_or(chrono::Duration::seconds(60)),
+            sensors,
+            current_window: None,
+            pending: Vec::new(),
+            rain_totals: HashMap::new(),
+            last: None,
+        }
+    }
+
+    // Position of the first configured entry matching `sensor_key`.
+    fn priority(&self, sensor_key: &str) -> Option<usize> {
+        self.sensors.iter().position(|allowed| {
+            sensor_key == allowed
+                || sensor_key.starts_with(&format!("{}:", allowed))
+                || sensor_key.split(':').nth(1) == Some(allowed.as_str())
+        })
+    }
+
+    fn accepts(&self, message: &SensorMessage) -> bool {
+        self.sensors.is_empty() || self.priority(&message.sensor_key).is_some()
+    }
+
+    fn window_start(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let window_ms = self.window.num_milliseconds().max(1);
+        let ms = timestamp.timestamp_millis();
+        Utc.timestamp_millis_opt(ms - ms.rem_euclid(window_ms)).single().unwrap_or(timestamp)
+    }
+
+    /// Returns the merged reading of the previous window once a message from a later window arrives.
+    pub fn push(&mut self, mut message: SensorMessage) -> Option<WeatherData> {
+        if !self.accepts(&message) {
+            tracing::debug!("Ignoring rtl_433 sensor {}", message.sensor_key);
+            return None;
+        }
+
+        if let Some(total) = message.rain_total_mm {
+            let previous = self.rain_totals.insert(message.sensor_key.clone(), (message.timestamp, total));
+            if message.reading.rain_rate.is_none() {
+                if let Some((previous_time, previous_total)) = previous {
+                    let hours = (message.timestamp - previous_time).num_milliseconds() as f32 / 3_600_000.0;
+                    // A counter that went backwards was reset (battery change); skip one sample.
+                    if hours > 0.0 && total >= previous_total {
+                        message.reading.rain_rate = Some((total - previous_total) / hours);
+                    }
+                }
+            }
+        }
+
+        let window = self.window_start(message.timestamp);
+        let flushed = match self.current_window {
+            Some(current) if window > current => self.flush(),
+            _ => None,
+        };
+        if self.current_window.is_none_or(|current| window > current) {
+            self.current_window = Some(window);
+        }
+
+        message.reading.timestamp = Some(message.timestamp);
+        match self.pending.iter_mut().find(|(key, _)| *key == message.sensor_key) {
+            Some((_, reading)) => reading.merge(message.reading),
+            None => self.pending.push((message.sensor_key, message.reading)),
+        }
+        flushed
+    }
+
+    /// Emits whatever has been merged so far, e.g. at end of input or when the window has gone quiet.
+    pub fn flush(&mut self) -> Option<WeatherData> {
+        let mut pending = std::mem::take(&mut self.pending);
+        // Stable, so sensors with the same priority keep their arrival order.
+        pending.sort_by_key(|(key, _)| self.priority(key).unwrap_or(usize::MAX));
+        let timestamp = pending.iter().filter_map(|(_, reading)| reading.timestamp).max();
+        // Merging in reverse lets the highest-priority sensor's fields win.
+        let mut merged = PartialReading::default();
+        for (_, reading) in pending.into_iter().rev() {
+            merged.merge(reading);
+        }
+        merged.timestamp = timestamp;
+        if merged.is_empty() {
+            return None;
+        }
+        let data = merged.complete(self.last.as_ref());
+        self.last = Some(data.clone());
+        Some(data)
+    }
+
+    /// Allows one extra window for late sensors before flushing a quiet window.
+    pub fn window_elapsed(&self, now: DateTime<Utc>) -> bool {
+        self.current_window.is_some_and(|start| now >= start + self.window + self.window)
+    }
+}
+
+async fn open_input(input: &str) -> Result<Box<dyn AsyncRead + Unpin + Send>> {
+    if input == "-" {
+        Ok(Box::new(tokio::io::stdin()))
+    } else if let Some(address) = input.strip_prefix("tcp://") {
+        Ok(Box::new(tokio::net::TcpStream::connect(address).await?))
+    } else {
+        Ok(Box::new(tokio::fs::File::open(input).await?))
+    }
+}
+
+// Ok(true) when the input ended, Ok(false) when the collector has gone or the source was
+// stopped; either way the window being merged is sent first.
+async fn read_input(reader: Box<dyn AsyncRead + Unpin + Send>, config: &Rtl433Config, tx: &SourceSender, health: &HealthCell, stop: &mut watch::Receiver<bool>) -> Result<bool> {
+    let mut lines = BufReader::new(reader).lines();
+    let mut merger = WindowMerger::new(Duration::from_secs(config.window_secs.max(1)), config.sensors.clone());
+    let idle_check = Duration::from_secs(config.window_secs.clamp(1, 10));
+    health.set(SourceHealth::Healthy);
+
+    loop {
+        let next = tokio::select! {
+            _ = stop.changed() => {
+                if let Some(data) = merger.flush() {
+                    tx.send(data);
+                }
+                return Ok(false);
+            }
+            next = tokio::time::timeout(idle_check, lines.next_line()) => next,
+        };
+        let line = match next {
+            Ok(line) => line?,
+            Err(_) => {
+                if merger.window_elapsed(Utc::now()) {
+                    if let Some(data) = merger.flush() {
+                        if !tx.send(data) {
+                            return Ok(false);
+                        }
+                    }
+                }
+                continue;
+            }
+        };
+
+        let Some(line) = line else {
+            if let Some(data) = merger.flush() {
+                tx.send(data);
+            }
+            return Ok(true);
+        };
+
+        if let Some(message) = parse_line(&line) {
+            if let Some(data) = merger.push(message) {
+                if !tx.send(data) {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+}
+
+pub struct Rtl433Source {
+    config: Rtl433Config,
+    health: HealthCell,
+    task: Option<JoinHandle<()>>,
+    stop: Option<watch::Sender<bool>>,
+}
+
+impl Rtl433Source {
+    pub fn new(config: Rtl433Config) -> Self {
+        Self {
+            config,
+            health: HealthCell::new(),
+            task: None,
+            stop: None,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherSource for Rtl433Source {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn start(&mut self, tx: SourceSender) -> Result<()> {
+        let config = self.config.clone();
+        let health = self.health.clone();
+        health.set(SourceHealth::Starting);
+        let (stop_tx, mut stop) = watch::channel(false);
+
+        self.task = Some(tokio::spawn(async move {
+            let is_socket = config.input.starts_with("tcp://");
+            let mut backoff = Duration::from_secs(1);
+
+            loop {
+                let opened = tokio::select! {
+                    _ = stop.changed() => break,
+                    opened = open_input(&config.input) => opened,
+                };
+                let result = match opened {
+                    Ok(reader) => {
+                        // Only repeated connection failures should keep growing the delay.
+                        backoff = Duration::from_secs(1);
+                        read_input(reader, &config, &tx, &health, &mut stop).await
+                    }
+                    Err(e) => Err(e),
+                };
+                match result {
+                    // Stdin and files are read once; a socket is reconnected when it closes.
+                    Ok(true) if is_socket => {
+                        health.set(SourceHealth::Degraded("connection closed".to_string()));
+                    }
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::warn!("rtl_433 source '{}' input {} failed: {}", config.name, config.input, e);
+                        if !is_socket {
+                            health.set(SourceHealth::Failed(e.to_string()));
+                            return;
+                        }
+                        health.set(SourceHealth::Degraded(e.to_string()));
+                    }
+                }
+                tokio::select! {
+                    _ = stop.changed() => break,
+                    _ = tokio::time::sleep(backoff) => {}
+                }
+                backoff = (backoff * 2).min(Duration::from_secs(60));
+            }
+
+            health.set(SourceHealth::Stopped);
+        }));
+        self.stop = Some(stop_tx);
+
+        Ok(())
+    }
+
+    // Signals the task so it can send the window it is merging rather than lose it.
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(true);
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        self.health.set(SourceHealth::Stopped);
+        Ok(())
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.get()
+    }
+
+    fn error_count(&self) -> u64 {
+        self.health.error_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../fixtures/rtl433/weather.jsonl");
+
+    fn merge_fixture(sensors: &[&str]) -> Vec<WeatherData> {
+        let mut merger = WindowMerger::new(Duration::from_secs(60), sensors.iter().map(|s| s.to_string()).collect());
+        let mut readings: Vec<WeatherData> = FIXTURE.lines().filter_map(parse_line).filter_map(|message| merger.push(message)).collect();
+        readings.extend(merger.flush());
+        readings
+    }
+
+    #[test]
+    fn parses_known_models_and_skips_others() {
+        let messages: Vec<SensorMessage> = FIXTURE.lines().filter_map(parse_line).collect();
+        assert_eq!(messages.len(), 8);
+        assert!(messages.iter().all(|message| !message.sensor_key.starts_with("Toyota")));
+
+        let acurite = &messages[0];
+        assert_eq!(acurite.sensor_key, "Acurite-5n1:1234:A");
+        assert!((acurite.reading.temperature.unwrap() - 20.22).abs() < 0.01);
+        assert!((acurite.reading.wind_speed.unwrap() - 11.2 / 3.6).abs() < 0.001);
+        assert_eq!(acurite.reading.pressure, None);
+        assert!((messages[1].rain_total_mm.unwrap() - 313.44).abs() < 0.01);
+    }
+
+    #[test]
+    fn raw_uv_counts_are_not_an_index() {
+        let line = r#"{"time" : "2024-06-01T14:00:48Z", "model" : "Fineoffset-WH65B", "id" : 87, "temperature_C" : 20.1, "uv" : 1912}"#;
+        let message = parse_line(line).unwrap();
+        assert_eq!(message.reading.uv_index, None);
+        assert_eq!(message.reading.temperature, Some(20.1));
+    }
+
+    #[test]
+    fn configured_order_decides_overlapping_fields() {
+        let readings = merge_fixture(&["1234", "87"]);
+        assert_eq!(readings.len(), 2);
+        let first = &readings[0];
+        // Temperature and humidity from the Acurite, which is listed first.
+        assert!((first.temperature - 20.22).abs() < 0.01);
+        assert_eq!(first.humidity, 61.0);
+        // Its last wind message in the window, not the Fine Offset's.
+        assert!((first.wind_speed - 10.4 / 3.6).abs() < 0.001);
+        assert_eq!(first.wind_direction, 247.5);
+        // Only the Fine Offset measures UV and light.
+        assert_eq!(first.uv_index, 4.0);
+        assert!((first.solar_radiation - 61240.0 / 126.7).abs() < 0.01);
+        assert_eq!(first.timestamp, "2024-06-01T14:00:48Z".parse::<DateTime<Utc>>().unwrap());
+
+        let readings = merge_fixture(&["87", "1234"]);
+        assert_eq!(readings[0].temperature, 20.1);
+        assert_eq!(readings[0].humidity, 60.0);
+        assert_eq!(readings[0].wind_speed, 2.9);
+    }
+
+    #[test]
+    fn unconfigured_sensors_merge_in_arrival_order() {
+        let readings = merge_fixture(&[]);
+        assert_eq!(readings.len(), 3);
+        assert!((readings[0].temperature - 20.22).abs() < 0.01);
+        // The LaCrosse alone makes up the last window.
+        assert_eq!(readings[2].temperature, 19.9);
+        assert_eq!(readings[2].humidity, 62.0);
+    }
+
+    #[test]
+    fn unmeasured_fields_stay_missing() {
+        let readings = merge_fixture(&["1234"]);
+        assert!(readings.iter().all(|data| data.pressure.is_nan() && data.uv_index.is_nan() && data.solar_radiation.is_nan()));
+        // No previous rain total in the first window, so no rate either.
+        assert!(readings[0].rain_rate.is_nan());
+    }
+
+    #[tokio::test]
+    async fn stop_sends_the_window_being_merged() {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = Rtl433Config {
+            input: format!("tcp://{}", listener.local_addr().unwrap()),
+            sensors: vec!["1234".to_string()],
+            ..Default::default()
+        };
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut source = Rtl433Source::new(config);
+        source.start(SourceSender::new("rtl433", tx, std::sync::Arc::new(crate::metrics::IngestMetrics::new()))).await.unwrap();
+
+        // Both Acurite messages of the first window; nothing later arrives to close it.
+        let (mut socket, _) = listener.accept().await.unwrap();
+        for line in FIXTURE.lines().take(2) {
+            socket.write_all(format!("{}\n", line).as_bytes()).await.unwrap();
+        }
+        socket.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(rx.try_recv().is_err());
+
+        source.stop().await.unwrap();
+        let flushed = rx.try_recv().expect("pending window sent on stop");
+        assert!((flushed.temperature - 20.22).abs() < 0.01);
+        assert_eq!(flushed.wind_direction, 247.5);
+        assert_eq!(flushed.timestamp, "2024-06-01T14:00:21Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(source.health(), SourceHealth::Stopped);
+    }
+
+    #[test]
+    fn rain_rate_from_counter_increase() {
+        let readings = merge_fixture(&["1234"]);
+        // 0.01 in within one minute.
+        assert!((readings[1].rain_rate - 15.24).abs() < 0.2, "{}", readings[1].rain_rate);
+
+        let mut merger = WindowMerger::new(Duration::from_secs(60), Vec::new());
+        let mut reset = parse_line(FIXTURE.lines().nth(6).unwrap()).unwrap();
+        merger.push(parse_line(FIXTURE.lines().nth(1).unwrap()).unwrap());
+        reset.rain_total_mm = Some(0.0);
+        merger.push(reset);
+        // A counter that went backwards yields no rate rather than a negative one.
+        assert!(merger.flush().unwrap().rain_rate.is_nan());
+    }
+}
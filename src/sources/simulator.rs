@@ -0,0 +1,589 @@
+use crate::data::WeatherData;
+use crate::derived;
+use crate::sources::{HealthCell, SourceHealth, SourceSender, WeatherSource};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const HOUR: f64 = 3600.0;
+const DAY: f64 = 24.0 * HOUR;
+// The model runs this long before its first reading so temperature, clouds and
+// pressure systems start from a settled state rather than the configured means.
+const SPIN_UP: f64 = 2.0 * DAY;
+// Longer gaps between readings are integrated in steps of at most this many seconds.
+const MAX_STEP: f64 = 600.0;
+// Pressure systems are scheduled this far ahead, so the tendency is known before they arrive.
+const SYSTEM_LOOKAHEAD: f64 = 5.0 * DAY;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulatorConfig {
+    pub name: String,
+    pub interval_secs: u64,
+    /// Site position in degrees, north and east positive; sets the sun's path and the seasons.
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Annual means of temperature, relative humidity and sea-level pressure at the site.
+    pub base_temperature: f32,
+    pub base_humidity: f32,
+    pub base_pressure: f32,
+    /// Difference between the coldest and warmest hour of a clear day, in °C.
+    pub diurnal_range: f32,
+    /// Where the simulated clock starts; now when unset.
+    pub start: Option<DateTime<Utc>>,
+    /// Simulated seconds per real second.
+    pub speed: f64,
+    /// Makes a run reproducible; a random seed is used when unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            name: "simulator".to_string(),
+            interval_secs: 5,
+            latitude: 48.85,
+            longitude: 2.35,
+            base_temperature: 12.5,
+            base_humidity: 75.0,
+            base_pressure: 1015.0,
+            diurnal_range: 10.0,
+            start: None,
+            speed: 1.0,
+            seed: None,
+        }
+    }
+}
+
+struct Sun {
+    // Sine of the elevation above the horizon, negative at night.
+    elevation: f64,
+    // Sine of the elevation at solar noon today.
+    noon: f64,
+    solar_hours: f64,
+    distance_factor: f64,
+}
+
+impl Sun {
+    // Declination and equation of time from the day of year, which is within a degree
+    // and a minute or so of an ephemeris.
+    fn at(time: DateTime<Utc>, latitude: f64, longitude: f64) -> Self {
+        let day = time.ordinal() as f64;
+        let hours = time.num_seconds_from_midnight() as f64 / HOUR;
+        let b = 2.0 * PI * (day - 81.0) / 364.0;
+        let equation_of_time = 9.87 * (2.0 * b).sin() - 7.53 * b.cos() - 1.5 * b.sin();
+        let solar_hours = (hours + longitude / 15.0 + equation_of_time / 60.0).rem_euclid(24.0);
+
+        let declination = 23.44f64.to_radians() * (2.0 * PI * (284.0 + day) / 365.0).sin();
+        let hour_angle = (15.0 * (solar_hours - 12.0)).to_radians();
+        let latitude = latitude.to_radians();
+        Self {
+            elevation: latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos(),
+            noon: (latitude - declination).cos(),
+            solar_hours,
+            distance_factor: 1.0 + 0.033 * (2.0 * PI * day / 365.0).cos(),
+        }
+    }
+
+    // 0 at night, 1 at solar noon.
+    fn daylight(&self) -> f64 {
+        if self.noon <= 0.0 { 0.0 } else { (self.elevation / self.noon).clamp(0.0, 1.0) }
+    }
+
+    // Global horizontal irradiance under a clear sky in W/m² (Haurwitz).
+    fn clear_sky_radiation(&self) -> f64 {
+        if self.elevation <= 0.0 {
+            return 0.0;
+        }
+        1098.0 * self.distance_factor * self.elevation * (-0.057 / self.elevation).exp()
+    }
+
+    // Clear-sky UV index for a typical ozone column.
+    fn clear_sky_uv(&self) -> f64 {
+        12.5 * self.elevation.max(0.0).powf(2.42)
+    }
+}
+
+// A high or low passing over the site; its effect on pressure peaks at `center`.
+struct PressureSystem {
+    center: f64,
+    width: f64,
+    depth: f64,
+}
+
+struct RainEvent {
+    start: f64,
+    end: f64,
+    peak: f64,
+}
+
+/// Weather at one site advanced through simulated time: temperature follows the sun and
+/// the season, humidity follows temperature through the dew point, and highs, lows and
+/// showers pass over days.
+pub struct WeatherModel {
+    config: SimulatorConfig,
+    rng: StdRng,
+    time: DateTime<Utc>,
+    temperature: f64,
+    dew_point: f64,
+    // Departure from the seasonal mean temperature as warm and cold air moves in.
+    air_mass: f64,
+    cloud: f64,
+    cloud_noise: f64,
+    wind_speed: f64,
+    wind_direction: f64,
+    systems: VecDeque<PressureSystem>,
+    rain: Option<RainEvent>,
+    rain_rate: f64,
+}
+
+impl WeatherModel {
+    pub fn new(config: &SimulatorConfig, start: DateTime<Utc>) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let time = start - chrono::Duration::seconds(SPIN_UP as i64);
+        let mut model = Self {
+            config: config.clone(),
+            rng,
+            time,
+            temperature: 0.0,
+            dew_point: 0.0,
+            air_mass: 0.0,
+            cloud: 0.4,
+            cloud_noise: 0.0,
+            wind_speed: 3.0,
+            wind_direction: 240.0,
+            systems: VecDeque::new(),
+            rain: None,
+            rain_rate: 0.0,
+        };
+        model.temperature = model.seasonal_mean(time);
+        model.dew_point = derived::dew_point(model.temperature as f32, config.base_humidity) as f64;
+        let first = seconds(time) - model.rng.gen_range(0.0..3.0) * DAY;
+        model.systems.push_back(PressureSystem { center: first, width: 24.0 * HOUR, depth: 0.0 });
+        model.advance_to(start);
+        model
+    }
+
+    /// Advances the model to `time` and returns what a station would read then.
+    pub fn reading(&mut self, time: DateTime<Utc>) -> WeatherData {
+        self.advance_to(time);
+        let config = &self.config;
+        let sun = Sun::at(self.time, config.latitude, config.longitude);
+
+        // Highs and lows plus the twice-daily atmospheric tide, largest in the tropics.
+        let tide = 1.2 * config.latitude.to_radians().cos() * (4.0 * PI * (sun.solar_hours - 10.0) / 24.0).cos();
+        let pressure = config.base_pressure as f64 + self.pressure_anomaly(seconds(self.time)) + tide;
+
+        let humidity = 100.0 * derived::saturation_vapor_pressure(self.dew_point as f32) / derived::saturation_vapor_pressure(self.temperature as f32);
+
+        // Broken cloud makes radiation flicker, so it is drawn per reading.
+        let cover = (self.cloud + 0.15 * self.cloud * gaussian(&mut self.rng)).clamp(0.0, 1.0);
+        let solar_radiation = sun.clear_sky_radiation() * (1.0 - 0.75 * cover.powf(3.4));
+        let uv_index = sun.clear_sky_uv() * (1.0 - 0.5 * cover.powi(2));
+
+        let gust = (1.0 + 0.25 * gaussian(&mut self.rng)).max(0.0);
+        let direction = (self.wind_direction + 12.0 * gaussian(&mut self.rng)).rem_euclid(360.0);
+
+        WeatherData::new(
+            (self.temperature + 0.05 * gaussian(&mut self.rng)) as f32,
+            humidity.clamp(1.0, 100.0),
+            (pressure + 0.05 * gaussian(&mut self.rng)) as f32,
+            (self.wind_speed * gust) as f32,
+            direction as f32,
+            self.rain_rate as f32,
+            ((uv_index * 10.0).round() / 10.0) as f32,
+            solar_radiation as f32,
+        )
+        .with_timestamp(self.time)
+    }
+
+    fn advance_to(&mut self, time: DateTime<Utc>) {
+        let mut remaining = (time - self.time).num_milliseconds() as f64 / 1000.0;
+        while remaining > 0.0 {
+            let dt = remaining.min(MAX_STEP);
+            self.time += chrono::Duration::milliseconds((dt * 1000.0) as i64);
+            self.step(dt);
+            remaining -= dt;
+        }
+    }
+
+    fn step(&mut self, dt: f64) {
+        let now = seconds(self.time);
+        let sun = Sun::at(self.time, self.config.latitude, self.config.longitude);
+        self.schedule_systems(now);
+
+        // Lows and falling pressure bring cloud, highs clear it.
+        let anomaly = self.pressure_anomaly(now);
+        let tendency = anomaly - self.pressure_anomaly(now - 3.0 * HOUR);
+        self.cloud_noise = wander(self.cloud_noise, 4.0 * HOUR, 0.2, dt, &mut self.rng);
+        let mut cloud_target = 0.45 - anomaly / 20.0 - tendency / 5.0 + self.cloud_noise;
+        if self.rain_rate > 0.0 {
+            cloud_target = cloud_target.max(0.95);
+        }
+        self.cloud += (cloud_target.clamp(0.0, 1.0) - self.cloud) * relax(dt, 1.5 * HOUR);
+
+        self.update_rain(now, dt, &sun);
+
+        // The air heats towards a daytime peak set by the sun and cools towards a night
+        // minimum; the lag puts the warmest hour in mid-afternoon and the coldest at dawn.
+        self.air_mass = wander(self.air_mass, 3.0 * DAY, 2.5, dt, &mut self.rng);
+        let mean = self.seasonal_mean(self.time) + self.air_mass;
+        let range = self.config.diurnal_range as f64 * (1.0 - 0.6 * self.cloud);
+        let cooling = 3.0 * (self.rain_rate / 5.0).min(1.0);
+        let target = mean + range * (1.25 * sun.daylight() - 0.45) - cooling;
+        self.temperature += (target - self.temperature) * relax(dt, 3.0 * HOUR);
+
+        // The air's moisture changes slowly, so relative humidity falls as it warms
+        // during the day. Cloud and rain moisten it.
+        let humidity = (self.config.base_humidity as f64 + 25.0 * (self.cloud - 0.45)).clamp(20.0, 98.0);
+        let dew_target = if self.rain_rate > 0.0 {
+            self.temperature - 0.3
+        } else {
+            derived::dew_point(mean as f32, humidity as f32) as f64
+        };
+        let tau = if self.rain_rate > 0.0 { 0.5 * HOUR } else { 6.0 * HOUR };
+        self.dew_point += (dew_target - self.dew_point) * relax(dt, tau);
+        self.dew_point = self.dew_point.min(self.temperature);
+
+        // Wind strengthens with deep lows, fast pressure changes and daytime mixing, and
+        // veers from south ahead of a low to north-west behind it.
+        let wind_target = 1.5 + 0.25 * (-anomaly).max(0.0) + 1.5 * tendency.abs() + 2.0 * sun.daylight() + 3.0 * (self.rain_rate / 10.0).min(1.0);
+        self.wind_speed += (wind_target - self.wind_speed) * relax(dt, HOUR);
+        let mut direction = 250.0 + 60.0 * (tendency / 3.0).clamp(-1.0, 1.0);
+        if self.config.latitude < 0.0 {
+            direction = 180.0 - direction;
+        }
+        let turn = (direction - self.wind_direction + 540.0).rem_euclid(360.0) - 180.0;
+        self.wind_direction = (self.wind_direction + turn * relax(dt, 2.0 * HOUR) + 3.0 * (dt / HOUR).sqrt() * gaussian(&mut self.rng)).rem_euclid(360.0);
+    }
+
+    // Mean temperature for the time of year, peaking about a month after midsummer.
+    // The seasonal swing grows with latitude.
+    fn seasonal_mean(&self, time: DateTime<Utc>) -> f64 {
+        let latitude = self.config.latitude;
+        let amplitude = (latitude.abs() * 0.16).clamp(0.5, 15.0);
+        let peak = if latitude >= 0.0 { 200.0 } else { 17.0 };
+        self.config.base_temperature as f64 + amplitude * (2.0 * PI * (time.ordinal() as f64 - peak) / 365.25).cos()
+    }
+
+    fn pressure_anomaly(&self, at: f64) -> f64 {
+        self.systems.iter()
+            .map(|system| system.depth * (-((at - system.center) / system.width).powi(2)).exp())
+            .sum()
+    }
+
+    // Highs and lows alternate every one and a half to four days; lows are deeper and pass
+    // faster than highs.
+    fn schedule_systems(&mut self, now: f64) {
+        while self.systems.front().is_some_and(|system| system.center < now - SYSTEM_LOOKAHEAD) {
+            self.systems.pop_front();
+        }
+        while let Some(last) = self.systems.back().filter(|last| last.center < now + SYSTEM_LOOKAHEAD) {
+            let center = last.center + self.rng.gen_range(36.0..96.0) * HOUR;
+            let low = self.rng.gen_bool(if last.depth < 0.0 { 0.35 } else { 0.75 });
+            let system = if low {
+                PressureSystem { center, width: self.rng.gen_range(10.0..30.0) * HOUR, depth: -self.rng.gen_range(6.0..26.0) }
+            } else {
+                PressureSystem { center, width: self.rng.gen_range(24.0..60.0) * HOUR, depth: self.rng.gen_range(4.0..16.0) }
+            };
+            self.systems.push_back(system);
+        }
+    }
+
+    // Long, steady rain comes with thick cloud; short heavy showers come on warm
+    // afternoons.
+    fn update_rain(&mut self, now: f64, dt: f64, sun: &Sun) {
+        if let Some(event) = &self.rain {
+            if now >= event.end {
+                self.rain = None;
+            }
+        }
+        if self.rain.is_none() {
+            let frontal = 0.12 * ((self.cloud - 0.7) / 0.3).max(0.0);
+            let convective = 0.03 * sun.daylight() * ((self.temperature - 18.0) / 10.0).clamp(0.0, 1.0) * self.cloud.min(0.6);
+            let chance = 1.0 - (-(frontal + convective) * dt / HOUR).exp();
+            if self.rng.gen_bool(chance.clamp(0.0, 1.0)) {
+                let (duration, peak) = if self.rng.gen_bool(frontal / (frontal + convective)) {
+                    (self.rng.gen_range(1.0..6.0), self.rng.gen_range(0.5..6.0))
+                } else {
+                    (self.rng.gen_range(0.3..1.5), self.rng.gen_range(4.0..25.0))
+                };
+                self.rain = Some(RainEvent { start: now, end: now + duration * HOUR, peak });
+            }
+        }
+
+        self.rain_rate = match &self.rain {
+            Some(event) => {
+                let progress = (now - event.start) / (event.end - event.start);
+                let rate = event.peak * (PI * progress).sin().powi(2) * (1.0 + 0.3 * gaussian(&mut self.rng));
+                (rate.max(0.0) * 10.0).round() / 10.0
+            }
+            None => 0.0,
+        };
+    }
+}
+
+fn seconds(time: DateTime<Utc>) -> f64 {
+    time.timestamp_millis() as f64 / 1000.0
+}
+
+// Fraction of the way a quantity relaxing with time constant `tau` moves in `dt`.
+fn relax(dt: f64, tau: f64) -> f64 {
+    1.0 - (-dt / tau).exp()
+}
+
+// One step of a mean-reverting random walk around zero with standard deviation `sigma`.
+fn wander(value: f64, tau: f64, sigma: f64, dt: f64, rng: &mut StdRng) -> f64 {
+    let decay = (-dt / tau).exp();
+    value * decay + sigma * (1.0 - decay * decay).sqrt() * gaussian(rng)
+}
+
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let v: f64 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+}
+
+pub struct SimulatorSource {
+    config: SimulatorConfig,
+    health: HealthCell,
+    task: Option<JoinHandle<()>>,
+}
+
+impl SimulatorSource {
+    pub fn new(config: SimulatorConfig) -> Self {
+        Self {
+            config,
+            health: HealthCell::new(),
+            task: None,
+        }
+    }
+}
+
+impl Default for SimulatorSource {
+    fn default() -> Self {
+        Self::new(SimulatorConfig::default())
+    }
+}
+
+#[async_trait]
+impl WeatherSource for SimulatorSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn start(&mut self, tx: SourceSender) -> Result<()> {
+        let interval = Duration::from_secs(self.config.interval_secs.max(1));
+        let config = self.config.clone();
+        let health = self.health.clone();
+        health.set(SourceHealth::Starting);
+
+        self.task = Some(tokio::spawn(async move {
+            let start = config.start.unwrap_or_else(Utc::now);
+            let started = tokio::time::Instant::now();
+            // Spinning up runs two simulated days of steps.
+            let mut model = match tokio::task::spawn_blocking({
+                let config = config.clone();
+                move || WeatherModel::new(&config, start)
+            }).await {
+                Ok(model) => model,
+                Err(e) => {
+                    health.set(SourceHealth::Failed(e.to_string()));
+                    return;
+                }
+            };
+
+            loop {
+                let elapsed = started.elapsed().as_secs_f64() * config.speed;
+                let weather = model.reading(start + chrono::Duration::milliseconds((elapsed * 1000.0) as i64));
+
+                if !tx.send(weather) {
+                    health.set(SourceHealth::Stopped);
+                    break;
+                }
+                health.set(SourceHealth::Healthy);
+
+                tokio::time::sleep(interval).await;
+            }
+        }));
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.health.set(SourceHealth::Stopped);
+        Ok(())
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.get()
+    }
+
+    fn error_count(&self) -> u64 {
+        self.health.error_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::IngestMetrics;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    fn config(seed: u64) -> SimulatorConfig {
+        SimulatorConfig { seed: Some(seed), ..SimulatorConfig::default() }
+    }
+
+    fn time(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    // Readings every `step_minutes` over `days` from `start`.
+    fn run(config: &SimulatorConfig, start: DateTime<Utc>, days: i64, step_minutes: i64) -> Vec<WeatherData> {
+        let mut model = WeatherModel::new(config, start);
+        (0..days * 24 * 60 / step_minutes)
+            .map(|i| model.reading(start + chrono::Duration::minutes(i * step_minutes)))
+            .collect()
+    }
+
+    fn correlation(pairs: &[(f64, f64)]) -> f64 {
+        let n = pairs.len() as f64;
+        let (mean_x, mean_y) = pairs.iter().fold((0.0, 0.0), |(x, y), (a, b)| (x + a / n, y + b / n));
+        let (mut xy, mut xx, mut yy) = (0.0, 0.0, 0.0);
+        for (x, y) in pairs {
+            xy += (x - mean_x) * (y - mean_y);
+            xx += (x - mean_x).powi(2);
+            yy += (y - mean_y).powi(2);
+        }
+        xy / (xx * yy).sqrt()
+    }
+
+    #[test]
+    fn a_seed_reproduces_the_run() {
+        let start = time("2024-03-10T00:00:00Z");
+        let first = run(&config(7), start, 3, 15);
+        let second = run(&config(7), start, 3, 15);
+        let fields = |data: &WeatherData| (data.timestamp, data.temperature, data.humidity, data.pressure, data.wind_speed, data.wind_direction, data.rain_rate, data.uv_index, data.solar_radiation);
+        assert_eq!(first.iter().map(fields).collect::<Vec<_>>(), second.iter().map(fields).collect::<Vec<_>>());
+
+        let other = run(&config(8), start, 3, 15);
+        assert_ne!(first.iter().map(fields).collect::<Vec<_>>(), other.iter().map(fields).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn the_sun_follows_latitude_and_season() {
+        // Paris at the June solstice: the sun culminates about 64.6° up shortly before 12:00 UTC.
+        let noon = Sun::at(time("2024-06-21T11:50:00Z"), 48.85, 2.35);
+        assert!((noon.elevation.asin().to_degrees() - 64.6).abs() < 1.0, "{}", noon.elevation.asin().to_degrees());
+        assert!(noon.daylight() > 0.99);
+        assert!(noon.clear_sky_radiation() > 850.0 && noon.clear_sky_radiation() < 1000.0, "{}", noon.clear_sky_radiation());
+        assert!(noon.clear_sky_uv() > 7.0 && noon.clear_sky_uv() < 10.0, "{}", noon.clear_sky_uv());
+
+        let midnight = Sun::at(time("2024-06-21T23:50:00Z"), 48.85, 2.35);
+        assert!(midnight.elevation < 0.0);
+        assert_eq!((midnight.daylight(), midnight.clear_sky_radiation(), midnight.clear_sky_uv()), (0.0, 0.0, 0.0));
+
+        // The same clock time in December is much lower.
+        let winter = Sun::at(time("2024-12-21T11:50:00Z"), 48.85, 2.35);
+        assert!((winter.elevation.asin().to_degrees() - 17.7).abs() < 1.0, "{}", winter.elevation.asin().to_degrees());
+    }
+
+    #[test]
+    fn no_sunshine_or_uv_at_night() {
+        let config = config(1);
+        let readings = run(&config, time("2024-06-01T00:00:00Z"), 4, 10);
+        let mut nights = 0;
+        for data in &readings {
+            let sun = Sun::at(data.timestamp, config.latitude, config.longitude);
+            if sun.elevation <= 0.0 {
+                nights += 1;
+                assert_eq!((data.uv_index, data.solar_radiation), (0.0, 0.0), "at {}", data.timestamp);
+            }
+        }
+        assert!(nights > 100);
+        assert!(readings.iter().any(|data| data.solar_radiation > 300.0));
+    }
+
+    #[test]
+    fn warmest_in_the_afternoon_and_coldest_around_dawn() {
+        // Paris keeps close to solar time in UTC, so hours of the day can be compared directly.
+        let readings = run(&config(3), time("2024-06-01T00:00:00Z"), 20, 10);
+        let mut sums = [(0.0, 0); 24];
+        for data in &readings {
+            let hour = &mut sums[data.timestamp.hour() as usize];
+            *hour = (hour.0 + data.temperature as f64, hour.1 + 1);
+        }
+        let means: Vec<f64> = sums.iter().map(|(sum, count)| sum / *count as f64).collect();
+        let warmest = (0..24).max_by(|a, b| means[*a].total_cmp(&means[*b])).unwrap();
+        let coldest = (0..24).min_by(|a, b| means[*a].total_cmp(&means[*b])).unwrap();
+        assert!((13..=17).contains(&warmest), "warmest at {}: {:?}", warmest, means);
+        assert!((3..=7).contains(&coldest), "coldest at {}: {:?}", coldest, means);
+        assert!(means[warmest] - means[coldest] > 4.0, "{:?}", means);
+    }
+
+    #[test]
+    fn humidity_falls_as_it_warms() {
+        let readings = run(&config(5), time("2024-05-01T00:00:00Z"), 10, 10);
+        let pairs: Vec<(f64, f64)> = readings.iter().map(|data| (data.temperature as f64, data.humidity as f64)).collect();
+        let r = correlation(&pairs);
+        assert!(r < -0.5, "correlation {}", r);
+    }
+
+    #[test]
+    fn readings_stay_physical_over_a_season() {
+        let readings = run(&config(11), time("2024-09-01T00:00:00Z"), 60, 30);
+        for data in &readings {
+            assert!((1.0..=100.0).contains(&data.humidity), "{:?}", data);
+            assert!((950.0..=1070.0).contains(&data.pressure), "{:?}", data);
+            assert!((-15.0..=40.0).contains(&data.temperature), "{:?}", data);
+            assert!((0.0..360.0).contains(&data.wind_direction), "{:?}", data);
+            assert!(data.wind_speed >= 0.0 && data.rain_rate >= 0.0 && data.uv_index >= 0.0, "{:?}", data);
+        }
+        // Pressure systems pass and it rains now and then.
+        let (low, high) = readings.iter().fold((f32::MAX, f32::MIN), |(low, high), data| (low.min(data.pressure), high.max(data.pressure)));
+        assert!(high - low > 10.0, "pressure only ranged {} to {}", low, high);
+        assert!(readings.iter().any(|data| data.rain_rate > 0.0));
+        assert!(readings.iter().filter(|data| data.rain_rate > 0.0).count() < readings.len() / 4);
+    }
+
+    #[test]
+    fn seasons_are_reversed_south_of_the_equator() {
+        let paris = WeatherModel::new(&config(2), time("2024-01-01T00:00:00Z"));
+        let sydney = WeatherModel::new(&SimulatorConfig { latitude: -33.87, longitude: 151.21, ..config(2) }, time("2024-01-01T00:00:00Z"));
+        let january = time("2024-01-15T00:00:00Z");
+        let july = time("2024-07-15T00:00:00Z");
+        assert!(paris.seasonal_mean(july) > paris.seasonal_mean(january) + 10.0);
+        assert!(sydney.seasonal_mean(january) > sydney.seasonal_mean(july) + 5.0);
+    }
+
+    #[tokio::test]
+    async fn source_sends_readings_from_the_configured_start() {
+        let start = time("2024-06-21T12:00:00Z");
+        let mut source = SimulatorSource::new(SimulatorConfig { start: Some(start), speed: 0.0, ..config(4) });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        source.start(SourceSender::new("simulator", tx, Arc::new(IngestMetrics::new()))).await.unwrap();
+
+        let data = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(data.timestamp, start);
+        assert_eq!(data.source, "simulator");
+        assert!(data.solar_radiation > 0.0);
+        assert_eq!(source.health(), SourceHealth::Healthy);
+
+        source.stop().await.unwrap();
+        assert_eq!(source.health(), SourceHealth::Stopped);
+    }
+}
@@ -0,0 +1,277 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex};
+use tokio::task::JoinHandle;
+
+use crate::alerts::notify::Notifier;
+use crate::api::{self, ApiState};
+use crate::config::{ApiConfig, Config};
+use crate::data::{TelemetrySystem, WeatherData};
+use crate::metrics::IngestMetrics;
+use crate::storage::{SqliteStore, WeatherStore};
+use crate::telemetry::TelemetryCollector;
+
+// Notifications still being delivered get this long to finish on shutdown.
+const NOTIFY_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+// Readings buffered for each live subscriber before a slow one starts skipping.
+const READINGS_CAPACITY: usize = 256;
+
+/// Everything that runs regardless of how the station is displayed: sources, storage,
+/// alerting and notifications. Shared by the dashboard and headless mode.
+pub struct Station {
+    telemetry_system: Arc<Mutex<TelemetrySystem>>,
+    collector: Arc<Mutex<TelemetryCollector>>,
+    readings: broadcast::Sender<WeatherData>,
+    ingest_metrics: Arc<IngestMetrics>,
+    pending: Option<(mpsc::UnboundedReceiver<WeatherData>, Notifier)>,
+    stop_tx: Option<oneshot::Sender<()>>,
+    ingest: Option<JoinHandle<()>>,
+    api: Option<(ApiConfig, Option<SqliteStore>)>,
+    server: Option<(watch::Sender<bool>, JoinHandle<()>)>,
+}
+
+impl Station {
+    /// Builds the station from a validated config.
+    pub fn new(config: &Config) -> Self {
+        let system = TelemetrySystem::with_retention(config.retention.history_size, config.retention.tiers.clone());
+        let (mut telemetry_system, stored) = match SqliteStore::open(&config.storage.database)
+            .and_then(|store| system.with_store(Box::new(store)))
+        {
+            Ok(system) => (system, true),
+            Err(e) => {
+                tracing::error!("Failed to open weather database {}, history will not be kept: {}", config.storage.database, e);
+                (TelemetrySystem::with_retention(config.retention.history_size, config.retention.tiers.clone()), false)
+            }
+        };
+        // The API reads ranges through its own connection so they never hold the telemetry
+        // lock; without one it serves them from the in-memory history.
+        let api_store = (config.api.enabled && stored)
+            .then(|| SqliteStore::open(&config.storage.database))
+            .and_then(|store| store.inspect_err(|e| tracing::error!("Failed to open a read connection to {}: {}", config.storage.database, e)).ok());
+        telemetry_system.set_storm_gap(chrono::Duration::hours(config.rain.storm_gap_hours));
+        telemetry_system.set_rain_timezone(config.rain.timezone());
+        telemetry_system.set_units(config.units);
+        telemetry_system.set_alert_rules(config.alerts.clone());
+
+        let (mut collector, weather_rx) = TelemetryCollector::new();
+        for source in &config.sources {
+            collector.add_source(source.build());
+        }
+        let ingest_metrics = collector.metrics();
+
+        Self {
+            telemetry_system: Arc::new(Mutex::new(telemetry_system)),
+            collector: Arc::new(Mutex::new(collector)),
+            readings: broadcast::channel(READINGS_CAPACITY).0,
+            ingest_metrics,
+            pending: Some((weather_rx, Notifier::new(&config.notifications))),
+            stop_tx: None,
+            ingest: None,
+            api: config.api.enabled.then(|| (config.api.clone(), api_store)),
+            server: None,
+        }
+    }
+
+    pub fn telemetry_system(&self) -> Arc<Mutex<TelemetrySystem>> {
+        self.telemetry_system.clone()
+    }
+
+    pub fn collector(&self) -> Arc<Mutex<TelemetryCollector>> {
+        self.collector.clone()
+    }
+
+    /// Every reading once it has been stored.
+    pub fn subscribe(&self) -> broadcast::Receiver<WeatherData> {
+        self.readings.subscribe()
+    }
+
+    /// Starts the sources and the task feeding their readings into the telemetry system.
+    /// Must be called from within a tokio runtime.
+    pub fn start(&mut self) {
+        let Some((mut weather_rx, mut notifier)) = self.pending.take() else {
+            return;
+        };
+        let (stop_tx, mut stop_rx) = oneshot::channel();
+        self.stop_tx = Some(stop_tx);
+
+        let telemetry_system = self.telemetry_system.clone();
+        let readings = self.readings.clone();
+        let ingest_metrics = self.ingest_metrics.clone();
+        // Readings are broadcast under the same lock they are stored under, so a subscriber
+        // taking a snapshot under the lock sees each reading exactly once.
+        let ingest = move |system: &mut TelemetrySystem, data: WeatherData| {
+            ingest_metrics.record_received(&data);
+            let events = system.add_weather_data(data.clone());
+            let _ = readings.send(data);
+            events
+        };
+        self.ingest = Some(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    data = weather_rx.recv() => match data {
+                        Some(data) => {
+                            let events = ingest(&mut *telemetry_system.lock().await, data);
+                            notifier.dispatch(&events);
+                        }
+                        None => break,
+                    },
+                    _ = &mut stop_rx => break,
+                }
+            }
+
+            // Readings already queued by the sources are still stored before exiting.
+            weather_rx.close();
+            let mut drained = 0;
+            while let Some(data) = weather_rx.recv().await {
+                let events = ingest(&mut *telemetry_system.lock().await, data);
+                notifier.dispatch(&events);
+                drained += 1;
+            }
+            if drained > 0 {
+                tracing::info!("Stored {} queued reading(s) during shutdown", drained);
+            }
+            notifier.finish(NOTIFY_DRAIN_TIMEOUT).await;
+            telemetry_system.lock().await.flush();
+        }));
+
+        if let Some((config, store)) = self.api.take() {
+            let (stop_tx, stop_rx) = watch::channel(false);
+            let state = ApiState {
+                telemetry_system: self.telemetry_system.clone(),
+                collector: self.collector.clone(),
+                ingest: self.ingest_metrics.clone(),
+                store: store.map(|store| Arc::new(std::sync::Mutex::new(store)) as Arc<std::sync::Mutex<dyn WeatherStore>>),
+                readings: self.readings.clone(),
+                shutdown: stop_rx,
+            };
+            let server = tokio::spawn(async move {
+                if let Err(e) = api::serve(&config, state).await {
+                    tracing::error!("REST API stopped: {:#}", e);
+                }
+            });
+            self.server = Some((stop_tx, server));
+        }
+
+        let collector = self.collector.clone();
+        tokio::spawn(async move {
+            if let Err(e) = collector.lock().await.start().await {
+                tracing::error!("Failed to start weather collection: {}", e);
+            }
+        });
+    }
+
+    /// Stops the sources, stores whatever they already sent, waits for outstanding
+    /// notifications and flushes the store.
+    pub async fn shutdown(&mut self) {
+        if let Some((stop_tx, server)) = self.server.take() {
+            let _ = stop_tx.send(true);
+            let _ = server.await;
+        }
+        self.collector.lock().await.stop().await;
+        if let Some(stop_tx) = self.stop_tx.take() {
+            let _ = stop_tx.send(());
+        }
+        if let Some(ingest) = self.ingest.take() {
+            if let Err(e) = ingest.await {
+                tracing::error!("Weather ingest task failed: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StorageConfig;
+    use crate::sources::{HealthCell, SourceHealth, SourceSender, WeatherSource};
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use chrono::{TimeZone, Utc};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    // Sends readings one second apart in bursts until stopped, counting the ones the
+    // station accepted. The bursts outpace storage so readings are still queued at shutdown.
+    struct FakeSource {
+        delivered: Arc<AtomicUsize>,
+        stop: Arc<AtomicBool>,
+        task: Option<JoinHandle<()>>,
+        health: HealthCell,
+    }
+
+    #[async_trait]
+    impl WeatherSource for FakeSource {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        async fn start(&mut self, tx: SourceSender) -> Result<()> {
+            let (delivered, stop) = (self.delivered.clone(), self.stop.clone());
+            self.task = Some(tokio::spawn(async move {
+                let start = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+                for i in 0.. {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let data = WeatherData::new(10.0, 50.0, 1013.0, 1.0, 90.0, 0.0, 1.0, 100.0).with_timestamp(start + chrono::Duration::seconds(i));
+                    if tx.send(data) {
+                        delivered.fetch_add(1, Ordering::SeqCst);
+                    }
+                    if i % 100 == 99 {
+                        tokio::task::yield_now().await;
+                    }
+                }
+            }));
+            self.health.set(SourceHealth::Healthy);
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> Result<()> {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(task) = self.task.take() {
+                task.await?;
+            }
+            self.health.set(SourceHealth::Stopped);
+            Ok(())
+        }
+
+        fn health(&self) -> SourceHealth {
+            self.health.get()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn shutdown_persists_every_reading_sent_before_it() {
+        let path = std::env::temp_dir().join(format!("station-{}.db", uuid::Uuid::new_v4()));
+        let config = Config {
+            storage: StorageConfig { database: path.to_string_lossy().into_owned() },
+            sources: Vec::new(),
+            ..Config::default()
+        };
+        let mut station = Station::new(&config);
+        let delivered = Arc::new(AtomicUsize::new(0));
+        station.collector().lock().await.add_source(Box::new(FakeSource {
+            delivered: delivered.clone(),
+            stop: Arc::new(AtomicBool::new(false)),
+            task: None,
+            health: HealthCell::new(),
+        }));
+        station.start();
+
+        // Shut down mid-stream, with the source still sending and readings still queued.
+        while delivered.load(Ordering::SeqCst) < 2_000 {
+            tokio::task::yield_now().await;
+        }
+        station.shutdown().await;
+
+        let sent = delivered.load(Ordering::SeqCst);
+        let store = SqliteStore::open(&path).unwrap();
+        let (from, to) = (Utc.timestamp_opt(0, 0).unwrap(), Utc.timestamp_opt(2_000_000_000, 0).unwrap());
+        let stored = store.query_range(from, to).unwrap();
+        assert_eq!(stored.len(), sent);
+        let start = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        assert!(stored.iter().enumerate().all(|(i, data)| data.timestamp == start + chrono::Duration::seconds(i as i64)));
+
+        drop((station, store));
+        let _ = std::fs::remove_file(&path);
+    }
+}
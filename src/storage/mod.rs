@@ -0,0 +1,36 @@
+pub mod sqlite;
+
+pub use sqlite::SqliteStore;
+
+use crate::data::WeatherData;
+use crate::rollup::Aggregate;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+/// Persistent backing for `TelemetrySystem`. Readings are returned oldest first.
+pub trait WeatherStore: Send {
+    fn insert(&mut self, data: &WeatherData) -> Result<()>;
+
+    fn load_recent(&self, limit: usize) -> Result<Vec<WeatherData>>;
+
+    fn query_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<WeatherData>>;
+
+    /// Number of readings `query_range` would return, without loading them.
+    fn count_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<usize>;
+
+    /// The mean of every field over each bucket of the range aligned to multiples of
+    /// `resolution`, stamped at the bucket start; buckets without readings are left out.
+    fn bucket_means(&self, from: DateTime<Utc>, to: DateTime<Utc>, resolution: Duration) -> Result<Vec<WeatherData>>;
+
+    /// Min/max/mean/last of every field over the range; `None` if it holds no readings.
+    fn range_stats(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Option<Aggregate>>;
+
+    /// Inserts the readings that aren't stored yet for the same source and timestamp,
+    /// including repeats within `readings`, and returns them.
+    fn insert_new<'a>(&mut self, readings: &'a [WeatherData]) -> Result<Vec<&'a WeatherData>>;
+
+    /// Makes everything inserted so far durable; called on shutdown.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
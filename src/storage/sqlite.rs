@@ -0,0 +1,375 @@
+use crate::data::{Metric, WeatherData};
+use crate::rollup::{Aggregate, FieldStats};
+use crate::storage::WeatherStore;
+use anyhow::Result;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+use uuid::Uuid;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS weather_readings (
+        id TEXT PRIMARY KEY,
+        timestamp_ms INTEGER NOT NULL,
+        source TEXT NOT NULL DEFAULT '',
+        temperature REAL,
+        humidity REAL,
+        pressure REAL,
+        wind_speed REAL,
+        wind_direction REAL,
+        rain_rate REAL,
+        uv_index REAL,
+        solar_radiation REAL
+    );
+    CREATE INDEX IF NOT EXISTS idx_weather_readings_timestamp ON weather_readings (timestamp_ms);
+";
+
+const COLUMNS: &str = "id, timestamp_ms, source, temperature, humidity, pressure, wind_speed, \
+    wind_direction, rain_rate, uv_index, solar_radiation";
+
+// Range condition shared by the queries, `from` exclusive and `to` inclusive.
+const IN_RANGE: &str = "timestamp_ms > ?1 AND timestamp_ms <= ?2";
+
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        // WAL keeps readers (range queries) from blocking the writer.
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.execute_batch(SCHEMA)?;
+        register_functions(&connection)?;
+        Ok(Self { connection })
+    }
+
+    /// A database that lives only as long as the store, e.g. for tests and dry runs.
+    pub fn open_in_memory() -> Result<Self> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch(SCHEMA)?;
+        register_functions(&connection)?;
+        Ok(Self { connection })
+    }
+
+    fn row_to_weather(row: &Row) -> rusqlite::Result<WeatherData> {
+        let id: String = row.get(0)?;
+        let timestamp_ms: i64 = row.get(1)?;
+        Ok(WeatherData {
+            id: Uuid::parse_str(&id).unwrap_or_else(|_| Uuid::new_v4()),
+            timestamp: Utc.timestamp_millis_opt(timestamp_ms).single().unwrap_or_default(),
+            source: row.get(2)?,
+            temperature: measured(row, 3)?,
+            humidity: measured(row, 4)?,
+            pressure: measured(row, 5)?,
+            wind_speed: measured(row, 6)?,
+            wind_direction: measured(row, 7)?,
+            rain_rate: measured(row, 8)?,
+            uv_index: measured(row, 9)?,
+            solar_radiation: measured(row, 10)?,
+        })
+    }
+}
+
+impl WeatherStore for SqliteStore {
+    fn insert(&mut self, data: &WeatherData) -> Result<()> {
+        insert_row(&self.connection, data)
+    }
+
+    fn load_recent(&self, limit: usize) -> Result<Vec<WeatherData>> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT {} FROM weather_readings ORDER BY timestamp_ms DESC LIMIT ?1",
+            COLUMNS
+        ))?;
+        let mut readings = statement
+            .query_map(params![limit as i64], Self::row_to_weather)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        readings.reverse();
+        Ok(readings)
+    }
+
+    fn query_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<WeatherData>> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT {} FROM weather_readings WHERE timestamp_ms > ?1 AND timestamp_ms <= ?2 ORDER BY timestamp_ms",
+            COLUMNS
+        ))?;
+        let readings = statement
+            .query_map(params![from.timestamp_millis(), to.timestamp_millis()], Self::row_to_weather)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(readings)
+    }
+
+    fn count_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<usize> {
+        let mut statement = self.connection.prepare_cached(&format!("SELECT COUNT(*) FROM weather_readings WHERE {}", IN_RANGE))?;
+        let count: i64 = statement.query_row(params![from.timestamp_millis(), to.timestamp_millis()], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    // Grouped in the database so only one row per bucket comes back. The last values are
+    // left out as the means don't need them.
+    fn bucket_means(&self, from: DateTime<Utc>, to: DateTime<Utc>, resolution: Duration) -> Result<Vec<WeatherData>> {
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT timestamp_ms - ((timestamp_ms % ?3) + ?3) % ?3 AS bucket, {} FROM weather_readings WHERE {} GROUP BY bucket ORDER BY bucket",
+            aggregate_columns(), IN_RANGE
+        ))?;
+        let buckets = statement
+            .query_map(params![from.timestamp_millis(), to.timestamp_millis(), resolution.num_milliseconds()], |row| {
+                let start = Utc.timestamp_millis_opt(row.get(0)?).single().unwrap_or_default();
+                let (count, fields, wind) = aggregate_parts(row, 1)?;
+                Ok(Aggregate::from_parts(start, count, fields, wind).to_weather_data())
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(buckets)
+    }
+
+    fn range_stats(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Option<Aggregate>> {
+        let range = params![from.timestamp_millis(), to.timestamp_millis()];
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT MIN(timestamp_ms), {} FROM weather_readings WHERE {}",
+            aggregate_columns(), IN_RANGE
+        ))?;
+        let (first, (count, mut fields, wind)) = statement.query_row(range, |row| Ok((row.get::<_, Option<i64>>(0)?, aggregate_parts(row, 1)?)))?;
+        let Some(start) = first.and_then(|first| Utc.timestamp_millis_opt(first).single()) else {
+            return Ok(None);
+        };
+
+        for metric in Metric::ALL {
+            let mut last = self.connection.prepare_cached(&format!(
+                "SELECT {0} FROM weather_readings WHERE {1} AND {0} IS NOT NULL ORDER BY timestamp_ms DESC LIMIT 1",
+                metric.key(), IN_RANGE
+            ))?;
+            if let Some(value) = last.query_row(range, |row| row.get::<_, f32>(0)).optional()? {
+                fields[metric as usize].last = value;
+            }
+        }
+        Ok(Some(Aggregate::from_parts(start, count, fields, wind)))
+    }
+
+    // One transaction for the whole batch; the timestamp index keeps the lookups cheap.
+    fn insert_new<'a>(&mut self, readings: &'a [WeatherData]) -> Result<Vec<&'a WeatherData>> {
+        let transaction = self.connection.transaction()?;
+        let mut inserted = Vec::new();
+        {
+            let mut exists = transaction.prepare_cached(
+                "SELECT 1 FROM weather_readings WHERE timestamp_ms = ?1 AND source = ?2 LIMIT 1"
+            )?;
+            for data in readings {
+                if !exists.exists(params![data.timestamp.timestamp_millis(), data.source])? {
+                    insert_row(&transaction, data)?;
+                    inserted.push(data);
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(inserted)
+    }
+
+    // Folds the write-ahead log back into the database file so a copied .db is complete.
+    fn flush(&mut self) -> Result<()> {
+        self.connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+}
+
+fn insert_row(connection: &Connection, data: &WeatherData) -> Result<()> {
+    let mut statement = connection.prepare_cached(&format!(
+        "INSERT OR IGNORE INTO weather_readings ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        COLUMNS
+    ))?;
+    statement.execute(params![
+        data.id.to_string(),
+        data.timestamp.timestamp_millis(),
+        data.source,
+        nullable(data.temperature),
+        nullable(data.humidity),
+        nullable(data.pressure),
+        nullable(data.wind_speed),
+        nullable(data.wind_direction),
+        nullable(data.rain_rate),
+        nullable(data.uv_index),
+        nullable(data.solar_radiation),
+    ])?;
+    Ok(())
+}
+
+// The unit vector of a wind direction, so it can be averaged like `Aggregate` does.
+fn register_functions(connection: &Connection) -> Result<()> {
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+    connection.create_scalar_function("wind_x", 1, flags, |context| {
+        Ok(context.get::<Option<f64>>(0)?.map(|degrees| degrees.to_radians().sin()))
+    })?;
+    connection.create_scalar_function("wind_y", 1, flags, |context| {
+        Ok(context.get::<Option<f64>>(0)?.map(|degrees| degrees.to_radians().cos()))
+    })?;
+    Ok(())
+}
+
+// Min, max, sum and count of every field, then the wind vector sums and the reading count.
+fn aggregate_columns() -> String {
+    let mut columns: Vec<String> = Metric::ALL
+        .iter()
+        .map(|metric| format!("MIN({0}), MAX({0}), SUM({0}), COUNT({0})", metric.key()))
+        .collect();
+    columns.push("SUM(wind_x(wind_direction)), SUM(wind_y(wind_direction)), COUNT(*)".to_string());
+    columns.join(", ")
+}
+
+// Reads `aggregate_columns` starting at `offset` as the reading count, field stats and
+// wind vector sums; the last values are left NaN.
+fn aggregate_parts(row: &Row, offset: usize) -> rusqlite::Result<(u32, [FieldStats; Metric::ALL.len()], (f64, f64))> {
+    let mut fields = [FieldStats { min: f32::NAN, max: f32::NAN, sum: 0.0, last: f32::NAN, count: 0 }; Metric::ALL.len()];
+    for (index, stats) in fields.iter_mut().enumerate() {
+        let column = offset + index * 4;
+        stats.min = measured(row, column)?;
+        stats.max = measured(row, column + 1)?;
+        stats.sum = row.get::<_, Option<f64>>(column + 2)?.unwrap_or(0.0);
+        stats.count = row.get(column + 3)?;
+    }
+    let column = offset + Metric::ALL.len() * 4;
+    let wind = (row.get::<_, Option<f64>>(column)?.unwrap_or(0.0), row.get::<_, Option<f64>>(column + 1)?.unwrap_or(0.0));
+    Ok((row.get(column + 2)?, fields, wind))
+}
+
+// Values that were not measured (NaN) are stored as NULL.
+fn nullable(value: f32) -> Option<f32> {
+    (!value.is_nan()).then_some(value)
+}
+
+fn measured(row: &Row, index: usize) -> rusqlite::Result<f32> {
+    Ok(row.get::<_, Option<f32>>(index)?.unwrap_or(f32::NAN))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(secs: i64, source: &str, temperature: f32) -> WeatherData {
+        let mut data = WeatherData::new(temperature, 50.0, 1013.0, 2.0, 180.0, 0.0, 1.0, 100.0)
+            .with_timestamp(Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap());
+        data.source = source.to_string();
+        data
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn store(readings: &[WeatherData]) -> SqliteStore {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        for data in readings {
+            store.insert(data).unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn query_range_excludes_from_and_includes_to() {
+        let store = store(&[reading(0, "a", 1.0), reading(60, "a", 2.0), reading(120, "a", 3.0), reading(180, "a", 4.0)]);
+        let temperatures = |from, to| store.query_range(at(from), at(to)).unwrap().iter().map(|data| data.temperature).collect::<Vec<_>>();
+        assert_eq!(temperatures(0, 120), [2.0, 3.0]);
+        assert_eq!(temperatures(-1, 0), [1.0]);
+        assert_eq!(temperatures(59, 181), [2.0, 3.0, 4.0]);
+        assert!(temperatures(180, 300).is_empty());
+        assert!(temperatures(120, 120).is_empty());
+    }
+
+    #[test]
+    fn load_recent_returns_the_newest_oldest_first() {
+        // Inserted out of order, as late readings are.
+        let store = store(&[reading(120, "a", 3.0), reading(0, "a", 1.0), reading(180, "a", 4.0), reading(60, "a", 2.0)]);
+        let temperatures = |limit| store.load_recent(limit).unwrap().iter().map(|data| data.temperature).collect::<Vec<_>>();
+        assert_eq!(temperatures(2), [3.0, 4.0]);
+        assert_eq!(temperatures(10), [1.0, 2.0, 3.0, 4.0]);
+        assert!(temperatures(0).is_empty());
+    }
+
+    #[test]
+    fn insert_new_skips_stored_and_repeated_readings() {
+        let mut store = store(&[reading(0, "a", 1.0)]);
+        let batch = [
+            // Already stored for this source and time, though with another id.
+            reading(0, "a", 9.0),
+            // Same time from another source.
+            reading(0, "b", 2.0),
+            reading(60, "a", 3.0),
+            // Repeated within the batch.
+            reading(60, "a", 4.0),
+        ];
+        let inserted = store.insert_new(&batch).unwrap();
+        assert_eq!(inserted.iter().map(|data| (data.source.as_str(), data.temperature)).collect::<Vec<_>>(), [("b", 2.0), ("a", 3.0)]);
+
+        let stored = store.load_recent(10).unwrap();
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored.iter().filter(|data| data.timestamp == at(0) && data.source == "a").map(|data| data.temperature).collect::<Vec<_>>(), [1.0]);
+        assert!(store.insert_new(&batch).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unmeasured_fields_round_trip_as_null() {
+        let mut missing = reading(0, "a", f32::NAN);
+        missing.rain_rate = f32::NAN;
+        let store = store(&[missing.clone()]);
+
+        let nulls: i64 = store.connection.query_row(
+            "SELECT COUNT(*) FROM weather_readings WHERE temperature IS NULL AND rain_rate IS NULL AND pressure IS NOT NULL",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(nulls, 1);
+        let stored = &store.load_recent(1).unwrap()[0];
+        assert_eq!(stored.id, missing.id);
+        assert!(stored.temperature.is_nan() && stored.rain_rate.is_nan());
+        assert_eq!(stored.pressure, 1013.0);
+    }
+
+    #[test]
+    fn count_range_matches_query_range() {
+        let store = store(&[reading(0, "a", 1.0), reading(60, "a", 2.0), reading(60, "b", 2.0), reading(120, "a", 3.0)]);
+        for (from, to) in [(-1, 120), (0, 60), (60, 300), (120, 120)] {
+            assert_eq!(store.count_range(at(from), at(to)).unwrap(), store.query_range(at(from), at(to)).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn range_stats_match_aggregating_the_readings() {
+        let mut readings = vec![reading(0, "a", 10.0), reading(60, "a", 14.0), reading(120, "b", 12.0), reading(180, "a", f32::NAN)];
+        readings[0].wind_direction = 350.0;
+        readings[1].wind_direction = 10.0;
+        readings[2].wind_direction = f32::NAN;
+        readings[3].uv_index = f32::NAN;
+        let store = store(&readings);
+
+        let stats = store.range_stats(at(-1), at(180)).unwrap().unwrap();
+        let expected = Aggregate::from_readings(&readings).unwrap();
+        assert_eq!((stats.start, stats.count), (expected.start, expected.count));
+        for metric in Metric::ALL {
+            let (field, expected_field) = (stats.stats(metric), expected.stats(metric));
+            assert_eq!(field.count, expected_field.count, "{:?}", metric);
+            for (value, expected_value) in [(field.min, expected_field.min), (field.max, expected_field.max), (field.last, expected_field.last), (stats.mean(metric), expected.mean(metric))] {
+                assert!((value - expected_value).abs() < 1e-3, "{:?}: {} != {}", metric, value, expected_value);
+            }
+        }
+        // The newest reading has no temperature, so the last one is from the reading before.
+        assert_eq!(stats.stats(Metric::Temperature).last, 12.0);
+        assert!(store.range_stats(at(180), at(300)).unwrap().is_none());
+    }
+
+    #[test]
+    fn bucket_means_are_aligned_to_the_resolution() {
+        let mut readings = vec![reading(0, "a", 10.0), reading(200, "a", 14.0), reading(260, "a", 20.0), reading(900, "a", 30.0)];
+        readings[1].wind_direction = 350.0;
+        readings[2].wind_direction = 10.0;
+        readings[3].uv_index = f32::NAN;
+        let store = store(&readings);
+
+        // 1_700_000_000 is 200 seconds past a multiple of five minutes.
+        let buckets = store.bucket_means(at(-1), at(900), Duration::minutes(5)).unwrap();
+        assert_eq!(buckets.iter().map(|data| data.timestamp).collect::<Vec<_>>(), [at(-200), at(100), at(700)]);
+        assert_eq!(buckets.iter().map(|data| data.temperature).collect::<Vec<_>>(), [10.0, 17.0, 30.0]);
+        assert!(buckets[1].wind_direction.abs() < 1e-3 || (buckets[1].wind_direction - 360.0).abs() < 1e-3);
+        assert!(buckets[2].uv_index.is_nan());
+        assert!(store.bucket_means(at(900), at(1000), Duration::minutes(5)).unwrap().is_empty());
+    }
+}
@@ -1,11 +1,17 @@
 use crate::data::WeatherData;
+use crate::metrics::IngestMetrics;
+use crate::sources::{SourceHealth, SourceSender, WeatherSource};
+use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::mpsc;
-use std::time::Duration;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
-#[derive(Clone)]
+/// Runs the registered sources and forwards their readings on one channel.
 pub struct TelemetryCollector {
     weather_tx: mpsc::UnboundedSender<WeatherData>,
+    sources: Vec<Box<dyn WeatherSource>>,
+    start_errors: HashMap<String, String>,
+    metrics: Arc<IngestMetrics>,
 }
 
 impl TelemetryCollector {
@@ -18,45 +24,170 @@ impl TelemetryCollector {
         (
             Self {
                 weather_tx,
+                sources: Vec::new(),
+                start_errors: HashMap::new(),
+                metrics: Arc::new(IngestMetrics::new()),
             },
             weather_rx,
         )
     }
 
-    pub async fn start_weather_simulation(&self) -> Result<()> {
-        let tx = self.weather_tx.clone();
-        
-        tokio::spawn(async move {
-            let mut base_temp = 20.0;
-            let mut base_humidity = 60.0;
-            let mut base_pressure = 1013.25;
-            
-            loop {
-                let weather = WeatherData::new(
-                    base_temp + (rand::random::<f32>() - 0.5) * 10.0,
-                    base_humidity + (rand::random::<f32>() - 0.5) * 20.0,
-                    base_pressure + (rand::random::<f32>() - 0.5) * 50.0,
-                    rand::random::<f32>() * 15.0,
-                    rand::random::<f32>() * 360.0,
-                    rand::random::<f32>() * 5.0,
-                    rand::random::<f32>() * 10.0,
-                    rand::random::<f32>() * 1000.0,
-                );
-
-                if tx.send(weather).is_err() {
-                    break;
+    /// Counts readings per source; the channel's consumer records them as received.
+    pub fn metrics(&self) -> Arc<IngestMetrics> {
+        self.metrics.clone()
+    }
+
+    pub fn add_source(&mut self, source: Box<dyn WeatherSource>) {
+        self.sources.push(source);
+    }
+
+    /// Starts every registered source; a failing source is logged and does not
+    /// prevent the others from running.
+    pub async fn start(&mut self) -> Result<()> {
+        let mut failed = Vec::new();
+        self.start_errors.clear();
+
+        for source in self.sources.iter_mut() {
+            let sender = SourceSender::new(source.name(), self.weather_tx.clone(), self.metrics.clone());
+            match source.start(sender).await {
+                Ok(()) => tracing::info!("Started weather source '{}'", source.name()),
+                Err(e) => {
+                    tracing::error!("Failed to start weather source '{}': {}", source.name(), e);
+                    failed.push(source.name().to_string());
+                    self.start_errors.insert(source.name().to_string(), e.to_string());
                 }
+            }
+        }
 
-                base_temp += (rand::random::<f32>() - 0.5) * 0.5;
-                base_humidity += (rand::random::<f32>() - 0.5) * 2.0;
-                base_pressure += (rand::random::<f32>() - 0.5) * 1.0;
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("weather sources failed to start: {}", failed.join(", ")))
+        }
+    }
 
-                tokio::time::sleep(Duration::from_secs(5)).await;
+    pub async fn stop(&mut self) {
+        for source in self.sources.iter_mut() {
+            if let Err(e) = source.stop().await {
+                tracing::warn!("Failed to stop weather source '{}': {}", source.name(), e);
             }
-        });
+        }
+    }
 
-        Ok(())
+    pub fn source_health(&self) -> Vec<(String, SourceHealth)> {
+        self.sources
+            .iter()
+            .map(|source| {
+                let health = match self.start_errors.get(source.name()) {
+                    Some(error) => SourceHealth::Failed(error.clone()),
+                    None => source.health(),
+                };
+                (source.name().to_string(), health)
+            })
+            .collect()
     }
 
+    /// Failures per source, including a failed start.
+    pub fn source_errors(&self) -> Vec<(String, u64)> {
+        self.sources
+            .iter()
+            .map(|source| {
+                let start_error = self.start_errors.contains_key(source.name()) as u64;
+                (source.name().to_string(), source.error_count() + start_error)
+            })
+            .collect()
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::HealthCell;
+    use async_trait::async_trait;
+
+    // Sends a fixed list of temperatures on start, or fails to start when `fail` is set.
+    struct FakeSource {
+        name: String,
+        temperatures: Vec<f32>,
+        fail: bool,
+        health: HealthCell,
+    }
+
+    impl FakeSource {
+        fn new(name: &str, temperatures: &[f32]) -> Self {
+            Self { name: name.to_string(), temperatures: temperatures.to_vec(), fail: false, health: HealthCell::new() }
+        }
+    }
+
+    #[async_trait]
+    impl WeatherSource for FakeSource {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        async fn start(&mut self, tx: SourceSender) -> Result<()> {
+            if self.fail {
+                return Err(anyhow!("port busy"));
+            }
+            self.health.set(SourceHealth::Starting);
+            for temperature in &self.temperatures {
+                tx.send(WeatherData::new(*temperature, 50.0, 1013.0, 1.0, 90.0, 0.0, 1.0, 100.0));
+            }
+            self.health.set(SourceHealth::Healthy);
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> Result<()> {
+            self.health.set(SourceHealth::Stopped);
+            Ok(())
+        }
+
+        fn health(&self) -> SourceHealth {
+            self.health.get()
+        }
+
+        fn error_count(&self) -> u64 {
+            self.health.error_count()
+        }
+    }
+
+    #[tokio::test]
+    async fn readings_keep_per_source_order_and_tags() {
+        let (mut collector, mut rx) = TelemetryCollector::new();
+        collector.add_source(Box::new(FakeSource::new("a", &[1.0, 2.0, 3.0])));
+        collector.add_source(Box::new(FakeSource::new("b", &[10.0, 20.0])));
+        collector.start().await.unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(data) = rx.try_recv() {
+            received.push((data.source, data.temperature));
+        }
+        let from = |name: &str| received.iter().filter(|(source, _)| source == name).map(|(_, t)| *t).collect::<Vec<_>>();
+        assert_eq!(from("a"), vec![1.0, 2.0, 3.0]);
+        assert_eq!(from("b"), vec![10.0, 20.0]);
+        assert_eq!(collector.metrics().snapshot()["a"].sent, 3);
+    }
+
+    #[tokio::test]
+    async fn failed_start_is_reported_without_stopping_others() {
+        let (mut collector, mut rx) = TelemetryCollector::new();
+        let mut broken = FakeSource::new("broken", &[]);
+        broken.fail = true;
+        collector.add_source(Box::new(broken));
+        collector.add_source(Box::new(FakeSource::new("ok", &[5.0])));
+
+        let error = collector.start().await.unwrap_err();
+        assert!(error.to_string().contains("broken"));
+        assert_eq!(rx.try_recv().unwrap().source, "ok");
+
+        let health: HashMap<_, _> = collector.source_health().into_iter().collect();
+        assert_eq!(health["broken"], SourceHealth::Failed("port busy".to_string()));
+        assert_eq!(health["ok"], SourceHealth::Healthy);
+        let errors: HashMap<_, _> = collector.source_errors().into_iter().collect();
+        assert_eq!((errors["broken"], errors["ok"]), (1, 0));
+
+        collector.stop().await;
+        let health: HashMap<_, _> = collector.source_health().into_iter().collect();
+        assert_eq!(health["ok"], SourceHealth::Stopped);
+    }
+}
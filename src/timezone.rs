@@ -0,0 +1,76 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use std::str::FromStr;
+
+/// Time zone wall-clock times are read and written in. Readings are always stored in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayTimezone {
+    Utc,
+    Local,
+    Offset(FixedOffset),
+}
+
+impl DisplayTimezone {
+    pub fn label(&self) -> String {
+        match self {
+            DisplayTimezone::Utc => "UTC".to_string(),
+            DisplayTimezone::Local => "Local".to_string(),
+            DisplayTimezone::Offset(offset) => offset.to_string(),
+        }
+    }
+
+    /// The UTC instant of a wall-clock time in this zone; `None` if it does not exist,
+    /// e.g. inside a daylight saving gap.
+    pub fn to_utc(&self, time: NaiveDateTime) -> Option<DateTime<Utc>> {
+        let time = match self {
+            DisplayTimezone::Utc => Utc.from_local_datetime(&time).earliest()?,
+            DisplayTimezone::Local => Local.from_local_datetime(&time).earliest()?.with_timezone(&Utc),
+            DisplayTimezone::Offset(offset) => offset.from_local_datetime(&time).earliest()?.with_timezone(&Utc),
+        };
+        Some(time)
+    }
+
+    /// Parses an RFC 3339 time, or a wall-clock `YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DD`
+    /// in this zone.
+    pub fn parse(&self, value: &str) -> Option<DateTime<Utc>> {
+        let value = value.trim();
+        if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+            return Some(time.with_timezone(&Utc));
+        }
+        let time = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"].iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))?;
+        self.to_utc(time)
+    }
+
+    /// Offset from UTC in this zone at `time`.
+    pub fn offset_at(&self, time: DateTime<Utc>) -> FixedOffset {
+        match self {
+            DisplayTimezone::Utc => FixedOffset::east_opt(0).expect("zero offset"),
+            DisplayTimezone::Local => *time.with_timezone(&Local).offset(),
+            DisplayTimezone::Offset(offset) => *offset,
+        }
+    }
+
+    /// `time` as RFC 3339 with this zone's offset.
+    pub fn format(&self, time: DateTime<Utc>) -> String {
+        match self {
+            DisplayTimezone::Utc => time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            _ => time.with_timezone(&self.offset_at(time)).to_rfc3339_opts(SecondsFormat::AutoSi, false),
+        }
+    }
+}
+
+impl FromStr for DisplayTimezone {
+    type Err = String;
+
+    /// `utc`, `local` or a fixed offset such as `+02:00`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "utc" | "z" => Ok(DisplayTimezone::Utc),
+            "local" => Ok(DisplayTimezone::Local),
+            _ => s.parse::<FixedOffset>()
+                .map(DisplayTimezone::Offset)
+                .map_err(|_| format!("unknown time zone '{}', expected utc, local or an offset like +02:00", s)),
+        }
+    }
+}
@@ -1,59 +1,132 @@
 use eframe::egui;
+use crate::alerts::{AlertEvent, AlertState, AlertStatus};
 use crate::data::WeatherData;
-use crate::ui::widgets::WeatherWidget;
+use crate::history::WeatherHistory;
+use crate::rain::RainReport;
+use crate::rollup::Aggregate;
+use crate::sources::SourceHealth;
+use crate::units::{Dimension, UnitPreferences};
+use crate::config::Config;
+use crate::import::ImportReport;
+use crate::ui::widgets::{filter_by_time_range, AlertsWidget, ExportRequest, ExportWidget, ImportRequest, ImportWidget, RainWidget, SettingsWidget, TimeRange, WeatherWidget, WindWidget};
+
+/// Everything the dashboard draws in one frame.
+pub struct DashboardData<'a> {
+    pub current: Option<&'a WeatherData>,
+    /// The raw in-memory readings, newest last.
+    pub recent: &'a WeatherHistory,
+    /// Chart series for the selected range: raw readings, or rollup means when `history_is_raw` is false.
+    pub history: &'a [WeatherData],
+    pub history_is_raw: bool,
+    pub summary: Option<&'a Aggregate>,
+    pub rain: &'a RainReport,
+    pub alerts: &'a [AlertStatus],
+    pub alert_history: &'a [AlertEvent],
+    pub source_health: &'a [(String, SourceHealth)],
+}
 
 pub struct DashboardUI {
     weather_widget: WeatherWidget,
+    wind_widget: WindWidget,
+    rain_widget: RainWidget,
+    alerts_widget: AlertsWidget,
+    settings_widget: SettingsWidget,
+    export_widget: ExportWidget,
+    import_widget: ImportWidget,
     selected_time_range: TimeRange,
+    selected_view: DashboardView,
+    units: UnitPreferences,
+    saved_config: Option<Config>,
+    export_request: Option<ExportRequest>,
+    import_request: Option<ImportRequest>,
 }
 
-#[derive(PartialEq)]
-enum TimeRange {
-    LastHour,
-    Last6Hours,
-    Last24Hours,
-    Last7Days,
+#[derive(PartialEq, Clone, Copy)]
+enum DashboardView {
+    Charts,
+    Wind,
+    Rain,
+    Alerts,
 }
 
-impl TimeRange {
-    fn to_hours(&self) -> u32 {
-        match self {
-            TimeRange::LastHour => 1,
-            TimeRange::Last6Hours => 6,
-            TimeRange::Last24Hours => 24,
-            TimeRange::Last7Days => 168,
-        }
-    }
+impl DashboardView {
+    const ALL: [DashboardView; 4] = [DashboardView::Charts, DashboardView::Wind, DashboardView::Rain, DashboardView::Alerts];
 
     fn label(&self) -> &'static str {
         match self {
-            TimeRange::LastHour => "Last Hour",
-            TimeRange::Last6Hours => "Last 6 Hours",
-            TimeRange::Last24Hours => "Last 24 Hours",
-            TimeRange::Last7Days => "Last 7 Days",
+            DashboardView::Charts => "Charts",
+            DashboardView::Wind => "Wind",
+            DashboardView::Rain => "Rain",
+            DashboardView::Alerts => "Alerts",
         }
     }
 }
 
 impl DashboardUI {
-    pub fn new() -> Self {
+    pub fn new(config: &Config, settings_widget: SettingsWidget) -> Self {
         Self {
             weather_widget: WeatherWidget::new(),
-            selected_time_range: TimeRange::Last6Hours,
+            wind_widget: WindWidget::new(),
+            rain_widget: RainWidget::new(),
+            alerts_widget: AlertsWidget::new(),
+            settings_widget,
+            export_widget: ExportWidget::new(),
+            import_widget: ImportWidget::new(),
+            selected_time_range: TimeRange::from_hours(config.ui.time_range_hours),
+            selected_view: DashboardView::Charts,
+            units: config.units,
+            saved_config: None,
+            export_request: None,
+            import_request: None,
         }
     }
 
-    pub fn render(
-        &mut self,
-        ctx: &egui::Context,
-        current_weather: Option<&WeatherData>,
-        weather_history: &[WeatherData],
-    ) {
-        self.render_header(ctx, current_weather);
-        self.render_main_content(ctx, current_weather, weather_history);
+    pub fn render(&mut self, ctx: &egui::Context, data: &DashboardData) {
+        self.render_header(ctx, data.current, data.alerts, data.source_health);
+        self.render_main_content(ctx, data);
+        if let Some(config) = self.settings_widget.show(ctx) {
+            self.units = config.units;
+            self.saved_config = Some(config);
+        }
+        let latest = data.current.map(|current| current.timestamp);
+        if let Some(request) = self.export_widget.show(ctx, latest, self.selected_time_range.to_hours(), &self.units) {
+            self.export_request = Some(request);
+        }
+        if let Some(request) = self.import_widget.show(ctx) {
+            self.import_request = Some(request);
+        }
+    }
+
+    /// The config written by the settings window since the last call, if any.
+    pub fn take_saved_config(&mut self) -> Option<Config> {
+        self.saved_config.take()
+    }
+
+    /// The export the user asked for since the last call, if any. Its outcome goes back
+    /// through [`export_finished`](Self::export_finished).
+    pub fn take_export_request(&mut self) -> Option<ExportRequest> {
+        self.export_request.take()
+    }
+
+    pub fn export_finished(&mut self, result: anyhow::Result<(usize, std::path::PathBuf)>) {
+        self.export_widget.finished(result);
+    }
+
+    /// The file the user asked to import since the last call, if any. Its report goes
+    /// back through [`import_finished`](Self::import_finished).
+    pub fn take_import_request(&mut self) -> Option<ImportRequest> {
+        self.import_request.take()
+    }
+
+    pub fn import_finished(&mut self, result: anyhow::Result<ImportReport>) {
+        self.import_widget.finished(result);
+    }
+
+    pub fn selected_hours(&self) -> u32 {
+        self.selected_time_range.to_hours()
     }
 
-    fn render_header(&mut self, ctx: &egui::Context, current_weather: Option<&WeatherData>) {
+    fn render_header(&mut self, ctx: &egui::Context, current_weather: Option<&WeatherData>, alerts: &[AlertStatus], source_health: &[(String, SourceHealth)]) {
         egui::TopBottomPanel::top("header")
             .exact_height(60.0)
             .show(ctx, |ui| {
@@ -82,12 +155,41 @@ impl DashboardUI {
                                 }
                             )).size(14.0).color(status_color));
                         });
+
+                        ui.add_space(20.0);
+
+                        ui.vertical(|ui| {
+                            for (name, health) in source_health {
+                                let health_color = if health.is_healthy() {
+                                    egui::Color32::from_rgb(34, 197, 94)
+                                } else {
+                                    egui::Color32::from_rgb(245, 158, 11)
+                                };
+                                ui.label(egui::RichText::new(format!("● {}: {}", name, health.label()))
+                                    .size(11.0).color(health_color))
+                                    .on_hover_text(match health {
+                                        SourceHealth::Degraded(reason) | SourceHealth::Failed(reason) => reason.as_str(),
+                                        _ => health.label(),
+                                    });
+                            }
+                        });
+                        
+                        let firing: Vec<&str> = alerts.iter()
+                            .filter(|status| status.state == AlertState::Firing)
+                            .map(|status| status.rule.as_str())
+                            .collect();
+                        if !firing.is_empty() {
+                            ui.add_space(20.0);
+                            ui.label(egui::RichText::new(format!("⚠ {} alert{} firing", firing.len(), if firing.len() == 1 { "" } else { "s" }))
+                                .size(14.0).strong().color(egui::Color32::from_rgb(239, 68, 68)))
+                                .on_hover_text(firing.join(", "));
+                        }
                         
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             if let Some(weather) = current_weather {
                                 ui.vertical(|ui| {
                                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                        ui.label(egui::RichText::new(format!("{:.1}°C", weather.temperature))
+                                        ui.label(egui::RichText::new(self.units.format(Dimension::Temperature, weather.temperature))
                                             .size(28.0).color(egui::Color32::WHITE).strong());
                                     });
                                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -102,12 +204,7 @@ impl DashboardUI {
             });
     }
 
-    fn render_main_content(
-        &mut self,
-        ctx: &egui::Context,
-        current_weather: Option<&WeatherData>,
-        weather_history: &[WeatherData],
-    ) {
+    fn render_main_content(&mut self, ctx: &egui::Context, data: &DashboardData) {
         egui::CentralPanel::default()
             .frame(egui::Frame::default().fill(egui::Color32::from_rgb(248, 250, 252)).inner_margin(10.0))
             .show(ctx, |ui| {
@@ -118,11 +215,46 @@ impl DashboardUI {
                         .selected_text(self.selected_time_range.label())
                         .width(120.0)
                         .show_ui(ui, |ui| {
-                            ui.selectable_value(&mut self.selected_time_range, TimeRange::LastHour, TimeRange::LastHour.label());
-                            ui.selectable_value(&mut self.selected_time_range, TimeRange::Last6Hours, TimeRange::Last6Hours.label());
-                            ui.selectable_value(&mut self.selected_time_range, TimeRange::Last24Hours, TimeRange::Last24Hours.label());
-                            ui.selectable_value(&mut self.selected_time_range, TimeRange::Last7Days, TimeRange::Last7Days.label());
+                            for range in TimeRange::ALL {
+                                ui.selectable_value(&mut self.selected_time_range, range, range.label());
+                            }
                         });
+
+                    ui.separator();
+
+                    for view in DashboardView::ALL {
+                        if ui.add(egui::Button::new(view.label())
+                            .fill(if self.selected_view == view { egui::Color32::from_rgb(99, 102, 241) } else { egui::Color32::from_rgb(241, 245, 249) })
+                            .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
+                            .rounding(4.0)
+                        ).clicked() {
+                            self.selected_view = view;
+                        }
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.add(egui::Button::new("⚙ Settings")
+                            .fill(egui::Color32::from_rgb(241, 245, 249))
+                            .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
+                            .rounding(4.0)
+                        ).clicked() {
+                            self.settings_widget.toggle();
+                        }
+                        if ui.add(egui::Button::new("📤 Export")
+                            .fill(egui::Color32::from_rgb(241, 245, 249))
+                            .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
+                            .rounding(4.0)
+                        ).clicked() {
+                            self.export_widget.toggle();
+                        }
+                        if ui.add(egui::Button::new("📥 Import")
+                            .fill(egui::Color32::from_rgb(241, 245, 249))
+                            .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
+                            .rounding(4.0)
+                        ).clicked() {
+                            self.import_widget.toggle();
+                        }
+                    });
                 });
 
                 ui.add_space(8.0);
@@ -132,16 +264,23 @@ impl DashboardUI {
                     // Left side - Weather cards (compact)
                     ui.vertical(|ui| {
                         ui.set_width(280.0);
-                        if let Some(current) = current_weather {
-                            self.render_compact_weather_cards(ui, current);
+                        if let Some(current) = data.current {
+                            egui::ScrollArea::vertical().id_source("weather_cards").show(ui, |ui| self.render_compact_weather_cards(ui, current));
                         }
                     });
                     
                     ui.add_space(10.0);
                     
                     // Right side - Charts and stats
+                    let hours = self.selected_time_range.to_hours();
+                    let filtered_history = filter_by_time_range(data.history, hours);
                     ui.vertical(|ui| {
-                        self.weather_widget.render(ui, current_weather, weather_history, self.selected_time_range.to_hours());
+                        match self.selected_view {
+                            DashboardView::Charts => self.weather_widget.render(ui, data.current, &filtered_history, data.summary, hours, &self.units),
+                            DashboardView::Wind => self.wind_widget.render(ui, data.current, data.recent, &filtered_history, data.history_is_raw, &self.units),
+                            DashboardView::Rain => self.rain_widget.render(ui, data.rain, hours, &self.units),
+                            DashboardView::Alerts => self.alerts_widget.render(ui, data.alerts, data.alert_history, &self.units),
+                        }
                     });
                 });
             });
@@ -149,22 +288,41 @@ impl DashboardUI {
 
     fn render_compact_weather_cards(&self, ui: &mut egui::Ui, weather: &WeatherData) {
         // Vertical compact layout for left panel
-        self.render_compact_card(ui, "T°", "Temperature", &format!("{:.1}°C", weather.temperature), egui::Color32::from_rgb(239, 68, 68));
+        let units = &self.units;
+        self.render_compact_card(ui, "T°", "Temperature", &units.format(Dimension::Temperature, weather.temperature), egui::Color32::from_rgb(239, 68, 68));
+        ui.add_space(6.0);
+        
+        self.render_compact_card(ui, "H%", "Humidity", &units.format(Dimension::Fixed("%"), weather.humidity), egui::Color32::from_rgb(59, 130, 246));
+        ui.add_space(6.0);
+        
+        self.render_compact_card(ui, "P", "Pressure", &units.format(Dimension::Pressure, weather.pressure), egui::Color32::from_rgb(34, 197, 94));
+        ui.add_space(6.0);
+        
+        self.render_compact_card(ui, "W", "Wind Speed", &units.format(Dimension::Speed, weather.wind_speed), egui::Color32::from_rgb(245, 158, 11));
+        ui.add_space(6.0);
+        
+        self.render_compact_card(ui, "R", "Rain Rate", &units.format(Dimension::RainRate, weather.rain_rate), egui::Color32::from_rgb(168, 85, 247));
+        ui.add_space(6.0);
+        
+        self.render_compact_card(ui, "UV", "UV Index", &units.format(Dimension::Fixed(""), weather.uv_index), egui::Color32::from_rgb(251, 146, 60));
+        ui.add_space(6.0);
+        
+        self.render_compact_card(ui, "DP", "Dew Point", &units.format(Dimension::Temperature, weather.dew_point()), egui::Color32::from_rgb(20, 184, 166));
         ui.add_space(6.0);
         
-        self.render_compact_card(ui, "H%", "Humidity", &format!("{:.1}%", weather.humidity), egui::Color32::from_rgb(59, 130, 246));
+        self.render_compact_card(ui, "FL", "Feels Like", &units.format(Dimension::Temperature, weather.feels_like()), egui::Color32::from_rgb(244, 63, 94));
         ui.add_space(6.0);
         
-        self.render_compact_card(ui, "P", "Pressure", &format!("{:.0} hPa", weather.pressure), egui::Color32::from_rgb(34, 197, 94));
+        self.render_compact_card(ui, "HI", "Heat Index", &units.format(Dimension::Temperature, weather.heat_index()), egui::Color32::from_rgb(234, 88, 12));
         ui.add_space(6.0);
         
-        self.render_compact_card(ui, "W", "Wind Speed", &format!("{:.1} m/s", weather.wind_speed), egui::Color32::from_rgb(245, 158, 11));
+        self.render_compact_card(ui, "WC", "Wind Chill", &units.format(Dimension::Temperature, weather.wind_chill()), egui::Color32::from_rgb(14, 165, 233));
         ui.add_space(6.0);
         
-        self.render_compact_card(ui, "R", "Rain Rate", &format!("{:.1} mm/h", weather.rain_rate), egui::Color32::from_rgb(168, 85, 247));
+        self.render_compact_card(ui, "AT", "Apparent Temp", &units.format(Dimension::Temperature, weather.apparent_temperature()), egui::Color32::from_rgb(217, 70, 239));
         ui.add_space(6.0);
         
-        self.render_compact_card(ui, "UV", "UV Index", &format!("{:.1}", weather.uv_index), egui::Color32::from_rgb(251, 146, 60));
+        self.render_compact_card(ui, "AH", "Abs. Humidity", &units.format(Dimension::Fixed("g/m³"), weather.absolute_humidity()), egui::Color32::from_rgb(6, 182, 212));
     }
 
     fn render_compact_card(&self, ui: &mut egui::Ui, icon: &str, label: &str, value: &str, accent_color: egui::Color32) {
@@ -1,4 +1,4 @@
 pub mod dashboard;
 pub mod widgets;
 
-pub use dashboard::DashboardUI;
\ No newline at end of file
+pub use dashboard::{DashboardData, DashboardUI};
\ No newline at end of file
@@ -0,0 +1,86 @@
+use eframe::egui;
+use crate::alerts::{AlertEvent, AlertState, AlertStatus};
+use crate::units::UnitPreferences;
+
+pub struct AlertsWidget;
+
+impl AlertsWidget {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn render(&self, ui: &mut egui::Ui, statuses: &[AlertStatus], history: &[AlertEvent], units: &UnitPreferences) {
+        let slate = egui::Color32::from_rgb(100, 116, 139);
+        let heading = egui::Color32::from_rgb(15, 23, 42);
+
+        ui.label(egui::RichText::new("Alert Rules").size(14.0).strong().color(heading));
+        ui.add_space(4.0);
+        if statuses.is_empty() {
+            ui.label(egui::RichText::new("No alert rules configured").color(slate));
+        } else {
+            egui::Frame::default()
+                .fill(egui::Color32::WHITE)
+                .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
+                .rounding(8.0)
+                .inner_margin(egui::Margin::symmetric(12.0, 8.0))
+                .show(ui, |ui| {
+                    egui::Grid::new("alert_rules")
+                        .num_columns(5)
+                        .spacing([16.0, 6.0])
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for title in ["Rule", "Condition", "State", "Since", "Value"] {
+                                ui.label(egui::RichText::new(title).size(11.0).strong().color(slate));
+                            }
+                            ui.end_row();
+
+                            for status in statuses {
+                                ui.label(egui::RichText::new(&status.rule).size(12.0).strong().color(heading));
+                                ui.label(egui::RichText::new(&status.description).size(12.0).color(heading));
+                                ui.label(egui::RichText::new(format!("● {}", status.state.label())).size(12.0).strong().color(state_color(status.state)));
+                                ui.label(egui::RichText::new(status.since.map(|since| since.with_timezone(&chrono::Local).format("%a %H:%M:%S").to_string()).unwrap_or_else(|| "-".to_string())).size(12.0).color(slate));
+                                ui.label(egui::RichText::new(status.value.map(|value| units.format(status.quantity.dimension(), value)).unwrap_or_else(|| "-".to_string())).size(12.0).color(heading));
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+
+        ui.add_space(12.0);
+        ui.label(egui::RichText::new("Alert History").size(14.0).strong().color(heading));
+        ui.add_space(4.0);
+        if history.is_empty() {
+            ui.label(egui::RichText::new("No alerts yet").color(slate));
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .id_source("alert_history")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for event in history {
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(event.timestamp.with_timezone(&chrono::Local).format("%a %d %b %H:%M:%S").to_string()).size(11.0).color(slate));
+                        ui.label(egui::RichText::new(event.state.label()).size(11.0).strong().color(state_color(event.state)));
+                        ui.label(egui::RichText::new(&event.rule).size(11.0).strong().color(heading));
+                        ui.label(egui::RichText::new(&event.message).size(11.0).color(heading));
+                    });
+                }
+            });
+    }
+}
+
+impl Default for AlertsWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn state_color(state: AlertState) -> egui::Color32 {
+    match state {
+        AlertState::Ok => egui::Color32::from_rgb(34, 197, 94),
+        AlertState::Pending => egui::Color32::from_rgb(245, 158, 11),
+        AlertState::Firing => egui::Color32::from_rgb(239, 68, 68),
+        AlertState::Resolved => egui::Color32::from_rgb(59, 130, 246),
+    }
+}
@@ -0,0 +1,241 @@
+use eframe::egui;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use std::path::PathBuf;
+use crate::data::Metric;
+use crate::export::{ExportFormat, ExportOptions};
+use crate::timezone::DisplayTimezone;
+use crate::units::UnitPreferences;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// What the user asked to export; the readings are fetched and written by the app.
+pub struct ExportRequest {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub path: PathBuf,
+    pub options: ExportOptions,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum RangeChoice {
+    Selected,
+    Custom,
+}
+
+/// Picks the range, fields, units and format of an export and asks where to save it.
+pub struct ExportWidget {
+    open: bool,
+    format: ExportFormat,
+    range: RangeChoice,
+    custom_from: String,
+    custom_to: String,
+    fields: [bool; Metric::ALL.len()],
+    display_units: bool,
+    timezone: DisplayTimezone,
+    running: bool,
+    status: Option<Result<String, String>>,
+}
+
+impl ExportWidget {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            format: ExportFormat::Csv,
+            range: RangeChoice::Selected,
+            custom_from: String::new(),
+            custom_to: String::new(),
+            fields: [true; Metric::ALL.len()],
+            display_units: true,
+            timezone: DisplayTimezone::Local,
+            running: false,
+            status: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Reports how an export returned by [`show`](Self::show) went.
+    pub fn finished(&mut self, result: anyhow::Result<(usize, PathBuf)>) {
+        self.running = false;
+        self.status = Some(match result {
+            Ok((count, path)) => Ok(format!("Wrote {} reading{} to {}", count, if count == 1 { "" } else { "s" }, path.display())),
+            Err(e) => Err(format!("{:#}", e)),
+        });
+    }
+
+    /// `latest` and `hours` describe the dashboard's selected time range, which ends at
+    /// the newest reading. Returns a request once the user has picked a file.
+    pub fn show(&mut self, ctx: &egui::Context, latest: Option<DateTime<Utc>>, hours: u32, units: &UnitPreferences) -> Option<ExportRequest> {
+        let mut open = self.open;
+        let mut request = None;
+        egui::Window::new("Export")
+            .open(&mut open)
+            .default_width(360.0)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.render_options(ui, latest, hours, units);
+                ui.separator();
+                request = self.render_footer(ui, latest, hours, units);
+            });
+        self.open = open;
+        request
+    }
+
+    fn render_options(&mut self, ui: &mut egui::Ui, latest: Option<DateTime<Utc>>, hours: u32, units: &UnitPreferences) {
+        let slate = egui::Color32::from_rgb(100, 116, 139);
+
+        egui::Grid::new("export_options").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+            ui.label("Format");
+            egui::ComboBox::from_id_source("export_format")
+                .selected_text(self.format.label())
+                .show_ui(ui, |ui| {
+                    for format in ExportFormat::ALL {
+                        ui.selectable_value(&mut self.format, format, format.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Range");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.range, RangeChoice::Selected, format!("Selected ({} h)", hours));
+                if ui.selectable_value(&mut self.range, RangeChoice::Custom, "Custom").clicked() && self.custom_to.is_empty() {
+                    let to = latest.unwrap_or_else(Utc::now);
+                    self.custom_from = self.wall_clock(to - Duration::hours(hours as i64));
+                    self.custom_to = self.wall_clock(to);
+                }
+            });
+            ui.end_row();
+            if self.range == RangeChoice::Custom {
+                ui.label("From");
+                ui.add(egui::TextEdit::singleline(&mut self.custom_from).hint_text(TIME_FORMAT));
+                ui.end_row();
+                ui.label("To");
+                ui.add(egui::TextEdit::singleline(&mut self.custom_to).hint_text(TIME_FORMAT));
+                ui.end_row();
+            }
+
+            ui.label("Units");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.display_units, true, "Dashboard");
+                ui.selectable_value(&mut self.display_units, false, "SI");
+            });
+            ui.end_row();
+
+            ui.label("Time zone");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.timezone, DisplayTimezone::Local, "Local");
+                ui.selectable_value(&mut self.timezone, DisplayTimezone::Utc, "UTC");
+            });
+            ui.end_row();
+        });
+        ui.add_space(8.0);
+
+        ui.label("Fields");
+        let units = if self.display_units { *units } else { UnitPreferences::default() };
+        egui::Grid::new("export_fields").num_columns(2).spacing([16.0, 4.0]).show(ui, |ui| {
+            for (i, metric) in Metric::ALL.into_iter().enumerate() {
+                let symbol = units.symbol(metric.dimension());
+                let label = if symbol.is_empty() { metric.key().to_string() } else { format!("{} ({})", metric.key(), symbol) };
+                ui.checkbox(&mut self.fields[i], label);
+                if i % 2 == 1 {
+                    ui.end_row();
+                }
+            }
+        });
+        ui.label(egui::RichText::new("Every export also has timestamp and source columns.").size(11.0).color(slate));
+    }
+
+    fn render_footer(&mut self, ui: &mut egui::Ui, latest: Option<DateTime<Utc>>, hours: u32, units: &UnitPreferences) -> Option<ExportRequest> {
+        let mut request = None;
+        ui.horizontal(|ui| {
+            let clicked = ui.add_enabled(!self.running, egui::Button::new(if self.running { "Exporting…" } else { "Export…" })).clicked();
+            if clicked {
+                match self.build_request(latest, hours, units) {
+                    Ok(Some(built)) => {
+                        self.running = true;
+                        self.status = None;
+                        request = Some(built);
+                    }
+                    Ok(None) => {}
+                    Err(message) => self.status = Some(Err(message)),
+                }
+            }
+        });
+        match &self.status {
+            Some(Ok(message)) => {
+                ui.label(egui::RichText::new(message).size(11.0).color(egui::Color32::from_rgb(34, 197, 94)));
+            }
+            Some(Err(message)) => {
+                ui.label(egui::RichText::new(message).size(11.0).color(egui::Color32::from_rgb(239, 68, 68)));
+            }
+            None => {}
+        }
+        request
+    }
+
+    // Ok(None) when the save dialog was cancelled.
+    fn build_request(&self, latest: Option<DateTime<Utc>>, hours: u32, units: &UnitPreferences) -> Result<Option<ExportRequest>, String> {
+        let fields: Vec<Metric> = Metric::ALL.into_iter()
+            .zip(self.fields)
+            .filter(|(_, selected)| *selected)
+            .map(|(metric, _)| metric)
+            .collect();
+        if fields.is_empty() {
+            return Err("Select at least one field".to_string());
+        }
+        let (from, to) = match self.range {
+            RangeChoice::Selected => {
+                let to = latest.unwrap_or_else(Utc::now);
+                (to - Duration::hours(hours as i64), to)
+            }
+            RangeChoice::Custom => (self.parse_time("From", &self.custom_from)?, self.parse_time("To", &self.custom_to)?),
+        };
+        if from >= to {
+            return Err("From must be before To".to_string());
+        }
+
+        let extension = self.format.extension();
+        let file_name = format!("weather-{}.{}", self.wall_clock(to).replace([' ', ':'], "-"), extension);
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(self.format.label(), &[extension])
+            .set_file_name(file_name)
+            .save_file()
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(ExportRequest {
+            from,
+            to,
+            path,
+            options: ExportOptions {
+                format: self.format,
+                fields,
+                units: if self.display_units { *units } else { UnitPreferences::default() },
+                timezone: self.timezone,
+            },
+        }))
+    }
+
+    fn parse_time(&self, name: &str, value: &str) -> Result<DateTime<Utc>, String> {
+        NaiveDateTime::parse_from_str(value.trim(), TIME_FORMAT)
+            .ok()
+            .and_then(|time| self.timezone.to_utc(time))
+            .ok_or_else(|| format!("{}: '{}' is not a time like 2024-05-01 12:00", name, value))
+    }
+
+    fn wall_clock(&self, time: DateTime<Utc>) -> String {
+        match self.timezone {
+            DisplayTimezone::Utc => time.format(TIME_FORMAT).to_string(),
+            _ => time.with_timezone(&chrono::Local).format(TIME_FORMAT).to_string(),
+        }
+    }
+}
+
+impl Default for ExportWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,207 @@
+use eframe::egui;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crate::timezone::DisplayTimezone;
+use crate::import::{ImportOptions, ImportPreset, ImportProgress, ImportReport};
+use crate::units::UnitPreferences;
+
+/// A file the user asked to import; it is read and stored by the app, which keeps
+/// `progress` up to date while reading.
+pub struct ImportRequest {
+    pub path: PathBuf,
+    pub options: ImportOptions,
+    pub progress: Arc<Mutex<ImportProgress>>,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum UnitsChoice {
+    Detect,
+    Metric,
+    Imperial,
+}
+
+/// Picks a file from another weather program and shows the import's progress and report.
+pub struct ImportWidget {
+    open: bool,
+    path: Option<PathBuf>,
+    preset: Option<ImportPreset>,
+    source: String,
+    timezone: DisplayTimezone,
+    units: UnitsChoice,
+    running: Option<Arc<Mutex<ImportProgress>>>,
+    result: Option<Result<ImportReport, String>>,
+}
+
+impl ImportWidget {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            path: None,
+            preset: None,
+            source: String::new(),
+            timezone: DisplayTimezone::Local,
+            units: UnitsChoice::Detect,
+            running: None,
+            result: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Reports how an import returned by [`show`](Self::show) went.
+    pub fn finished(&mut self, result: anyhow::Result<ImportReport>) {
+        self.running = None;
+        self.result = Some(result.map_err(|e| format!("{:#}", e)));
+    }
+
+    /// Returns a request once the user has chosen a file and pressed Import.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<ImportRequest> {
+        let mut open = self.open;
+        let mut request = None;
+        egui::Window::new("Import")
+            .open(&mut open)
+            .default_width(420.0)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.render_options(ui);
+                ui.separator();
+                request = self.render_footer(ui);
+            });
+        self.open = open;
+        request
+    }
+
+    fn render_options(&mut self, ui: &mut egui::Ui) {
+        let slate = egui::Color32::from_rgb(100, 116, 139);
+
+        egui::Grid::new("import_options").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+            ui.label("File");
+            ui.horizontal(|ui| {
+                if ui.button("Choose…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Weather data", &["csv", "txt", "log"])
+                        .add_filter("All files", &["*"])
+                        .pick_file()
+                    {
+                        self.path = Some(path);
+                        self.result = None;
+                    }
+                }
+                match &self.path {
+                    Some(path) => ui.label(path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default()).on_hover_text(path.display().to_string()),
+                    None => ui.label(egui::RichText::new("No file chosen").color(slate)),
+                };
+            });
+            ui.end_row();
+
+            ui.label("Format");
+            egui::ComboBox::from_id_source("import_preset")
+                .selected_text(self.preset.map_or("Detect", |preset| preset.label()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.preset, None, "Detect");
+                    for preset in ImportPreset::ALL {
+                        ui.selectable_value(&mut self.preset, Some(preset), preset.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Source name");
+            let hint = self.preset.map_or("from the file", |preset| preset.default_source());
+            ui.add(egui::TextEdit::singleline(&mut self.source).hint_text(hint));
+            ui.end_row();
+
+            ui.label("Times in file");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.timezone, DisplayTimezone::Local, "Local");
+                ui.selectable_value(&mut self.timezone, DisplayTimezone::Utc, "UTC");
+            });
+            ui.end_row();
+
+            ui.label("Units in file");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.units, UnitsChoice::Detect, "Detect");
+                ui.selectable_value(&mut self.units, UnitsChoice::Metric, "Metric");
+                ui.selectable_value(&mut self.units, UnitsChoice::Imperial, "Imperial");
+            });
+            ui.end_row();
+        });
+        ui.label(egui::RichText::new("Readings already stored for the same source and time are skipped.").size(11.0).color(slate));
+    }
+
+    fn render_footer(&mut self, ui: &mut egui::Ui) -> Option<ImportRequest> {
+        let mut request = None;
+
+        if let Some(progress) = &self.running {
+            let progress = progress.lock().map(|progress| *progress).unwrap_or_default();
+            let text = if progress.fraction() >= 1.0 {
+                format!("Storing {} rows…", progress.rows)
+            } else {
+                format!("Reading… {} rows", progress.rows)
+            };
+            ui.add(egui::ProgressBar::new(progress.fraction()).text(text));
+        } else if ui.add_enabled(self.path.is_some(), egui::Button::new("Import")).clicked() {
+            if let Some(path) = self.path.clone() {
+                let progress = Arc::new(Mutex::new(ImportProgress::default()));
+                self.running = Some(progress.clone());
+                self.result = None;
+                request = Some(ImportRequest {
+                    path,
+                    options: ImportOptions {
+                        preset: self.preset,
+                        source: Some(self.source.trim().to_string()).filter(|source| !source.is_empty()),
+                        timezone: self.timezone,
+                        units: match self.units {
+                            UnitsChoice::Detect => None,
+                            UnitsChoice::Metric => Some(UnitPreferences::metric()),
+                            UnitsChoice::Imperial => Some(UnitPreferences::imperial()),
+                        },
+                    },
+                    progress,
+                });
+            }
+        }
+
+        match &self.result {
+            Some(Ok(report)) => render_report(ui, report),
+            Some(Err(message)) => {
+                ui.label(egui::RichText::new(message).size(11.0).color(egui::Color32::from_rgb(239, 68, 68)));
+            }
+            None => {}
+        }
+        request
+    }
+}
+
+impl Default for ImportWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn render_report(ui: &mut egui::Ui, report: &ImportReport) {
+    let slate = egui::Color32::from_rgb(100, 116, 139);
+    let color = if report.failed == 0 { egui::Color32::from_rgb(34, 197, 94) } else { egui::Color32::from_rgb(245, 158, 11) };
+    ui.label(egui::RichText::new(format!("{} as {}", report.summary(), report.preset.label())).size(12.0).color(color));
+    if let (Some(first), Some(last)) = (report.first, report.last) {
+        ui.label(egui::RichText::new(format!(
+            "{} to {}",
+            first.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            last.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        )).size(11.0).color(slate));
+    }
+    for note in &report.units {
+        ui.label(egui::RichText::new(note).size(11.0).color(slate));
+    }
+    if !report.errors.is_empty() {
+        egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+            for error in &report.errors {
+                ui.label(egui::RichText::new(error).size(11.0).color(egui::Color32::from_rgb(239, 68, 68)));
+            }
+            if report.failed > report.errors.len() {
+                ui.label(egui::RichText::new(format!("… and {} more", report.failed - report.errors.len())).size(11.0).color(slate));
+            }
+        });
+    }
+}
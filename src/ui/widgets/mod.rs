@@ -1,3 +1,190 @@
+pub mod alerts;
+pub mod export;
+pub mod import;
+pub mod rain;
+pub mod settings;
 pub mod weather;
+pub mod wind;
 
-pub use weather::WeatherWidget;
\ No newline at end of file
+pub use alerts::AlertsWidget;
+pub use export::{ExportRequest, ExportWidget};
+pub use import::{ImportRequest, ImportWidget};
+pub use rain::RainWidget;
+pub use settings::SettingsWidget;
+pub use weather::WeatherWidget;
+pub use wind::WindWidget;
+
+use chrono::{DateTime, Local, Offset, TimeZone};
+use egui_plot::{GridInput, GridMark};
+use crate::data::WeatherData;
+
+// Grid steps in seconds that land on "round" clock times; each one divides the next.
+const TIME_STEPS: [f64; 10] = [
+    60.0, 300.0, 900.0, 1800.0, 3600.0,
+    3.0 * 3600.0, 6.0 * 3600.0, 12.0 * 3600.0, 86400.0, 7.0 * 86400.0,
+];
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum TimeRange {
+    LastHour,
+    Last6Hours,
+    Last24Hours,
+    Last7Days,
+}
+
+impl TimeRange {
+    pub const ALL: [TimeRange; 4] = [TimeRange::LastHour, TimeRange::Last6Hours, TimeRange::Last24Hours, TimeRange::Last7Days];
+
+    pub fn from_hours(hours: u32) -> Self {
+        Self::ALL.into_iter().find(|range| range.to_hours() == hours).unwrap_or(TimeRange::Last6Hours)
+    }
+
+    pub fn to_hours(&self) -> u32 {
+        match self {
+            TimeRange::LastHour => 1,
+            TimeRange::Last6Hours => 6,
+            TimeRange::Last24Hours => 24,
+            TimeRange::Last7Days => 168,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeRange::LastHour => "Last Hour",
+            TimeRange::Last6Hours => "Last 6 Hours",
+            TimeRange::Last24Hours => "Last 24 Hours",
+            TimeRange::Last7Days => "Last 7 Days",
+        }
+    }
+}
+
+/// Readings within the last `hours`, anchored on the newest reading rather than the
+/// wall clock so replayed recordings show up.
+pub fn filter_by_time_range(history: &[WeatherData], hours: u32) -> Vec<&WeatherData> {
+    let latest = history.last().map(|data| data.timestamp).unwrap_or_else(chrono::Utc::now);
+    let cutoff = latest - chrono::Duration::hours(hours as i64);
+    let start = history.partition_point(|data| data.timestamp <= cutoff);
+    history[start..].iter().collect()
+}
+
+pub fn to_local(secs: f64) -> Option<DateTime<Local>> {
+    Local.timestamp_millis_opt((secs * 1000.0) as i64).single()
+}
+
+/// Grid marks on round clock times in local time, with up to three line weights.
+pub fn time_grid_spacer(input: GridInput) -> Vec<GridMark> {
+    grid_marks(input, |secs| to_local(secs).map_or(0.0, |time| time.offset().fix().local_minus_utc() as f64))
+}
+
+// Marks on round wall-clock times, where `offset` is the zone's offset from UTC in seconds
+// at a time. The offset is looked up per mark so marks stay on the hour across a daylight
+// saving change; a wall-clock time skipped by the change gets no mark.
+fn grid_marks(input: GridInput, offset: impl Fn(f64) -> f64) -> Vec<GridMark> {
+    let (min, max) = input.bounds;
+    let Some(first) = TIME_STEPS.iter().position(|&step| step >= input.base_step_size) else {
+        return Vec::new();
+    };
+    let steps = &TIME_STEPS[first..(first + 3).min(TIME_STEPS.len())];
+    let smallest = steps[0];
+
+    let mut marks: Vec<GridMark> = Vec::new();
+    let mut current = offset(min);
+    let mut local = ((min + current) / smallest).ceil() * smallest;
+    loop {
+        let mut value = local - current;
+        let actual = offset(value);
+        if actual != current {
+            current = actual;
+            value = local - current;
+        }
+        if value > max {
+            break;
+        }
+        if value >= min && marks.last().is_none_or(|mark| value > mark.value) {
+            let step_size = steps.iter().rev()
+                .find(|&&step| (local / step).fract().abs() < 1e-9)
+                .copied()
+                .unwrap_or(smallest);
+            marks.push(GridMark { value, step_size });
+        }
+        local += smallest;
+    }
+    marks
+}
+
+pub fn format_axis_time(mark: GridMark, hours: u32) -> String {
+    let Some(time) = to_local(mark.value) else {
+        return String::new();
+    };
+    if mark.step_size >= 86400.0 {
+        time.format("%a %d %b").to_string()
+    } else if hours > 24 {
+        time.format("%a %H:%M").to_string()
+    } else {
+        time.format("%H:%M").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn reading(secs: i64) -> WeatherData {
+        WeatherData::new(15.0, 50.0, 1013.0, 2.0, 180.0, 0.0, 1.0, 100.0)
+            .with_timestamp(Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap())
+    }
+
+    // 2024-03-31 00:00 UTC, an hour before Central Europe moves from +01:00 to +02:00.
+    const SWITCH_DAY: f64 = 1_711_843_200.0;
+
+    fn marks(bounds: (f64, f64), base_step_size: f64, offset: impl Fn(f64) -> f64) -> Vec<(f64, f64)> {
+        grid_marks(GridInput { bounds, base_step_size }, offset).into_iter().map(|mark| (mark.value, mark.step_size)).collect()
+    }
+
+    #[test]
+    fn time_range_keeps_the_newest_readings_within_the_hours() {
+        let history: Vec<WeatherData> = (0..=4).map(|i| reading(i * 1800)).collect();
+        let within = filter_by_time_range(&history, 1);
+        // Anchored on the newest reading; one exactly an hour older is left out.
+        assert_eq!(within.iter().map(|data| data.timestamp).collect::<Vec<_>>(), [history[3].timestamp, history[4].timestamp]);
+        assert_eq!(filter_by_time_range(&history, 24).len(), 5);
+        assert!(filter_by_time_range(&[], 6).is_empty());
+    }
+
+    #[test]
+    fn grid_marks_land_on_round_times_with_heavier_lines_on_larger_steps() {
+        let start = SWITCH_DAY - 86400.0;
+        let utc = marks((start - 100.0, start + 3.0 * 3600.0), 600.0, |_| 0.0);
+        assert_eq!(utc.len(), 13);
+        assert_eq!(utc[0], (start, 3600.0));
+        assert_eq!(utc[1], (start + 900.0, 900.0));
+        assert_eq!(utc[2], (start + 1800.0, 1800.0));
+
+        // In +05:30, round local hours fall on the half hour in UTC.
+        let india = marks((start, start + 3.0 * 3600.0), 3000.0, |_| 19800.0);
+        assert_eq!(india.iter().map(|mark| mark.0 - start).collect::<Vec<_>>(), [1800.0, 5400.0, 9000.0]);
+        assert!(marks((start, start + 3600.0), 30.0 * 86400.0, |_| 0.0).is_empty());
+    }
+
+    #[test]
+    fn grid_marks_stay_on_the_hour_across_daylight_saving() {
+        let cet = |secs: f64| if secs < SWITCH_DAY + 3600.0 { 3600.0 } else { 7200.0 };
+        let hours = marks((SWITCH_DAY - 100.0, SWITCH_DAY + 4.0 * 3600.0), 3600.0, cet);
+        // Local 01:00, then 03:00 as 02:00 doesn't exist, 04:00, 05:00 and 06:00.
+        assert_eq!(hours.iter().map(|mark| mark.0 - SWITCH_DAY).collect::<Vec<_>>(), [0.0, 3600.0, 7200.0, 10800.0, 14400.0]);
+        for (value, _) in hours {
+            assert_eq!((value + cet(value)) % 3600.0, 0.0);
+        }
+    }
+
+    #[test]
+    fn axis_labels_show_dates_for_day_steps_and_weekdays_for_long_ranges() {
+        let value = SWITCH_DAY + 12.0 * 3600.0;
+        let local = to_local(value).unwrap();
+        assert_eq!(format_axis_time(GridMark { value, step_size: 3600.0 }, 6), local.format("%H:%M").to_string());
+        assert_eq!(format_axis_time(GridMark { value, step_size: 3600.0 }, 168), local.format("%a %H:%M").to_string());
+        assert_eq!(format_axis_time(GridMark { value, step_size: 86400.0 }, 168), local.format("%a %d %b").to_string());
+        assert_eq!(format_axis_time(GridMark { value: f64::MAX, step_size: 3600.0 }, 6), "");
+    }
+}
@@ -0,0 +1,127 @@
+use eframe::egui;
+use egui_plot::{Bar, BarChart, Line, Plot, PlotPoints};
+use crate::rain::RainReport;
+use crate::units::{Dimension, UnitPreferences};
+use super::{format_axis_time, time_grid_spacer, to_local};
+
+const RAIN_COLOR: egui::Color32 = egui::Color32::from_rgb(168, 85, 247);
+const CUMULATIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(59, 130, 246);
+
+pub struct RainWidget;
+
+impl RainWidget {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn render(&self, ui: &mut egui::Ui, rain: &RainReport, hours: u32, units: &UnitPreferences) {
+        let totals = &rain.totals;
+        ui.horizontal_wrapped(|ui| {
+            self.render_total(ui, "Last Hour", totals.last_hour_mm, units);
+            self.render_total(ui, "Today", totals.today_mm, units);
+            self.render_total(ui, "Last 24h", totals.last_24h_mm, units);
+            self.render_total(ui, "This Month", totals.month_mm, units);
+            self.render_total(ui, "This Year", totals.year_mm, units);
+        });
+        ui.add_space(6.0);
+
+        let slate = egui::Color32::from_rgb(100, 116, 139);
+        match &totals.storm {
+            Some(storm) => {
+                let started = storm.start.with_timezone(&chrono::Local).format("%a %d %b %H:%M");
+                let text = if storm.ongoing {
+                    format!("Storm in progress since {}: {}", started, units.format(Dimension::Rain, storm.total_mm))
+                } else {
+                    format!("Last storm {} to {}: {}", started, storm.end.with_timezone(&chrono::Local).format("%a %d %b %H:%M"), units.format(Dimension::Rain, storm.total_mm))
+                };
+                ui.label(egui::RichText::new(text).size(12.0).color(if storm.ongoing { RAIN_COLOR } else { slate }));
+            }
+            None => {
+                ui.label(egui::RichText::new("No storms recorded").size(12.0).color(slate));
+            }
+        }
+        ui.add_space(8.0);
+
+        if rain.buckets.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label(egui::RichText::new("No rain data available").color(slate));
+            });
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!("Rainfall ({})", units.symbol(Dimension::Rain))).size(14.0).strong().color(egui::Color32::from_rgb(15, 23, 42)));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(egui::RichText::new("— cumulative").size(10.0).color(CUMULATIVE_COLOR));
+                ui.label(egui::RichText::new(format!("■ per {}", bucket_label(rain.bucket_secs))).size(10.0).color(RAIN_COLOR));
+            });
+        });
+
+        // Bars sit in the middle of their bucket; the cumulative line steps up at each bucket end.
+        let units = *units;
+        let half = rain.bucket_secs as f64 / 2.0;
+        let bars: Vec<Bar> = rain.buckets.iter()
+            .map(|(start, amount)| Bar::new(start.timestamp() as f64 + half, units.convert(Dimension::Rain, *amount) as f64).width(rain.bucket_secs as f64 * 0.85))
+            .collect();
+        let mut total = 0.0;
+        let mut cumulative = Vec::with_capacity(rain.buckets.len() + 1);
+        if let Some((first, _)) = rain.buckets.first() {
+            cumulative.push([first.timestamp() as f64, 0.0]);
+        }
+        for (start, amount) in &rain.buckets {
+            total += units.convert(Dimension::Rain, *amount) as f64;
+            cumulative.push([start.timestamp() as f64 + 2.0 * half, total]);
+        }
+
+        Plot::new("rain_plot")
+            .height(ui.available_height() - 20.0)
+            .show_axes([true, true])
+            .show_grid(true)
+            .include_y(0.0)
+            .x_grid_spacer(time_grid_spacer)
+            .x_axis_formatter(move |mark, _range| format_axis_time(mark, hours))
+            .label_formatter(move |name, point| {
+                let time = to_local(point.x)
+                    .map(|time| time.format(if hours > 24 { "%a %d %b %H:%M" } else { "%H:%M" }).to_string())
+                    .unwrap_or_default();
+                let amount = units.format_value(Dimension::Rain, point.y as f32);
+                if name.is_empty() {
+                    format!("{}\n{}", time, amount)
+                } else {
+                    format!("{}\n{}: {}", time, name, amount)
+                }
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars).name("Rain").color(RAIN_COLOR));
+                plot_ui.line(Line::new(PlotPoints::from(cumulative)).name("Cumulative").color(CUMULATIVE_COLOR).width(2.0));
+            });
+    }
+
+    fn render_total(&self, ui: &mut egui::Ui, label: &str, amount: f32, units: &UnitPreferences) {
+        egui::Frame::default()
+            .fill(egui::Color32::WHITE)
+            .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
+            .rounding(8.0)
+            .inner_margin(egui::Margin::symmetric(12.0, 8.0))
+            .show(ui, |ui| {
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new(label).size(11.0).color(egui::Color32::from_rgb(100, 116, 139)));
+                    ui.label(egui::RichText::new(units.format(Dimension::Rain, amount)).size(16.0).strong().color(egui::Color32::from_rgb(15, 23, 42)));
+                });
+            });
+    }
+}
+
+impl Default for RainWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn bucket_label(bucket_secs: i64) -> String {
+    match bucket_secs {
+        secs if secs % 3600 == 0 && secs > 3600 => format!("{} hours", secs / 3600),
+        3600 => "hour".to_string(),
+        secs => format!("{} min", secs / 60),
+    }
+}
@@ -0,0 +1,227 @@
+use eframe::egui;
+use std::path::PathBuf;
+use crate::config::Config;
+use crate::sources::SourceConfig;
+use crate::units::{PressureUnit, RainUnit, SpeedUnit, TemperatureUnit, UnitPreferences};
+use super::TimeRange;
+
+/// Edits the config file on disk. Command-line overrides are not part of the draft, so
+/// saving never writes them back.
+pub struct SettingsWidget {
+    path: PathBuf,
+    // The file as the station was started with, to tell which saved changes wait for a restart.
+    started: Config,
+    saved: Config,
+    draft: Config,
+    open: bool,
+    status: Option<Result<String, String>>,
+}
+
+impl SettingsWidget {
+    pub fn new(path: PathBuf, config: Config) -> Self {
+        Self {
+            path,
+            draft: config.clone(),
+            started: config.clone(),
+            saved: config,
+            open: false,
+            status: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Returns the config once it has been written to disk.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Config> {
+        let mut open = self.open;
+        let mut saved = None;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .default_width(420.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().max_height(520.0).show(ui, |ui| {
+                    self.render_sections(ui);
+                });
+                ui.separator();
+                saved = self.render_footer(ui);
+            });
+        self.open = open;
+        saved
+    }
+
+    fn render_sections(&mut self, ui: &mut egui::Ui) {
+        let slate = egui::Color32::from_rgb(100, 116, 139);
+        let draft = &mut self.draft;
+
+        ui.heading("Storage");
+        egui::Grid::new("settings_storage").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+            ui.label("Database");
+            ui.text_edit_singleline(&mut draft.storage.database);
+            ui.end_row();
+        });
+        ui.add_space(8.0);
+
+        ui.heading("Retention");
+        egui::Grid::new("settings_retention").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+            ui.label("Readings kept in memory");
+            ui.add(egui::DragValue::new(&mut draft.retention.history_size).range(1..=1_000_000));
+            ui.end_row();
+            for tier in draft.retention.tiers.iter_mut() {
+                ui.label(format!("{} s rollups kept for", tier.resolution_secs));
+                ui.add(egui::DragValue::new(&mut tier.retention_hours).range(1..=24 * 3660).suffix(" h"));
+                ui.end_row();
+            }
+        });
+        ui.add_space(8.0);
+
+        ui.heading("Rain");
+        egui::Grid::new("settings_rain").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+            ui.label("Dry spell ending a storm");
+            ui.add(egui::DragValue::new(&mut draft.rain.storm_gap_hours).range(1..=240).suffix(" h"));
+            ui.end_row();
+        });
+        ui.add_space(8.0);
+
+        ui.heading("Dashboard");
+        egui::Grid::new("settings_ui").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+            ui.label("Default time range");
+            let mut range = TimeRange::from_hours(draft.ui.time_range_hours);
+            egui::ComboBox::from_id_source("settings_time_range")
+                .selected_text(range.label())
+                .show_ui(ui, |ui| {
+                    for option in TimeRange::ALL {
+                        ui.selectable_value(&mut range, option, option.label());
+                    }
+                });
+            draft.ui.time_range_hours = range.to_hours();
+            ui.end_row();
+            ui.label("Window size");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut draft.ui.window_width).range(400.0..=7680.0));
+                ui.label("×");
+                ui.add(egui::DragValue::new(&mut draft.ui.window_height).range(300.0..=4320.0));
+            });
+            ui.end_row();
+        });
+        ui.add_space(8.0);
+
+        ui.heading("Units");
+        ui.horizontal(|ui| {
+            if ui.button("Metric").clicked() {
+                draft.units = UnitPreferences::metric();
+            }
+            if ui.button("Imperial").clicked() {
+                draft.units = UnitPreferences::imperial();
+            }
+        });
+        egui::Grid::new("settings_units").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+            ui.label("Temperature");
+            unit_combo(ui, "settings_unit_temperature", &mut draft.units.temperature, &TemperatureUnit::ALL, TemperatureUnit::symbol);
+            ui.end_row();
+            ui.label("Wind speed");
+            unit_combo(ui, "settings_unit_wind_speed", &mut draft.units.wind_speed, &SpeedUnit::ALL, SpeedUnit::symbol);
+            ui.end_row();
+            ui.label("Pressure");
+            unit_combo(ui, "settings_unit_pressure", &mut draft.units.pressure, &PressureUnit::ALL, PressureUnit::symbol);
+            ui.end_row();
+            ui.label("Rain");
+            unit_combo(ui, "settings_unit_rain", &mut draft.units.rain, &RainUnit::ALL, RainUnit::symbol);
+            ui.end_row();
+        });
+        ui.label(egui::RichText::new("Readings are always stored in °C, m/s, hPa and mm.").size(11.0).color(slate));
+        ui.add_space(8.0);
+
+        ui.heading("Sources");
+        let mut others = 0;
+        for source in draft.sources.iter_mut() {
+            let SourceConfig::Simulator(simulator) = source else {
+                others += 1;
+                continue;
+            };
+            egui::Grid::new(format!("settings_simulator_{}", simulator.name)).num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+                ui.label(egui::RichText::new(&simulator.name).strong());
+                ui.end_row();
+                ui.label("Interval");
+                ui.add(egui::DragValue::new(&mut simulator.interval_secs).range(1..=3600).suffix(" s"));
+                ui.end_row();
+                ui.label("Latitude");
+                ui.add(egui::DragValue::new(&mut simulator.latitude).range(-90.0..=90.0).speed(0.1).suffix("°"));
+                ui.end_row();
+                ui.label("Longitude");
+                ui.add(egui::DragValue::new(&mut simulator.longitude).range(-180.0..=180.0).speed(0.1).suffix("°"));
+                ui.end_row();
+                ui.label("Mean temperature");
+                ui.add(egui::DragValue::new(&mut simulator.base_temperature).speed(0.1).suffix(" °C"));
+                ui.end_row();
+                ui.label("Diurnal range");
+                ui.add(egui::DragValue::new(&mut simulator.diurnal_range).range(0.0..=30.0).speed(0.1).suffix(" °C"));
+                ui.end_row();
+                ui.label("Mean humidity");
+                ui.add(egui::DragValue::new(&mut simulator.base_humidity).range(0.0..=100.0).suffix(" %"));
+                ui.end_row();
+                ui.label("Mean pressure");
+                ui.add(egui::DragValue::new(&mut simulator.base_pressure).speed(0.1).suffix(" hPa"));
+                ui.end_row();
+            });
+        }
+        if others > 0 {
+            ui.label(egui::RichText::new(format!("{} other source{} can be edited in {}", others, if others == 1 { "" } else { "s" }, self.path.display())).size(11.0).color(slate));
+        }
+        ui.add_space(8.0);
+
+        ui.heading("Alerts");
+        ui.label(egui::RichText::new(format!(
+            "{} rule{} and {} notification channel{}, edited in {}",
+            draft.alerts.len(), if draft.alerts.len() == 1 { "" } else { "s" },
+            draft.notifications.channels.len(), if draft.notifications.channels.len() == 1 { "" } else { "s" },
+            self.path.display()
+        )).size(11.0).color(slate));
+    }
+
+    fn render_footer(&mut self, ui: &mut egui::Ui) -> Option<Config> {
+        let mut saved = None;
+        ui.label(egui::RichText::new("Rain and unit changes apply at once; storage, retention, window size, sources, alerts, notifications and the API after a restart.").size(11.0).color(egui::Color32::from_rgb(100, 116, 139)));
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                match self.draft.save(&self.path) {
+                    Ok(()) => {
+                        self.saved = self.draft.clone();
+                        self.status = Some(Ok(match self.draft.restart_needed(&self.started).as_slice() {
+                            [] => format!("Saved to {}", self.path.display()),
+                            pending => format!("Saved to {}; restart to apply the {} changes", self.path.display(), pending.join(", ")),
+                        }));
+                        saved = Some(self.draft.clone());
+                    }
+                    Err(e) => self.status = Some(Err(format!("{:#}", e))),
+                }
+            }
+            if ui.button("Revert").clicked() {
+                self.draft = self.saved.clone();
+                self.status = None;
+            }
+        });
+        match &self.status {
+            Some(Ok(message)) => {
+                ui.label(egui::RichText::new(message).size(11.0).color(egui::Color32::from_rgb(34, 197, 94)));
+            }
+            Some(Err(message)) => {
+                ui.label(egui::RichText::new(message).size(11.0).color(egui::Color32::from_rgb(239, 68, 68)));
+            }
+            None => {}
+        }
+        saved
+    }
+}
+
+fn unit_combo<T: Copy + PartialEq>(ui: &mut egui::Ui, id: &str, value: &mut T, options: &[T], symbol: fn(&T) -> &'static str) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(symbol(value))
+        .show_ui(ui, |ui| {
+            for option in options {
+                ui.selectable_value(value, *option, symbol(option));
+            }
+        });
+}
@@ -1,110 +1,176 @@
 use eframe::egui;
-use egui_plot::{Line, Plot, PlotPoints};
-use crate::data::WeatherData;
+use egui_plot::{Line, Plot, PlotPoint, PlotPoints, Points};
+use crate::data::{Metric, WeatherData};
+use crate::rollup::Aggregate;
+use crate::units::{Dimension, UnitPreferences};
+use super::{format_axis_time, time_grid_spacer, to_local};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChartSeries {
+    Temperature,
+    Humidity,
+    Pressure,
+    WindSpeed,
+    DewPoint,
+    FeelsLike,
+    ApparentTemperature,
+    HeatIndex,
+    WindChill,
+    AbsoluteHumidity,
+}
+
+impl ChartSeries {
+    const PRIMARY: [ChartSeries; 4] = [
+        ChartSeries::Temperature,
+        ChartSeries::Humidity,
+        ChartSeries::Pressure,
+        ChartSeries::WindSpeed,
+    ];
+
+    const DERIVED: [ChartSeries; 6] = [
+        ChartSeries::DewPoint,
+        ChartSeries::FeelsLike,
+        ChartSeries::ApparentTemperature,
+        ChartSeries::HeatIndex,
+        ChartSeries::WindChill,
+        ChartSeries::AbsoluteHumidity,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            ChartSeries::Temperature => "Temperature",
+            ChartSeries::Humidity => "Humidity",
+            ChartSeries::Pressure => "Pressure",
+            ChartSeries::WindSpeed => "Wind Speed",
+            ChartSeries::DewPoint => "Dew Point",
+            ChartSeries::FeelsLike => "Feels Like",
+            ChartSeries::ApparentTemperature => "Apparent Temperature",
+            ChartSeries::HeatIndex => "Heat Index",
+            ChartSeries::WindChill => "Wind Chill",
+            ChartSeries::AbsoluteHumidity => "Absolute Humidity",
+        }
+    }
+
+    fn short_label(&self) -> &'static str {
+        match self {
+            ChartSeries::Temperature => "Temp",
+            ChartSeries::WindSpeed => "Wind",
+            other => other.label(),
+        }
+    }
+
+    fn dimension(&self) -> Dimension {
+        match self {
+            ChartSeries::AbsoluteHumidity => Dimension::Fixed("g/m³"),
+            raw => raw.metric().map(Metric::dimension).unwrap_or(Dimension::Temperature),
+        }
+    }
+
+    fn color(&self) -> egui::Color32 {
+        match self {
+            ChartSeries::Temperature => egui::Color32::from_rgb(239, 68, 68),
+            ChartSeries::Humidity => egui::Color32::from_rgb(59, 130, 246),
+            ChartSeries::Pressure => egui::Color32::from_rgb(34, 197, 94),
+            ChartSeries::WindSpeed => egui::Color32::from_rgb(245, 158, 11),
+            ChartSeries::DewPoint => egui::Color32::from_rgb(20, 184, 166),
+            ChartSeries::FeelsLike => egui::Color32::from_rgb(244, 63, 94),
+            ChartSeries::ApparentTemperature => egui::Color32::from_rgb(236, 72, 153),
+            ChartSeries::HeatIndex => egui::Color32::from_rgb(234, 88, 12),
+            ChartSeries::WindChill => egui::Color32::from_rgb(14, 165, 233),
+            ChartSeries::AbsoluteHumidity => egui::Color32::from_rgb(6, 182, 212),
+        }
+    }
+
+    // Raw fields have exact range statistics in the summary; derived series are computed from the plotted points.
+    fn metric(&self) -> Option<Metric> {
+        match self {
+            ChartSeries::Temperature => Some(Metric::Temperature),
+            ChartSeries::Humidity => Some(Metric::Humidity),
+            ChartSeries::Pressure => Some(Metric::Pressure),
+            ChartSeries::WindSpeed => Some(Metric::WindSpeed),
+            _ => None,
+        }
+    }
+
+    fn value(&self, data: &WeatherData) -> f64 {
+        let value = match self {
+            ChartSeries::DewPoint => data.dew_point(),
+            ChartSeries::FeelsLike => data.feels_like(),
+            ChartSeries::ApparentTemperature => data.apparent_temperature(),
+            ChartSeries::HeatIndex => data.heat_index(),
+            ChartSeries::WindChill => data.wind_chill(),
+            ChartSeries::AbsoluteHumidity => data.absolute_humidity(),
+            raw => raw.metric().map(|metric| metric.value(data)).unwrap_or_default(),
+        };
+        value as f64
+    }
+}
 
 pub struct WeatherWidget {
-    show_temperature_chart: bool,
-    show_humidity_chart: bool,
-    show_pressure_chart: bool,
-    show_wind_chart: bool,
+    selected_chart: ChartSeries,
     show_all_charts: bool,
+    units: UnitPreferences,
 }
 
 impl WeatherWidget {
     pub fn new() -> Self {
         Self {
-            show_temperature_chart: true,
-            show_humidity_chart: true,
-            show_pressure_chart: false,
-            show_wind_chart: false,
+            selected_chart: ChartSeries::Temperature,
             show_all_charts: false,
+            units: UnitPreferences::default(),
         }
     }
 
-    pub fn render(&mut self, ui: &mut egui::Ui, _current: Option<&WeatherData>, history: &[WeatherData], time_range_hours: u32) {
-        if history.is_empty() {
+    pub fn render(&mut self, ui: &mut egui::Ui, _current: Option<&WeatherData>, filtered_history: &[&WeatherData], summary: Option<&Aggregate>, time_range_hours: u32, units: &UnitPreferences) {
+        self.units = *units;
+        if filtered_history.is_empty() {
             ui.centered_and_justified(|ui| {
                 ui.label(egui::RichText::new("No weather data available").color(egui::Color32::from_rgb(100, 116, 139)));
             });
             return;
         }
 
-        let filtered_history = self.filter_by_time_range(history, time_range_hours);
-        
         // Compact chart controls
         self.render_compact_chart_controls(ui);
         ui.add_space(8.0);
         
         // Main chart area - single chart view only for space efficiency
         if self.show_all_charts {
-            self.render_compact_grid(ui, &filtered_history);
+            self.render_compact_grid(ui, filtered_history, time_range_hours);
         } else {
-            self.render_single_selected_chart(ui, &filtered_history);
+            self.render_single_selected_chart(ui, filtered_history, summary, time_range_hours);
         }
     }
 
-    fn filter_by_time_range<'a>(&self, history: &'a [WeatherData], hours: u32) -> Vec<&'a WeatherData> {
-        let cutoff = chrono::Utc::now() - chrono::Duration::hours(hours as i64);
-        history.iter().filter(|data| data.timestamp > cutoff).collect()
-    }
-
     fn render_compact_chart_controls(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label(egui::RichText::new("Charts:").size(14.0).color(egui::Color32::from_rgb(71, 85, 105)));
             ui.add_space(8.0);
             
             // Toggle buttons for charts with colored backgrounds
-            let temp_selected = self.show_temperature_chart && !self.show_all_charts;
-            if ui.add(egui::Button::new("Temperature")
-                .fill(if temp_selected { egui::Color32::from_rgb(239, 68, 68) } else { egui::Color32::from_rgb(241, 245, 249) })
-                .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
-                .rounding(4.0)
-            ).clicked() {
-                self.show_all_charts = false;
-                self.show_temperature_chart = true;
-                self.show_humidity_chart = false;
-                self.show_pressure_chart = false;
-                self.show_wind_chart = false;
-            }
-            
-            let humid_selected = self.show_humidity_chart && !self.show_all_charts;
-            if ui.add(egui::Button::new("Humidity")
-                .fill(if humid_selected { egui::Color32::from_rgb(59, 130, 246) } else { egui::Color32::from_rgb(241, 245, 249) })
-                .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
-                .rounding(4.0)
-            ).clicked() {
-                self.show_all_charts = false;
-                self.show_temperature_chart = false;
-                self.show_humidity_chart = true;
-                self.show_pressure_chart = false;
-                self.show_wind_chart = false;
-            }
-            
-            let press_selected = self.show_pressure_chart && !self.show_all_charts;
-            if ui.add(egui::Button::new("Pressure")
-                .fill(if press_selected { egui::Color32::from_rgb(34, 197, 94) } else { egui::Color32::from_rgb(241, 245, 249) })
-                .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
-                .rounding(4.0)
-            ).clicked() {
-                self.show_all_charts = false;
-                self.show_temperature_chart = false;
-                self.show_humidity_chart = false;
-                self.show_pressure_chart = true;
-                self.show_wind_chart = false;
-            }
-            
-            let wind_selected = self.show_wind_chart && !self.show_all_charts;
-            if ui.add(egui::Button::new("Wind")
-                .fill(if wind_selected { egui::Color32::from_rgb(245, 158, 11) } else { egui::Color32::from_rgb(241, 245, 249) })
-                .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
-                .rounding(4.0)
-            ).clicked() {
-                self.show_all_charts = false;
-                self.show_temperature_chart = false;
-                self.show_humidity_chart = false;
-                self.show_pressure_chart = false;
-                self.show_wind_chart = true;
+            for series in ChartSeries::PRIMARY {
+                let selected = self.selected_chart == series && !self.show_all_charts;
+                if ui.add(egui::Button::new(series.short_label())
+                    .fill(if selected { series.color() } else { egui::Color32::from_rgb(241, 245, 249) })
+                    .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
+                    .rounding(4.0)
+                ).clicked() {
+                    self.show_all_charts = false;
+                    self.selected_chart = series;
+                }
             }
+
+            let derived_selected = ChartSeries::DERIVED.contains(&self.selected_chart);
+            egui::ComboBox::from_id_source("derived_series")
+                .selected_text(if derived_selected { self.selected_chart.label() } else { "Derived..." })
+                .width(150.0)
+                .show_ui(ui, |ui| {
+                    for series in ChartSeries::DERIVED {
+                        if ui.selectable_value(&mut self.selected_chart, series, series.label()).clicked() {
+                            self.show_all_charts = false;
+                        }
+                    }
+                });
             
             ui.separator();
             
@@ -118,30 +184,21 @@ impl WeatherWidget {
         });
     }
 
-    fn render_single_selected_chart(&self, ui: &mut egui::Ui, history: &[&WeatherData]) {
+    fn render_single_selected_chart(&self, ui: &mut egui::Ui, history: &[&WeatherData], summary: Option<&Aggregate>, hours: u32) {
         let chart_height = ui.available_height() - 20.0;
-        
-        if self.show_temperature_chart {
-            self.render_chart_with_stats(ui, history, "Temperature", "°C", |d| d.temperature as f64, egui::Color32::from_rgb(239, 68, 68), chart_height);
-        } else if self.show_humidity_chart {
-            self.render_chart_with_stats(ui, history, "Humidity", "%", |d| d.humidity as f64, egui::Color32::from_rgb(59, 130, 246), chart_height);
-        } else if self.show_pressure_chart {
-            self.render_chart_with_stats(ui, history, "Pressure", "hPa", |d| d.pressure as f64, egui::Color32::from_rgb(34, 197, 94), chart_height);
-        } else if self.show_wind_chart {
-            self.render_chart_with_stats(ui, history, "Wind Speed", "m/s", |d| d.wind_speed as f64, egui::Color32::from_rgb(245, 158, 11), chart_height);
-        }
+        self.render_chart_with_stats(ui, history, summary, self.selected_chart, hours, chart_height);
     }
 
-    fn render_compact_grid(&self, ui: &mut egui::Ui, history: &[&WeatherData]) {
+    fn render_compact_grid(&self, ui: &mut egui::Ui, history: &[&WeatherData], hours: u32) {
         let chart_height = (ui.available_height() - 20.0) / 2.0 - 10.0;
         
         ui.columns(2, |columns| {
             columns[0].group(|ui| {
-                self.render_mini_chart(ui, history, "Temp", "°C", |d| d.temperature as f64, egui::Color32::from_rgb(239, 68, 68), chart_height);
+                self.render_mini_chart(ui, history, ChartSeries::Temperature, hours, chart_height);
             });
             
             columns[1].group(|ui| {
-                self.render_mini_chart(ui, history, "Humidity", "%", |d| d.humidity as f64, egui::Color32::from_rgb(59, 130, 246), chart_height);
+                self.render_mini_chart(ui, history, ChartSeries::Humidity, hours, chart_height);
             });
         });
 
@@ -149,96 +206,240 @@ impl WeatherWidget {
 
         ui.columns(2, |columns| {
             columns[0].group(|ui| {
-                self.render_mini_chart(ui, history, "Pressure", "hPa", |d| d.pressure as f64, egui::Color32::from_rgb(34, 197, 94), chart_height);
+                self.render_mini_chart(ui, history, ChartSeries::Pressure, hours, chart_height);
             });
             
             columns[1].group(|ui| {
-                self.render_mini_chart(ui, history, "Wind", "m/s", |d| d.wind_speed as f64, egui::Color32::from_rgb(245, 158, 11), chart_height);
+                self.render_mini_chart(ui, history, ChartSeries::WindSpeed, hours, chart_height);
             });
         });
     }
 
-    fn render_chart_with_stats<F>(&self, ui: &mut egui::Ui, history: &[&WeatherData], title: &str, unit: &str, extractor: F, color: egui::Color32, height: f32)
-    where
-        F: Fn(&WeatherData) -> f64,
-    {
+    fn render_chart_with_stats(&self, ui: &mut egui::Ui, history: &[&WeatherData], summary: Option<&Aggregate>, series: ChartSeries, hours: u32, height: f32) {
         if history.is_empty() {
             return;
         }
 
-        // Stats bar
+        let units = &self.units;
+        let dimension = series.dimension();
+        let extractor = |d: &WeatherData| units.convert(dimension, series.value(d) as f32) as f64;
+        let (title, unit, color) = (series.label(), units.symbol(dimension), series.color());
+        let precision = units.precision(dimension);
+
+        // Stats bar - prefer the range summary, which keeps true extremes when the chart shows rollup means
         ui.horizontal(|ui| {
-            let values: Vec<f32> = history.iter().map(|d| extractor(d) as f32).collect();
-            let min = values.iter().fold(f32::INFINITY, |a, &b| a.min(b));
-            let max = values.iter().fold(f32::NEG_INFINITY, |a, &b| a.max(b));
-            let avg = values.iter().sum::<f32>() / values.len() as f32;
-            let latest = values.last().copied().unwrap_or(0.0);
+            let (min, max, avg, latest) = match summary.zip(series.metric()) {
+                Some((summary, metric)) => {
+                    let stats = summary.stats(metric);
+                    let convert = |value: f32| units.convert(dimension, value);
+                    (convert(stats.min), convert(stats.max), convert(summary.mean(metric)), convert(stats.last))
+                }
+                None => {
+                    let values: Vec<f32> = history.iter().map(|d| extractor(d) as f32).filter(|value| !value.is_nan()).collect();
+                    let min = values.iter().fold(f32::NAN, |a, &b| a.min(b));
+                    let max = values.iter().fold(f32::NAN, |a, &b| a.max(b));
+                    let avg = values.iter().sum::<f32>() / values.len() as f32;
+                    let latest = values.last().copied().unwrap_or(f32::NAN);
+                    (min, max, avg, latest)
+                }
+            };
 
             ui.label(egui::RichText::new(format!("{} ({})", title, unit)).size(14.0).strong().color(egui::Color32::from_rgb(15, 23, 42)));
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                ui.label(egui::RichText::new(format!("Max: {:.1}", max)).size(10.0).color(egui::Color32::from_rgb(220, 38, 127)));
-                ui.label(egui::RichText::new(format!("Avg: {:.1}", avg)).size(10.0).color(egui::Color32::from_rgb(100, 116, 139)));
-                ui.label(egui::RichText::new(format!("Min: {:.1}", min)).size(10.0).color(egui::Color32::from_rgb(59, 130, 246)));
-                ui.label(egui::RichText::new(format!("Now: {:.1}", latest)).size(11.0).strong().color(color));
+                let number = |value: f32| if value.is_nan() { "—".to_string() } else { format!("{:.*}", precision, value) };
+                ui.label(egui::RichText::new(format!("Max: {}", number(max))).size(10.0).color(egui::Color32::from_rgb(220, 38, 127)));
+                ui.label(egui::RichText::new(format!("Avg: {}", number(avg))).size(10.0).color(egui::Color32::from_rgb(100, 116, 139)));
+                ui.label(egui::RichText::new(format!("Min: {}", number(min))).size(10.0).color(egui::Color32::from_rgb(59, 130, 246)));
+                ui.label(egui::RichText::new(format!("Now: {}", number(latest))).size(11.0).strong().color(color));
             });
         });
 
         ui.add_space(4.0);
 
         // Chart
-        let points: PlotPoints = history
-            .iter()
-            .enumerate()
-            .map(|(i, data)| [i as f64, extractor(data)])
-            .collect();
-
-        let line = Line::new(points)
-            .color(color)
-            .width(2.0);
+        let segments = split_at_gaps(history, extractor);
 
         Plot::new(format!("plot_{}", title))
             .height(height - 40.0)
-            .show_axes([false, true])
+            .show_axes([true, true])
             .show_grid(true)
+            .include_x(range_start(history, hours))
+            .x_grid_spacer(time_grid_spacer)
+            .x_axis_formatter(move |mark, _range| format_axis_time(mark, hours))
+            .label_formatter(move |_name, point| format_hover(point, hours, unit, precision))
             .show(ui, |plot_ui| {
-                plot_ui.line(line);
+                for segment in segments {
+                    plot_segment(plot_ui, segment, title, color, 2.0);
+                }
             });
     }
 
-    fn render_mini_chart<F>(&self, ui: &mut egui::Ui, history: &[&WeatherData], title: &str, unit: &str, extractor: F, color: egui::Color32, height: f32)
-    where
-        F: Fn(&WeatherData) -> f64,
-    {
+    fn render_mini_chart(&self, ui: &mut egui::Ui, history: &[&WeatherData], series: ChartSeries, hours: u32, height: f32) {
         if history.is_empty() {
             return;
         }
 
-        let latest = history.last().map(|d| extractor(d) as f32).unwrap_or(0.0);
+        let units = &self.units;
+        let dimension = series.dimension();
+        let extractor = |d: &WeatherData| units.convert(dimension, series.value(d) as f32) as f64;
+        let (title, unit, color) = (series.short_label(), units.symbol(dimension), series.color());
+        let precision = units.precision(dimension);
+
+        let latest = history.last().map(|d| extractor(d) as f32).unwrap_or(f32::NAN);
 
         ui.horizontal(|ui| {
             ui.label(egui::RichText::new(title).size(12.0).strong().color(egui::Color32::from_rgb(15, 23, 42)));
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                ui.label(egui::RichText::new(format!("{:.1}{}", latest, unit)).size(11.0).strong().color(color));
+                ui.label(egui::RichText::new(units.format_value(dimension, latest)).size(11.0).strong().color(color));
             });
         });
 
-        let points: PlotPoints = history
-            .iter()
-            .enumerate()
-            .map(|(i, data)| [i as f64, extractor(data)])
-            .collect();
-
-        let line = Line::new(points)
-            .color(color)
-            .width(1.5);
+        let segments = split_at_gaps(history, extractor);
 
         Plot::new(format!("mini_plot_{}", title))
             .height(height - 25.0)
             .show_axes([false, false])
             .show_grid(false)
+            .include_x(range_start(history, hours))
+            .label_formatter(move |_name, point| format_hover(point, hours, unit, precision))
             .show(ui, |plot_ui| {
-                plot_ui.line(line);
+                for segment in segments {
+                    plot_segment(plot_ui, segment, title, color, 1.5);
+                }
             });
     }
 
+}
+
+impl Default for WeatherWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn timestamp_secs(data: &WeatherData) -> f64 {
+    data.timestamp.timestamp_millis() as f64 / 1000.0
+}
+
+// Left edge of the selected range, so missing data at the start shows as empty space.
+fn range_start(history: &[&WeatherData], hours: u32) -> f64 {
+    history.last().map(|data| timestamp_secs(data)).unwrap_or_default() - hours as f64 * 3600.0
+}
+
+// Breaks the series wherever the spacing between readings is well above the usual
+// sample interval, or a value is missing (NaN), so outages show up as holes instead of
+// straight interpolated lines.
+fn split_at_gaps<F>(history: &[&WeatherData], extractor: F) -> Vec<Vec<[f64; 2]>>
+where
+    F: Fn(&WeatherData) -> f64,
+{
+    let mut spacings: Vec<f64> = history.windows(2)
+        .map(|pair| timestamp_secs(pair[1]) - timestamp_secs(pair[0]))
+        .collect();
+    spacings.sort_by(|a, b| a.total_cmp(b));
+    let typical = spacings.get(spacings.len() / 2).copied().unwrap_or(0.0);
+    let max_gap = (typical * 3.0).max(1.0);
+
+    let mut segments: Vec<Vec<[f64; 2]>> = Vec::new();
+    let mut previous: Option<f64> = None;
+    for data in history {
+        let x = timestamp_secs(data);
+        let y = extractor(data);
+        if y.is_nan() {
+            previous = None;
+            continue;
+        }
+        if previous.is_none_or(|prev| x - prev > max_gap) {
+            segments.push(Vec::new());
+        }
+        if let Some(segment) = segments.last_mut() {
+            segment.push([x, y]);
+        }
+        previous = Some(x);
+    }
+    segments
+}
+
+fn plot_segment(plot_ui: &mut egui_plot::PlotUi, segment: Vec<[f64; 2]>, name: &str, color: egui::Color32, width: f32) {
+    // A lone reading between two gaps has no line to draw, so mark it instead.
+    if segment.len() == 1 {
+        plot_ui.points(Points::new(PlotPoints::from(segment)).name(name).color(color).radius(width + 1.0));
+    } else {
+        plot_ui.line(Line::new(PlotPoints::from(segment)).name(name).color(color).width(width));
+    }
+}
+
+fn format_hover(point: &PlotPoint, hours: u32, unit: &str, precision: usize) -> String {
+    let time = to_local(point.x)
+        .map(|time| time.format(if hours > 24 { "%a %d %b %H:%M:%S" } else { "%H:%M:%S" }).to_string())
+        .unwrap_or_default();
+    format!("{}\n{:.*} {}", time, precision, point.y, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn reading(secs: i64, temperature: f32) -> WeatherData {
+        WeatherData::new(temperature, 50.0, 1013.0, 2.0, 180.0, 0.0, 1.0, 100.0)
+            .with_timestamp(Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap())
+    }
+
+    fn segments(readings: &[WeatherData]) -> Vec<Vec<[f64; 2]>> {
+        let history: Vec<&WeatherData> = readings.iter().collect();
+        split_at_gaps(&history, |data| data.temperature as f64)
+    }
+
+    fn lengths(segments: &[Vec<[f64; 2]>]) -> Vec<usize> {
+        segments.iter().map(Vec::len).collect()
+    }
+
+    #[test]
+    fn regular_readings_make_one_line() {
+        let readings: Vec<WeatherData> = (0..10).map(|i| reading(i * 60, i as f32)).collect();
+        let segments = segments(&readings);
+        assert_eq!(lengths(&segments), [10]);
+        assert_eq!(segments[0][3], [1_700_000_180.0, 3.0]);
+        assert!(self::segments(&[]).is_empty());
+        assert_eq!(lengths(&self::segments(&[reading(0, 1.0)])), [1]);
+    }
+
+    #[test]
+    fn outages_and_missing_values_break_the_line() {
+        // Minute readings with a 10-minute outage, then one unmeasured value.
+        let mut readings: Vec<WeatherData> = (0..5).map(|i| reading(i * 60, 10.0)).collect();
+        readings.extend((15..20).map(|i| reading(i * 60, 11.0)));
+        readings[7].temperature = f32::NAN;
+        assert_eq!(lengths(&segments(&readings)), [5, 2, 2]);
+
+        // Up to three times the usual spacing still counts as connected.
+        let mut jittery: Vec<WeatherData> = (0..5).map(|i| reading(i * 60, 1.0)).collect();
+        jittery.extend([reading(420, 1.0), reading(601, 1.0), reading(661, 1.0)]);
+        assert_eq!(lengths(&segments(&jittery)), [6, 2]);
+    }
+
+    #[test]
+    fn a_lone_reading_between_gaps_is_its_own_segment() {
+        let mut readings: Vec<WeatherData> = (0..5).map(|i| reading(i * 60, 1.0)).collect();
+        readings.push(reading(1200, 2.0));
+        readings.extend((40..45).map(|i| reading(i * 60, 3.0)));
+        assert_eq!(lengths(&segments(&readings)), [5, 1, 5]);
+    }
+
+    #[test]
+    fn range_starts_hours_before_the_newest_reading() {
+        let readings = [reading(0, 1.0), reading(7200, 1.0)];
+        let history: Vec<&WeatherData> = readings.iter().collect();
+        assert_eq!(range_start(&history, 6), 1_700_007_200.0 - 6.0 * 3600.0);
+        assert_eq!(range_start(&[], 1), -3600.0);
+    }
+
+    #[test]
+    fn hover_shows_time_and_value_with_unit() {
+        let point = PlotPoint::new(1_700_000_000.0, 20.456);
+        let local = to_local(1_700_000_000.0).unwrap();
+        assert_eq!(format_hover(&point, 6, "°C", 1), format!("{}\n20.5 °C", local.format("%H:%M:%S")));
+        assert_eq!(format_hover(&point, 168, "inHg", 2), format!("{}\n20.46 inHg", local.format("%a %d %b %H:%M:%S")));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,346 @@
+use eframe::egui;
+use std::f32::consts::PI;
+use crate::data::WeatherData;
+use crate::history::WeatherHistory;
+use crate::units::{Dimension, UnitPreferences};
+
+// Readings below this speed count as calm and have no meaningful direction.
+const CALM_MS: f32 = 0.5;
+// Gusts are the strongest reading over this trailing window.
+const GUST_WINDOW_SECS: i64 = 600;
+// Upper edges of the speed bins in m/s; the last bin is open-ended.
+const SPEED_BINS: [f32; 4] = [2.0, 4.0, 6.0, 8.0];
+
+const COMPASS_POINTS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE",
+    "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RoseSectors {
+    Eight,
+    Sixteen,
+}
+
+impl RoseSectors {
+    fn count(&self) -> usize {
+        match self {
+            RoseSectors::Eight => 8,
+            RoseSectors::Sixteen => 16,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            RoseSectors::Eight => "8 sectors",
+            RoseSectors::Sixteen => "16 sectors",
+        }
+    }
+}
+
+// Reading counts per direction sector and speed bin.
+struct WindRose {
+    counts: Vec<[usize; SPEED_BINS.len() + 1]>,
+    calm: usize,
+    total: usize,
+}
+
+impl WindRose {
+    fn from_history(history: &[&WeatherData], sectors: usize) -> Self {
+        let mut rose = Self { counts: vec![[0; SPEED_BINS.len() + 1]; sectors], calm: 0, total: 0 };
+        let width = 360.0 / sectors as f32;
+
+        for data in history {
+            let calm = data.wind_speed < CALM_MS;
+            // A reading without a speed, or moving air without a direction, cannot be placed.
+            if data.wind_speed.is_nan() || (!calm && data.wind_direction.is_nan()) {
+                continue;
+            }
+            rose.total += 1;
+            if calm {
+                rose.calm += 1;
+                continue;
+            }
+            // Sectors are centred on their compass point, so north spans -width/2..width/2.
+            let sector = ((data.wind_direction.rem_euclid(360.0) + width / 2.0) / width) as usize % sectors;
+            let bin = SPEED_BINS.iter().position(|&edge| data.wind_speed < edge).unwrap_or(SPEED_BINS.len());
+            rose.counts[sector][bin] += 1;
+        }
+        rose
+    }
+
+    fn sector_fraction(&self, sector: usize, bins: usize) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.counts[sector][..bins].iter().sum::<usize>() as f32 / self.total as f32
+    }
+
+    fn max_sector_fraction(&self) -> f32 {
+        (0..self.counts.len())
+            .map(|sector| self.sector_fraction(sector, SPEED_BINS.len() + 1))
+            .fold(0.0, f32::max)
+    }
+}
+
+pub struct WindWidget {
+    sectors: RoseSectors,
+    units: UnitPreferences,
+}
+
+impl WindWidget {
+    pub fn new() -> Self {
+        Self {
+            sectors: RoseSectors::Sixteen,
+            units: UnitPreferences::default(),
+        }
+    }
+
+    /// Gusts come from the raw readings in `recent`; the rose from `history`, which holds
+    /// rollup means rather than readings when `history_is_raw` is false.
+    pub fn render(&mut self, ui: &mut egui::Ui, current: Option<&WeatherData>, recent: &WeatherHistory, history: &[&WeatherData], history_is_raw: bool, units: &UnitPreferences) {
+        self.units = *units;
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Wind Rose:").size(14.0).color(egui::Color32::from_rgb(71, 85, 105)));
+            ui.add_space(8.0);
+            for sectors in [RoseSectors::Eight, RoseSectors::Sixteen] {
+                if ui.add(egui::Button::new(sectors.label())
+                    .fill(if self.sectors == sectors { egui::Color32::from_rgb(245, 158, 11) } else { egui::Color32::from_rgb(241, 245, 249) })
+                    .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)))
+                    .rounding(4.0)
+                ).clicked() {
+                    self.sectors = sectors;
+                }
+            }
+        });
+        ui.add_space(8.0);
+
+        let Some(current) = current else {
+            ui.centered_and_justified(|ui| {
+                ui.label(egui::RichText::new("No wind data available").color(egui::Color32::from_rgb(100, 116, 139)));
+            });
+            return;
+        };
+
+        let gust = gust_in_window(current, recent);
+        let rose = WindRose::from_history(history, self.sectors.count());
+
+        ui.columns(2, |columns| {
+            columns[0].group(|ui| {
+                ui.label(egui::RichText::new("Live Wind").size(14.0).strong().color(egui::Color32::from_rgb(15, 23, 42)));
+                self.render_compass(ui, current, gust);
+            });
+            columns[1].group(|ui| {
+                ui.label(egui::RichText::new("Wind Rose").size(14.0).strong().color(egui::Color32::from_rgb(15, 23, 42)));
+                if !history_is_raw {
+                    ui.label(egui::RichText::new("From averaged intervals; brief gusts are smoothed out").size(10.0).color(egui::Color32::from_rgb(100, 116, 139)));
+                }
+                self.render_rose(ui, &rose);
+            });
+        });
+    }
+
+    fn render_compass(&self, ui: &mut egui::Ui, current: &WeatherData, gust: Option<&WeatherData>) {
+        let size = ui.available_width().min(ui.available_height() - 40.0).max(120.0);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), size), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let center = rect.center();
+        let radius = size / 2.0 - 24.0;
+        let slate = egui::Color32::from_rgb(100, 116, 139);
+
+        painter.circle(center, radius, egui::Color32::WHITE, egui::Stroke::new(2.0, egui::Color32::from_rgb(226, 232, 240)));
+        for (i, point) in COMPASS_POINTS.iter().enumerate() {
+            let angle = i as f32 * 22.5;
+            let inner = if i % 4 == 0 { radius - 12.0 } else { radius - 6.0 };
+            painter.line_segment([polar(center, inner, angle), polar(center, radius, angle)], egui::Stroke::new(1.0, slate));
+            if i % 4 == 0 {
+                painter.text(polar(center, radius + 12.0, angle), egui::Align2::CENTER_CENTER, *point, egui::FontId::proportional(13.0), egui::Color32::from_rgb(15, 23, 42));
+            }
+        }
+
+        // Gust marker on the rim, pointing where the strongest recent wind came from.
+        if let Some(gust) = gust.filter(|gust| !gust.wind_direction.is_nan()) {
+            let tip = polar(center, radius - 2.0, gust.wind_direction);
+            let left = polar(center, radius + 8.0, gust.wind_direction - 5.0);
+            let right = polar(center, radius + 8.0, gust.wind_direction + 5.0);
+            painter.add(egui::Shape::convex_polygon(vec![tip, left, right], egui::Color32::from_rgb(239, 68, 68), egui::Stroke::NONE));
+        }
+
+        // The needle points into the wind, i.e. the direction it blows from.
+        let needle_color = egui::Color32::from_rgb(245, 158, 11);
+        if current.wind_speed >= CALM_MS && !current.wind_direction.is_nan() {
+            let direction = current.wind_direction;
+            let tip = polar(center, radius - 16.0, direction);
+            let tail = polar(center, radius - 16.0, direction + 180.0);
+            painter.line_segment([tail, tip], egui::Stroke::new(3.0, needle_color));
+            let left = polar(center, radius - 34.0, direction - 8.0);
+            let right = polar(center, radius - 34.0, direction + 8.0);
+            painter.add(egui::Shape::convex_polygon(vec![tip, left, right], needle_color, egui::Stroke::NONE));
+        }
+        painter.circle_filled(center, 5.0, needle_color);
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(self.units.format(Dimension::Speed, current.wind_speed)).size(16.0).strong().color(needle_color));
+            if current.wind_speed < CALM_MS {
+                ui.label(egui::RichText::new("calm").size(12.0).color(slate));
+            } else if !current.wind_direction.is_nan() {
+                ui.label(egui::RichText::new(format!("from {} ({:.0}°)", compass_point(current.wind_direction), current.wind_direction)).size(12.0).color(slate));
+            }
+            if let Some(gust) = gust {
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.label(egui::RichText::new(self.gust_text(gust)).size(12.0).strong().color(egui::Color32::from_rgb(239, 68, 68)))
+                        .on_hover_text(format!("Strongest reading in the last 10 minutes, at {}", gust.timestamp.with_timezone(&chrono::Local).format("%H:%M:%S")));
+                });
+            }
+        });
+    }
+
+    // The compass point is left out when the gust's direction wasn't measured.
+    fn gust_text(&self, gust: &WeatherData) -> String {
+        let speed = self.units.format(Dimension::Speed, gust.wind_speed);
+        if gust.wind_direction.is_nan() {
+            format!("Gust {}", speed)
+        } else {
+            format!("Gust {} {}", speed, compass_point(gust.wind_direction))
+        }
+    }
+
+    fn render_rose(&self, ui: &mut egui::Ui, rose: &WindRose) {
+        let size = ui.available_width().min(ui.available_height() - 60.0).max(120.0);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), size), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let center = rect.center();
+        let radius = size / 2.0 - 24.0;
+        let slate = egui::Color32::from_rgb(100, 116, 139);
+        let sectors = rose.counts.len();
+        let width = 360.0 / sectors as f32;
+
+        // Rings at even percentages of the busiest sector.
+        let max_fraction = rose.max_sector_fraction().max(0.01);
+        for ring in 1..=4 {
+            let ring_radius = radius * ring as f32 / 4.0;
+            painter.circle_stroke(center, ring_radius, egui::Stroke::new(1.0, egui::Color32::from_rgb(226, 232, 240)));
+            painter.text(polar(center, ring_radius, 45.0), egui::Align2::LEFT_BOTTOM, format!("{:.0}%", max_fraction * 100.0 * ring as f32 / 4.0), egui::FontId::proportional(9.0), slate);
+        }
+        for (i, point) in COMPASS_POINTS.iter().enumerate().step_by(4) {
+            painter.text(polar(center, radius + 12.0, i as f32 * 22.5), egui::Align2::CENTER_CENTER, *point, egui::FontId::proportional(13.0), egui::Color32::from_rgb(15, 23, 42));
+        }
+
+        // Stacked wedges: paint the cumulative outer bins first and the slower bins over them.
+        for bin in (1..=SPEED_BINS.len() + 1).rev() {
+            let color = speed_bin_color(bin - 1);
+            for sector in 0..sectors {
+                let fraction = rose.sector_fraction(sector, bin);
+                if fraction <= 0.0 {
+                    continue;
+                }
+                let wedge_radius = radius * fraction / max_fraction;
+                let start = sector as f32 * width - width / 2.0;
+                let mut points = vec![center];
+                for step in 0..=8 {
+                    points.push(polar(center, wedge_radius, start + width * step as f32 / 8.0));
+                }
+                painter.add(egui::Shape::convex_polygon(points, color, egui::Stroke::new(0.5, egui::Color32::WHITE)));
+            }
+        }
+
+        // Bins stay in m/s; only their edges are shown in the display unit.
+        let edge = |i: usize| self.units.convert(Dimension::Speed, SPEED_BINS[i]);
+        ui.horizontal_wrapped(|ui| {
+            for bin in 0..=SPEED_BINS.len() {
+                let label = match bin {
+                    0 => format!("<{:.0}", edge(0)),
+                    b if b == SPEED_BINS.len() => format!("≥{:.0}", edge(b - 1)),
+                    b => format!("{:.0}-{:.0}", edge(b - 1), edge(b)),
+                };
+                ui.label(egui::RichText::new("■").color(speed_bin_color(bin)));
+                ui.label(egui::RichText::new(format!("{} {}", label, self.units.symbol(Dimension::Speed))).size(11.0).color(slate));
+            }
+        });
+        let calm = if rose.total > 0 { rose.calm as f32 / rose.total as f32 * 100.0 } else { 0.0 };
+        ui.label(egui::RichText::new(format!("{} readings, calm {:.0}%", rose.total, calm)).size(11.0).color(slate));
+    }
+}
+
+impl Default for WindWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Strongest raw reading within the gust window up to the current one. Chart series may be
+// rollup means, which would average the gust away.
+fn gust_in_window<'a>(current: &WeatherData, recent: &'a WeatherHistory) -> Option<&'a WeatherData> {
+    let cutoff = current.timestamp - chrono::Duration::seconds(GUST_WINDOW_SECS);
+    recent.window(cutoff - chrono::Duration::milliseconds(1), current.timestamp)
+        .filter(|data| !data.wind_speed.is_nan())
+        .max_by(|a, b| a.wind_speed.total_cmp(&b.wind_speed))
+}
+
+fn compass_point(direction: f32) -> &'static str {
+    COMPASS_POINTS[((direction.rem_euclid(360.0) + 11.25) / 22.5) as usize % 16]
+}
+
+// Screen position at `radius` from `center` for a compass bearing in degrees (0 = up, clockwise).
+fn polar(center: egui::Pos2, radius: f32, bearing: f32) -> egui::Pos2 {
+    let angle = bearing * PI / 180.0;
+    egui::pos2(center.x + radius * angle.sin(), center.y - radius * angle.cos())
+}
+
+fn speed_bin_color(bin: usize) -> egui::Color32 {
+    match bin {
+        0 => egui::Color32::from_rgb(186, 230, 253),
+        1 => egui::Color32::from_rgb(56, 189, 248),
+        2 => egui::Color32::from_rgb(34, 197, 94),
+        3 => egui::Color32::from_rgb(245, 158, 11),
+        _ => egui::Color32::from_rgb(239, 68, 68),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn wind(secs: i64, speed: f32, direction: f32) -> WeatherData {
+        WeatherData::new(15.0, 50.0, 1013.0, speed, direction, 0.0, 1.0, 100.0)
+            .with_timestamp(Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap())
+    }
+
+    #[test]
+    fn gust_is_the_raw_maximum_in_the_window() {
+        let mut recent = WeatherHistory::new(100);
+        // A strong gust just outside the window, then a brief one inside it.
+        recent.extend([wind(0, 20.0, 0.0), wind(400, 3.0, 0.0), wind(450, 12.0, 0.0), wind(500, f32::NAN, 0.0), wind(700, 4.0, 0.0)]);
+        let current = recent.latest().unwrap().clone();
+        let gust = gust_in_window(&current, &recent).unwrap();
+        assert_eq!(gust.wind_speed, 12.0);
+
+        // The reading exactly at the cutoff still counts; one second later it has aged out.
+        recent.push(wind(1050, 1.0, 0.0));
+        let current = recent.latest().unwrap().clone();
+        assert_eq!(gust_in_window(&current, &recent).unwrap().wind_speed, 12.0);
+        recent.push(wind(1051, 1.0, 0.0));
+        let current = recent.latest().unwrap().clone();
+        assert_eq!(gust_in_window(&current, &recent).unwrap().wind_speed, 4.0);
+    }
+
+    #[test]
+    fn gust_without_a_direction_has_no_compass_point() {
+        let widget = WindWidget::new();
+        assert_eq!(widget.gust_text(&wind(0, 12.0, 315.0)), "Gust 12.0 m/s NW");
+        assert_eq!(widget.gust_text(&wind(0, 12.0, f32::NAN)), "Gust 12.0 m/s");
+    }
+
+    #[test]
+    fn rose_bins_by_sector_and_speed() {
+        let readings = [wind(0, 0.2, 90.0), wind(1, 3.0, 350.0), wind(2, 9.0, 10.0), wind(3, 5.0, 90.0), wind(4, 5.0, f32::NAN), wind(5, f32::NAN, 90.0)];
+        let history: Vec<&WeatherData> = readings.iter().collect();
+        let rose = WindRose::from_history(&history, 8);
+        // The unplaceable readings are left out entirely.
+        assert_eq!((rose.total, rose.calm), (4, 1));
+        assert_eq!(rose.counts[0], [0, 1, 0, 0, 1]);
+        assert_eq!(rose.counts[2], [0, 0, 1, 0, 0]);
+        assert_eq!(rose.sector_fraction(0, SPEED_BINS.len() + 1), 0.5);
+    }
+}
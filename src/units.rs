@@ -0,0 +1,368 @@
+use serde::{Deserialize, Serialize};
+
+pub fn fahrenheit_to_celsius(fahrenheit: f32) -> f32 {
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+pub fn inhg_to_hpa(inhg: f32) -> f32 {
+    inhg * 33.863_89
+}
+
+pub fn mph_to_ms(mph: f32) -> f32 {
+    mph * 0.447_04
+}
+
+pub fn inches_to_mm(inches: f32) -> f32 {
+    inches * 25.4
+}
+
+pub fn celsius_to_fahrenheit(celsius: f32) -> f32 {
+    celsius * 9.0 / 5.0 + 32.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TemperatureUnit {
+    #[serde(rename = "°C")]
+    Celsius,
+    #[serde(rename = "°F")]
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub const ALL: [TemperatureUnit; 2] = [TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeedUnit {
+    #[serde(rename = "m/s")]
+    MetersPerSecond,
+    #[serde(rename = "km/h")]
+    KilometersPerHour,
+    #[serde(rename = "mph")]
+    MilesPerHour,
+    #[serde(rename = "kn")]
+    Knots,
+}
+
+impl SpeedUnit {
+    pub const ALL: [SpeedUnit; 4] = [SpeedUnit::MetersPerSecond, SpeedUnit::KilometersPerHour, SpeedUnit::MilesPerHour, SpeedUnit::Knots];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            SpeedUnit::MetersPerSecond => "m/s",
+            SpeedUnit::KilometersPerHour => "km/h",
+            SpeedUnit::MilesPerHour => "mph",
+            SpeedUnit::Knots => "kn",
+        }
+    }
+
+    // Metres per second in one of this unit.
+    fn factor(&self) -> f32 {
+        match self {
+            SpeedUnit::MetersPerSecond => 1.0,
+            SpeedUnit::KilometersPerHour => 1.0 / 3.6,
+            SpeedUnit::MilesPerHour => 0.447_04,
+            SpeedUnit::Knots => 0.514_444,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PressureUnit {
+    #[serde(rename = "hPa")]
+    Hectopascal,
+    #[serde(rename = "inHg")]
+    InchesOfMercury,
+    #[serde(rename = "mmHg")]
+    MillimetersOfMercury,
+}
+
+impl PressureUnit {
+    pub const ALL: [PressureUnit; 3] = [PressureUnit::Hectopascal, PressureUnit::InchesOfMercury, PressureUnit::MillimetersOfMercury];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PressureUnit::Hectopascal => "hPa",
+            PressureUnit::InchesOfMercury => "inHg",
+            PressureUnit::MillimetersOfMercury => "mmHg",
+        }
+    }
+
+    // Hectopascals in one of this unit.
+    fn factor(&self) -> f32 {
+        match self {
+            PressureUnit::Hectopascal => 1.0,
+            PressureUnit::InchesOfMercury => 33.863_89,
+            PressureUnit::MillimetersOfMercury => 1.333_224,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RainUnit {
+    #[serde(rename = "mm")]
+    Millimeters,
+    #[serde(rename = "in")]
+    Inches,
+}
+
+impl RainUnit {
+    pub const ALL: [RainUnit; 2] = [RainUnit::Millimeters, RainUnit::Inches];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            RainUnit::Millimeters => "mm",
+            RainUnit::Inches => "in",
+        }
+    }
+
+    // Millimetres in one of this unit.
+    fn factor(&self) -> f32 {
+        match self {
+            RainUnit::Millimeters => 1.0,
+            RainUnit::Inches => 25.4,
+        }
+    }
+}
+
+/// What a value measures, which decides the units it can be shown in. `Fixed` values
+/// are always shown in the given unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Temperature,
+    Speed,
+    Pressure,
+    Rain,
+    RainRate,
+    Fixed(&'static str),
+}
+
+/// Display units per quantity. Readings are stored and processed in SI (°C, m/s, hPa,
+/// mm) and only converted when shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnitPreferences {
+    pub temperature: TemperatureUnit,
+    pub wind_speed: SpeedUnit,
+    pub pressure: PressureUnit,
+    pub rain: RainUnit,
+}
+
+impl Default for UnitPreferences {
+    fn default() -> Self {
+        Self::metric()
+    }
+}
+
+impl UnitPreferences {
+    pub fn metric() -> Self {
+        Self {
+            temperature: TemperatureUnit::Celsius,
+            wind_speed: SpeedUnit::MetersPerSecond,
+            pressure: PressureUnit::Hectopascal,
+            rain: RainUnit::Millimeters,
+        }
+    }
+
+    pub fn imperial() -> Self {
+        Self {
+            temperature: TemperatureUnit::Fahrenheit,
+            wind_speed: SpeedUnit::MilesPerHour,
+            pressure: PressureUnit::InchesOfMercury,
+            rain: RainUnit::Inches,
+        }
+    }
+
+    pub fn symbol(&self, dimension: Dimension) -> &'static str {
+        match dimension {
+            Dimension::Temperature => self.temperature.symbol(),
+            Dimension::Speed => self.wind_speed.symbol(),
+            Dimension::Pressure => self.pressure.symbol(),
+            Dimension::Rain => self.rain.symbol(),
+            Dimension::RainRate => match self.rain {
+                RainUnit::Millimeters => "mm/h",
+                RainUnit::Inches => "in/h",
+            },
+            Dimension::Fixed(symbol) => symbol,
+        }
+    }
+
+    /// Converts an SI value to the preferred unit.
+    pub fn convert(&self, dimension: Dimension, value: f32) -> f32 {
+        match dimension {
+            Dimension::Temperature => match self.temperature {
+                TemperatureUnit::Celsius => value,
+                TemperatureUnit::Fahrenheit => celsius_to_fahrenheit(value),
+            },
+            Dimension::Speed => value / self.wind_speed.factor(),
+            Dimension::Pressure => value / self.pressure.factor(),
+            Dimension::Rain | Dimension::RainRate => value / self.rain.factor(),
+            Dimension::Fixed(_) => value,
+        }
+    }
+
+    /// Converts a difference, such as a change within a window, to the preferred unit.
+    pub fn convert_delta(&self, dimension: Dimension, delta: f32) -> f32 {
+        match dimension {
+            Dimension::Temperature => self.convert(dimension, delta) - self.convert(dimension, 0.0),
+            _ => self.convert(dimension, delta),
+        }
+    }
+
+    /// Digits after the decimal point that keep a useful resolution in the preferred unit.
+    pub fn precision(&self, dimension: Dimension) -> usize {
+        match dimension {
+            Dimension::Pressure if self.pressure == PressureUnit::InchesOfMercury => 2,
+            Dimension::Rain | Dimension::RainRate if self.rain == RainUnit::Inches => 2,
+            _ => 1,
+        }
+    }
+
+    /// `value` (SI) converted and formatted with its unit, e.g. `68.0°F` or `29.92 inHg`.
+    pub fn format(&self, dimension: Dimension, value: f32) -> String {
+        self.format_value(dimension, self.convert(dimension, value))
+    }
+
+    /// Formats a value that is already in the preferred unit; NaN (not measured) is `—`.
+    pub fn format_value(&self, dimension: Dimension, value: f32) -> String {
+        if value.is_nan() {
+            return "—".to_string();
+        }
+        let symbol = self.symbol(dimension);
+        let separator = if symbol.starts_with('°') || symbol == "%" || symbol.is_empty() { "" } else { " " };
+        format!("{:.*}{}{}", self.precision(dimension), value, separator, symbol)
+    }
+}
+
+/// Converts `value` given in the unit named by `symbol` to SI, or `None` if `symbol` is
+/// not a unit of `dimension`. `delta` values (changes, margins) skip the °F offset.
+pub fn to_si(dimension: Dimension, symbol: &str, value: f32, delta: bool) -> Option<f32> {
+    match dimension {
+        Dimension::Temperature => match TemperatureUnit::ALL.into_iter().find(|unit| unit.symbol() == symbol)? {
+            TemperatureUnit::Celsius => Some(value),
+            TemperatureUnit::Fahrenheit if delta => Some(value * 5.0 / 9.0),
+            TemperatureUnit::Fahrenheit => Some(fahrenheit_to_celsius(value)),
+        },
+        Dimension::Speed => SpeedUnit::ALL.into_iter().find(|unit| unit.symbol() == symbol).map(|unit| value * unit.factor()),
+        Dimension::Pressure => PressureUnit::ALL.into_iter().find(|unit| unit.symbol() == symbol).map(|unit| value * unit.factor()),
+        Dimension::Rain => RainUnit::ALL.into_iter().find(|unit| unit.symbol() == symbol).map(|unit| value * unit.factor()),
+        Dimension::RainRate => RainUnit::ALL.into_iter()
+            .find(|unit| format!("{}/h", unit.symbol()) == symbol)
+            .map(|unit| value * unit.factor()),
+        Dimension::Fixed(unit) => (unit == symbol).then_some(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!((actual - expected).abs() <= tolerance, "expected {} ± {}, got {}", expected, tolerance, actual);
+    }
+
+    fn with(temperature: TemperatureUnit, wind_speed: SpeedUnit, pressure: PressureUnit, rain: RainUnit) -> UnitPreferences {
+        UnitPreferences { temperature, wind_speed, pressure, rain }
+    }
+
+    #[test]
+    fn temperature_matches_reference_values() {
+        let imperial = UnitPreferences::imperial();
+        assert_close(imperial.convert(Dimension::Temperature, 0.0), 32.0, 1e-4);
+        assert_close(imperial.convert(Dimension::Temperature, 20.0), 68.0, 1e-4);
+        assert_close(imperial.convert(Dimension::Temperature, 100.0), 212.0, 1e-4);
+        assert_close(imperial.convert(Dimension::Temperature, -40.0), -40.0, 1e-4);
+        assert_close(fahrenheit_to_celsius(98.6), 37.0, 1e-4);
+        assert_close(UnitPreferences::metric().convert(Dimension::Temperature, 21.5), 21.5, 0.0);
+    }
+
+    #[test]
+    fn temperature_differences_skip_the_offset() {
+        let imperial = UnitPreferences::imperial();
+        assert_close(imperial.convert_delta(Dimension::Temperature, 5.0), 9.0, 1e-4);
+        assert_close(to_si(Dimension::Temperature, "°F", 9.0, true).unwrap(), 5.0, 1e-4);
+        assert_close(to_si(Dimension::Temperature, "°F", 50.0, false).unwrap(), 10.0, 1e-4);
+        // Other quantities have no offset, so a change converts like a value.
+        assert_close(imperial.convert_delta(Dimension::Pressure, 10.0), imperial.convert(Dimension::Pressure, 10.0), 0.0);
+    }
+
+    #[test]
+    fn speed_matches_reference_values() {
+        let metric = UnitPreferences::metric();
+        let speed = |unit| with(TemperatureUnit::Celsius, unit, PressureUnit::Hectopascal, RainUnit::Millimeters);
+        assert_close(metric.convert(Dimension::Speed, 10.0), 10.0, 0.0);
+        assert_close(speed(SpeedUnit::KilometersPerHour).convert(Dimension::Speed, 10.0), 36.0, 1e-4);
+        assert_close(speed(SpeedUnit::MilesPerHour).convert(Dimension::Speed, 10.0), 22.369, 1e-3);
+        assert_close(speed(SpeedUnit::Knots).convert(Dimension::Speed, 10.0), 19.438, 1e-3);
+        assert_close(mph_to_ms(60.0), 26.822, 1e-3);
+        assert_close(to_si(Dimension::Speed, "kn", 1.0, false).unwrap(), 0.514_444, 1e-6);
+    }
+
+    #[test]
+    fn pressure_and_rain_match_reference_values() {
+        let pressure = |unit| with(TemperatureUnit::Celsius, SpeedUnit::MetersPerSecond, unit, RainUnit::Millimeters);
+        assert_close(pressure(PressureUnit::InchesOfMercury).convert(Dimension::Pressure, 1013.25), 29.921, 1e-3);
+        assert_close(pressure(PressureUnit::MillimetersOfMercury).convert(Dimension::Pressure, 1013.25), 760.0, 0.01);
+        assert_close(inhg_to_hpa(29.92), 1013.21, 0.01);
+
+        let imperial = UnitPreferences::imperial();
+        assert_close(imperial.convert(Dimension::Rain, 25.4), 1.0, 1e-6);
+        assert_close(imperial.convert(Dimension::RainRate, 12.7), 0.5, 1e-6);
+        assert_close(inches_to_mm(2.0), 50.8, 1e-4);
+        assert_close(to_si(Dimension::RainRate, "in/h", 0.5, false).unwrap(), 12.7, 1e-4);
+    }
+
+    #[test]
+    fn every_unit_round_trips_through_si() {
+        for temperature in TemperatureUnit::ALL {
+            for wind_speed in SpeedUnit::ALL {
+                for pressure in PressureUnit::ALL {
+                    for rain in RainUnit::ALL {
+                        let units = with(temperature, wind_speed, pressure, rain);
+                        for (dimension, value) in [
+                            (Dimension::Temperature, -12.3),
+                            (Dimension::Temperature, 31.7),
+                            (Dimension::Speed, 17.4),
+                            (Dimension::Pressure, 987.6),
+                            (Dimension::Rain, 42.2),
+                            (Dimension::RainRate, 3.3),
+                            (Dimension::Fixed("%"), 55.0),
+                        ] {
+                            let shown = units.convert(dimension, value);
+                            let back = to_si(dimension, units.symbol(dimension), shown, false).unwrap();
+                            assert_close(back, value, value.abs() * 1e-5);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_symbols_are_rejected() {
+        assert_eq!(to_si(Dimension::Temperature, "K", 280.0, false), None);
+        assert_eq!(to_si(Dimension::Speed, "hPa", 1.0, false), None);
+        assert_eq!(to_si(Dimension::Rain, "mm/h", 1.0, false), None);
+        assert_eq!(to_si(Dimension::RainRate, "mm", 1.0, false), None);
+        assert_eq!(to_si(Dimension::Fixed("%"), "g/m³", 1.0, false), None);
+    }
+
+    #[test]
+    fn formats_with_unit_and_precision() {
+        let imperial = UnitPreferences::imperial();
+        assert_eq!(imperial.format(Dimension::Temperature, 20.0), "68.0°F");
+        assert_eq!(imperial.format(Dimension::Pressure, 1013.25), "29.92 inHg");
+        assert_eq!(imperial.format(Dimension::RainRate, 12.7), "0.50 in/h");
+        assert_eq!(imperial.format(Dimension::Speed, f32::NAN), "—");
+        let metric = UnitPreferences::metric();
+        assert_eq!(metric.format(Dimension::Speed, 3.25), "3.2 m/s");
+        assert_eq!(metric.format(Dimension::Fixed("%"), 55.0), "55.0%");
+    }
+}